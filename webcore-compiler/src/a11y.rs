@@ -0,0 +1,332 @@
+//! Accessibility lint over the element tree of every layout, page and component
+//!
+//! Heading order is checked per generated page: the layouts around it and
+//! the views of the components it uses count, in the order they render.
+//!
+//! Each finding carries the id of the rule that reported it; `[a11y]` in
+//! webc.toml sets a rule to "error", "warning" or "off":
+//!
+//! ```toml
+//! [a11y]
+//! img-alt = "error"
+//! heading-order = "off"
+//! ```
+
+use crate::ast::*;
+use crate::checker::{Diagnostic, Severity};
+use crate::ir;
+use crate::project;
+use std::collections::{HashMap, HashSet};
+
+pub const RULES: &[&str] = &[
+    "img-alt",
+    "accessible-name",
+    "heading-order",
+    "html-lang",
+    "tabindex",
+    "href-hash",
+    "form-label",
+];
+
+// Attributes that give any element an accessible name
+const NAME_ATTRIBUTES: &[&str] = &["aria-label", "aria-labelledby", "title"];
+// Inputs that are labelled by their value or need no label
+const UNLABELLED_INPUTS: &[&str] = &["hidden", "submit", "reset", "button", "image"];
+
+/// Severity of each rule; every rule is a warning unless webc.toml says otherwise
+#[derive(Debug, Clone, Default)]
+pub struct Rules {
+    levels: HashMap<String, Option<Severity>>,
+}
+
+impl Rules {
+    /// Reads the `[a11y]` table of webc.toml
+    pub fn from_config(table: &HashMap<String, String>) -> Result<Self, String> {
+        let mut levels = HashMap::new();
+        for (rule, level) in table {
+            if !RULES.contains(&rule.as_str()) {
+                return Err(format!("unknown a11y rule '{}' in webc.toml (expected {})", rule, RULES.join(", ")));
+            }
+            let level = match level.as_str() {
+                "error" => Some(Severity::Error),
+                "warning" => Some(Severity::Warning),
+                "off" => None,
+                other => return Err(format!("a11y rule '{}' has level '{}', expected error, warning or off", rule, other)),
+            };
+            levels.insert(rule.clone(), level);
+        }
+        Ok(Self { levels })
+    }
+
+    fn level(&self, rule: &str) -> Option<Severity> {
+        self.levels.get(rule).copied().unwrap_or(Some(Severity::Warning))
+    }
+}
+
+/// Lints every view of the project; `lang` is the page language from webc.toml
+/// and `lang_span` where it is (or would be) set there
+pub fn lint(document: &WebCoreDocument, lang: Option<&str>, lang_span: Span, rules: &Rules) -> Vec<Diagnostic> {
+    let mut lint = Lint { rules, file: "", labelled: HashSet::new(), last_heading: 0, diagnostics: Vec::new() };
+    if lang.is_none_or(|l| l.trim().is_empty()) {
+        lint.file = "webc.toml";
+        lint.report("html-lang", lang_span, "pages have no language, set `lang` in the [app] section of webc.toml".to_string());
+    }
+
+    let mut views: Vec<(&str, &[Element])> = Vec::new();
+    views.extend(document.layouts.values().map(|l| (l.file.as_str(), l.content.as_slice())));
+    views.extend(document.pages.values().map(|p| (p.file.as_str(), p.content.as_slice())));
+    views.extend(document.components.values().map(|c| (c.file.as_str(), c.view.as_slice())));
+    for (file, view) in views {
+        lint.file = file;
+        lint.labelled.clear();
+        collect_label_targets(view, &mut lint.labelled);
+        lint.walk(view, false);
+    }
+
+    // Components named `*Page` are rendered as pages of their own
+    let mut pages: Vec<Page> = document.pages.values().cloned().collect();
+    pages.extend(document.components.values()
+        .filter(|c| c.name.ends_with("Page") && !document.pages.contains_key(&c.name))
+        .map(project::component_page));
+    for page in &pages {
+        // A page without a layout fails the build with its own error
+        let layouts = ir::resolve_layout_chain(document, page).unwrap_or_default();
+        lint.last_heading = 0;
+        let content = Filler { elements: &page.content, file: &page.file, outer: None };
+        lint.nest(document, &layouts, &content);
+    }
+
+    let mut diagnostics = lint.diagnostics;
+    diagnostics.sort_by(|a, b| (&a.file, a.span.start).cmp(&(&b.file, b.span.start)));
+    // A heading in a layout or component is seen once per page that renders it
+    diagnostics.dedup_by(|a, b| a.file == b.file && a.span == b.span && a.message == b.message);
+    diagnostics
+}
+
+// What a `slot` renders: the content given to the layout or component, and
+// what fills the slots inside that content
+struct Filler<'a, 'f> {
+    elements: &'a [Element],
+    file: &'a str,
+    outer: Option<&'f Filler<'a, 'f>>,
+}
+
+struct Lint<'a> {
+    rules: &'a Rules,
+    file: &'a str,
+    // `label for="..."` targets of the current view
+    labelled: HashSet<String>,
+    // Level of the previous heading on the current page, 0 before the first
+    last_heading: usize,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl<'a> Lint<'a> {
+    fn report(&mut self, rule: &'static str, span: Span, message: String) {
+        let diagnostic = match self.rules.level(rule) {
+            Some(Severity::Error) => Diagnostic::error(self.file, span, message),
+            Some(Severity::Warning) => Diagnostic::warning(self.file, span, message),
+            None => return,
+        };
+        self.diagnostics.push(diagnostic.with_rule(rule));
+    }
+
+    fn walk(&mut self, elements: &[Element], in_label: bool) {
+        for element in elements {
+            match element {
+                Element::Tag { name, attributes, content, span } => {
+                    self.check_tag(name, attributes, content, *span, in_label);
+                    self.walk(content, in_label || name == "label");
+                }
+                Element::Component { content, .. } | Element::For { content, .. } => self.walk(content, in_label),
+                Element::If { content, else_content, .. } => {
+                    self.walk(content, in_label);
+                    self.walk(else_content, in_label);
+                }
+                Element::Text { .. } | Element::Slot { .. } | Element::Interpolation { .. } | Element::Raw(_) => {}
+            }
+        }
+    }
+
+    fn check_tag(&mut self, name: &str, attributes: &[Attribute], content: &[Element], span: Span, in_label: bool) {
+        let has = |attr: &str| attributes.iter().any(|a| a.name == attr);
+        let string = |attr: &str| attributes.iter().find(|a| a.name == attr).and_then(|a| match &a.value {
+            AttributeValue::String(value) => Some(value.as_str()),
+            _ => None,
+        });
+        let named = NAME_ATTRIBUTES.iter().any(|a| has(a));
+
+        match name {
+            "img" if !has("alt") => self.report("img-alt", span,
+                "image has no `alt` text, describe it or use alt=\"\" if it is decorative".to_string()),
+            "button" | "a" | "link" if !named && !has_text(content) => self.report("accessible-name", span,
+                format!("{} has no accessible name, give it text or an `aria-label`", name)),
+            "input" | "select" | "textarea" if !named && !in_label => {
+                let is_labelled = string("id").is_some_and(|id| self.labelled.contains(id));
+                let exempt = name == "input" && string("type").is_some_and(|t| UNLABELLED_INPUTS.contains(&t));
+                if !is_labelled && !exempt {
+                    self.report("form-label", span, format!(
+                        "{} has no label, wrap it in a `label`, point a `label for` at its id or give it an `aria-label`", name));
+                }
+            }
+            _ => {}
+        }
+
+        if let Some(tabindex) = string("tabindex").and_then(|t| t.trim().parse::<i64>().ok()).filter(|t| *t > 0) {
+            self.report("tabindex", span, format!("tabindex=\"{}\" changes the tab order, use 0 or -1", tabindex));
+        }
+        let is_link = name == "a" || name == "link";
+        let target = string("href").or(string("to"));
+        if is_link && (target == Some("#") || (name == "link" && !has("href") && !has("to"))) {
+            self.report("href-hash", span,
+                "link goes nowhere (`href=\"#\"`), give it a real URL or use a button for actions".to_string());
+        }
+    }
+}
+
+impl<'a> Lint<'a> {
+    // Layouts innermost first: each one's slot renders what is nested so far
+    fn nest(&mut self, document: &'a WebCoreDocument, layouts: &[&'a Layout], inner: &Filler<'a, '_>) {
+        match layouts.split_first() {
+            Some((layout, outer)) => {
+                let next = Filler { elements: &layout.content, file: &layout.file, outer: Some(inner) };
+                self.nest(document, outer, &next);
+            }
+            None => self.headings(document, inner.elements, inner.file, inner.outer, &mut Vec::new()),
+        }
+    }
+
+    fn headings(&mut self, document: &'a WebCoreDocument, elements: &'a [Element], file: &'a str,
+                slot: Option<&Filler<'a, '_>>, expanding: &mut Vec<&'a str>) {
+        for element in elements {
+            match element {
+                Element::Tag { name, content, span, .. } => {
+                    if let Some(level) = heading_level(name) {
+                        if self.last_heading > 0 && level > self.last_heading + 1 {
+                            self.file = file;
+                            self.report("heading-order", *span,
+                                format!("h{} follows h{}, heading levels should not be skipped", level, self.last_heading));
+                        }
+                        self.last_heading = level;
+                    }
+                    self.headings(document, content, file, slot, expanding);
+                }
+                Element::Component { name, content, .. } => match document.components.get(name) {
+                    Some(component) if !expanding.contains(&name.as_str()) => {
+                        let given = Filler { elements: content, file, outer: slot };
+                        expanding.push(name);
+                        self.headings(document, &component.view, &component.file, Some(&given), expanding);
+                        expanding.pop();
+                    }
+                    _ => self.headings(document, content, file, slot, expanding),
+                },
+                Element::Slot { name, .. } if name == "content" => {
+                    if let Some(filler) = slot {
+                        self.headings(document, filler.elements, filler.file, filler.outer, expanding);
+                    }
+                }
+                Element::For { content, .. } => self.headings(document, content, file, slot, expanding),
+                Element::If { content, else_content, .. } => {
+                    self.headings(document, content, file, slot, expanding);
+                    self.headings(document, else_content, file, slot, expanding);
+                }
+                Element::Text { .. } | Element::Slot { .. } | Element::Interpolation { .. } | Element::Raw(_) => {}
+            }
+        }
+    }
+}
+
+fn heading_level(name: &str) -> Option<usize> {
+    let level = name.strip_prefix('h')?.parse::<usize>().ok()?;
+    (1..=6).contains(&level).then_some(level)
+}
+
+// Text a screen reader would announce; components, slots and raw html may provide it
+fn has_text(elements: &[Element]) -> bool {
+    elements.iter().any(|element| match element {
+        Element::Text { text, .. } => !text.trim().is_empty(),
+        Element::Interpolation { .. } | Element::Component { .. } | Element::Slot { .. } | Element::Raw(_) => true,
+        Element::Tag { name, attributes, .. } if name == "img" => attributes.iter().any(|a| a.name == "alt"
+            && !matches!(&a.value, AttributeValue::String(alt) if alt.trim().is_empty())),
+        Element::Tag { content, .. } | Element::For { content, .. } => has_text(content),
+        Element::If { content, else_content, .. } => has_text(content) || has_text(else_content),
+    })
+}
+
+fn collect_label_targets(elements: &[Element], out: &mut HashSet<String>) {
+    for element in elements {
+        match element {
+            Element::Tag { name, attributes, content, .. } => {
+                if name == "label" {
+                    for attr in attributes.iter().filter(|a| a.name == "for") {
+                        if let AttributeValue::String(id) = &attr.value {
+                            out.insert(id.clone());
+                        }
+                    }
+                }
+                collect_label_targets(content, out);
+            }
+            Element::Component { content, .. } | Element::For { content, .. } => collect_label_targets(content, out),
+            Element::If { content, else_content, .. } => {
+                collect_label_targets(content, out);
+                collect_label_targets(else_content, out);
+            }
+            Element::Text { .. } | Element::Slot { .. } | Element::Interpolation { .. } | Element::Raw(_) => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_webc_file;
+
+    #[test]
+    fn findings_carry_their_rule_and_configured_severity() {
+        let src = r#"
+component FormPage {
+  view {
+    h1 "Contact"
+    h3 "Details"
+    img src="/logo.png" {}
+    button on:click={ sent = true } {}
+    link "Home"
+    div tabindex="2" "Focus me"
+    label for="email" "Email"
+    input id="email" {}
+    label { "Name" input {} }
+    input type="submit" {}
+    textarea {}
+  }
+  state { sent: Boolean = false }
+}
+layout MainLayout { h1 "Site" slot }
+"#;
+        let doc = parse_webc_file(src, "src/components/form.webc").expect("parse ok");
+        let mut config = HashMap::new();
+        config.insert("img-alt".to_string(), "error".to_string());
+        config.insert("html-lang".to_string(), "off".to_string());
+        let rules = Rules::from_config(&config).expect("valid config");
+        let messages: Vec<String> = lint(&doc, None, Span::default(), &rules).iter()
+            .map(|d| format!("{} {}", d.to_string().split(':').next().unwrap_or_default(), d.span.line)).collect();
+        assert_eq!(messages, vec![
+            "warning[heading-order] 5", "error[img-alt] 6", "warning[accessible-name] 7",
+            "warning[href-hash] 8", "warning[tabindex] 9", "warning[form-label] 14",
+        ]);
+
+        // The layout's h2 and the card's h4 are only out of order on the page that combines them
+        let src = r#"
+layout MainLayout { h2 "Site" slot }
+component Card { view { h4 "Card" slot } }
+page "home" { Card { h3 "Inside" } }
+component Standalone { view { h1 "Alone" h3 "Skipped" } }
+"#;
+        let doc = parse_webc_file(src, "src/pages/home.webc").expect("parse ok");
+        let found: Vec<(usize, String)> = lint(&doc, Some("en"), Span::default(), &rules).into_iter()
+            .map(|d| (d.span.line, d.message)).collect();
+        assert_eq!(found, vec![(3, "h4 follows h2, heading levels should not be skipped".to_string())]);
+
+        config.insert("alt-text".to_string(), "error".to_string());
+        assert!(Rules::from_config(&config).expect_err("unknown rule").starts_with("unknown a11y rule 'alt-text'"));
+    }
+}
@@ -0,0 +1,190 @@
+//! `[global]` tags from webc.toml, added to every page
+//!
+//! ```toml
+//! [global]
+//! before = ["https://cdn.example.com/lib.js"]            # end of <head>
+//! after = [{ src = "scripts/app.js", type = "module" }]   # before </body>
+//! stylesheets = ["styles/print.css"]
+//! meta = [{ name = "theme-color", content = "#007acc" }]
+//! link = [{ rel = "icon", href = "public/favicon.png" }]
+//! ```
+//!
+//! URLs and root-absolute paths (`/favicon.ico`) are used as they are. Local
+//! files are read from the project and copied to `assets/` under a name
+//! carrying a hash of their content (`assets/app.3f2a9c1b.js`), so they can be
+//! cached for good. Meta and link attributes keep the order they are declared in.
+
+use crate::project::ProjectInput;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct GlobalSection {
+    before: Vec<ScriptEntry>,
+    after: Vec<ScriptEntry>,
+    stylesheets: Vec<String>,
+    meta: Vec<toml::Table>,
+    link: Vec<toml::Table>,
+}
+
+// `"lib.js"` or `{ src = "lib.js", defer = true }`
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum ScriptEntry {
+    Src(String),
+    Options {
+        src: String,
+        #[serde(default, rename = "async")]
+        is_async: bool,
+        #[serde(default)]
+        defer: bool,
+        #[serde(rename = "type")]
+        kind: Option<String>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Script {
+    pub src: Asset,
+    pub is_async: bool,
+    pub defer: bool,
+    pub kind: Option<String>,
+}
+
+/// Where a tag points: an external URL, or a copied file relative to the dist root
+#[derive(Debug, Clone, PartialEq)]
+pub enum Asset {
+    Url(String),
+    File(String),
+}
+
+impl Asset {
+    /// The reference as written in a page `base_path` away from the dist root
+    pub fn href(&self, base_path: &str) -> String {
+        match self {
+            Asset::Url(url) => url.clone(),
+            Asset::File(path) => format!("{}{}", base_path, path),
+        }
+    }
+}
+
+/// The `[global]` section with local files resolved
+#[derive(Debug, Clone, Default)]
+pub struct GlobalAssets {
+    pub before: Vec<Script>,
+    pub after: Vec<Script>,
+    pub stylesheets: Vec<Asset>,
+    pub meta: Vec<Vec<(String, String)>>,
+    /// Attributes of each `<link>`, `href` resolved
+    pub links: Vec<Vec<(String, Asset)>>,
+}
+
+/// Reads the local files the section refers to into `files`, keyed by their
+/// path under `dist/`; a missing one is an error
+pub fn resolve(section: &GlobalSection, input: &ProjectInput, files: &mut BTreeMap<String, Vec<u8>>) -> Result<GlobalAssets, String> {
+    let mut assets = GlobalAssets::default();
+    for (entries, scripts) in [(&section.before, &mut assets.before), (&section.after, &mut assets.after)] {
+        for entry in entries {
+            let script = match entry {
+                ScriptEntry::Src(src) => Script { src: asset(src, input, files)?, is_async: false, defer: false, kind: None },
+                ScriptEntry::Options { src, is_async, defer, kind } => Script {
+                    src: asset(src, input, files)?,
+                    is_async: *is_async,
+                    defer: *defer,
+                    kind: kind.clone(),
+                },
+            };
+            scripts.push(script);
+        }
+    }
+    for href in &section.stylesheets {
+        let stylesheet = asset(href, input, files)?;
+        assets.stylesheets.push(stylesheet);
+    }
+    for attrs in &section.meta {
+        assets.meta.push(attributes("meta", attrs)?);
+    }
+    for attrs in &section.link {
+        let mut link = Vec::new();
+        for (name, value) in attributes("link", attrs)? {
+            let value = if name == "href" { asset(&value, input, files)? } else { Asset::Url(value) };
+            link.push((name, value));
+        }
+        assets.links.push(link);
+    }
+    Ok(assets)
+}
+
+// Names are printed into the tag as they are, so only `[a-z-]+` is accepted
+fn attributes(tag: &str, table: &toml::Table) -> Result<Vec<(String, String)>, String> {
+    table.iter().map(|(name, value)| {
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_lowercase() || c == '-') {
+            return Err(format!("[global] {} attribute '{}' is not a valid attribute name", tag, name));
+        }
+        match value.as_str() {
+            Some(value) => Ok((name.clone(), value.to_string())),
+            None => Err(format!("[global] {} attribute '{}' must be a string", tag, name)),
+        }
+    }).collect()
+}
+
+fn asset(reference: &str, input: &ProjectInput, files: &mut BTreeMap<String, Vec<u8>>) -> Result<Asset, String> {
+    if is_url(reference) {
+        return Ok(Asset::Url(reference.to_string()));
+    }
+    let contents = input.read(reference)
+        .ok_or_else(|| format!("[global] file '{}' not found", reference))?;
+    let path = fingerprinted(reference, contents);
+    files.insert(path.clone(), contents.to_vec());
+    Ok(Asset::File(path))
+}
+
+// Root-absolute paths point into the deployed site, not the project
+fn is_url(reference: &str) -> bool {
+    reference.contains("://") || reference.starts_with('/') || reference.starts_with("data:")
+}
+
+// `scripts/app.js` → `assets/app.3f2a9c1b.js`
+fn fingerprinted(reference: &str, contents: &[u8]) -> String {
+    let digest = Sha256::digest(contents);
+    let hash: String = digest.iter().take(4).map(|b| format!("{:02x}", b)).collect();
+    let name = reference.rsplit(['/', '\\']).next().unwrap_or(reference);
+    match name.rsplit_once('.') {
+        Some((stem, extension)) if !stem.is_empty() => format!("assets/{}.{}.{}", stem, hash, extension),
+        _ => format!("assets/{}.{}", name, hash),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn local_files_are_fingerprinted_and_urls_kept() {
+        let mut input = ProjectInput::new();
+        input.insert("scripts/app.js", "console.log(1)");
+        input.insert("public/favicon.png", vec![0u8, 1, 2]);
+        let section: GlobalSection = toml::from_str(r#"
+before = ["https://cdn.example.com/lib.js"]
+after = [{ src = "./scripts/app.js", type = "module", defer = true }]
+link = [{ rel = "icon", href = "public/favicon.png" }, { rel = "manifest", href = "/site.webmanifest" }]
+"#).expect("toml ok");
+        let mut files = BTreeMap::new();
+        let assets = resolve(&section, &input, &mut files).expect("resolves");
+        assert_eq!(assets.before[0].src, Asset::Url("https://cdn.example.com/lib.js".to_string()));
+        let Asset::File(path) = &assets.after[0].src else { panic!("copied file") };
+        assert!(path.starts_with("assets/app.") && path.ends_with(".js") && path.len() == "assets/app.12345678.js".len(), "{}", path);
+        assert_eq!(assets.after[0].kind.as_deref(), Some("module"));
+        assert_eq!(files[path], b"console.log(1)");
+        assert!(matches!(&assets.links[0][1], (name, Asset::File(icon)) if name == "href" && icon.starts_with("assets/favicon.")));
+        assert_eq!(assets.links[1][1], ("href".to_string(), Asset::Url("/site.webmanifest".to_string())));
+
+        let invalid: GlobalSection = toml::from_str("meta = [{ \"on load\" = \"x\" }]").expect("toml ok");
+        assert_eq!(resolve(&invalid, &input, &mut files).unwrap_err(), "[global] meta attribute 'on load' is not a valid attribute name");
+
+        let missing: GlobalSection = toml::from_str("stylesheets = [\"styles/none.css\"]").expect("toml ok");
+        assert_eq!(resolve(&missing, &input, &mut files).unwrap_err(), "[global] file 'styles/none.css' not found");
+    }
+}
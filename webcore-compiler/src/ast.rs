@@ -1,95 +1,324 @@
 //! AST definition for WebCore
+//!
+//! Every node serializes to JSON (`webc ast`); maps are written in name order
+//! so the dump is stable from one run to the next.
 
-use std::collections::HashMap;
+use serde::{Serialize, Serializer};
+use std::collections::{BTreeMap, HashMap};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct WebCoreDocument {
     pub app: Option<App>,
+    #[serde(serialize_with = "sorted")]
     pub layouts: HashMap<String, Layout>,
+    #[serde(serialize_with = "sorted")]
     pub pages: HashMap<String, Page>,
+    #[serde(serialize_with = "sorted")]
     pub components: HashMap<String, Component>,
+    #[serde(serialize_with = "sorted")]
+    pub stores: HashMap<String, Store>,
+    /// `style global { ... }` blocks, emitted verbatim into global.css
+    pub global_styles: Vec<RawBlock>,
 }
 
-#[derive(Debug, Clone)]
+fn sorted<S: Serializer, V: Serialize>(map: &HashMap<String, V>, serializer: S) -> Result<S::Ok, S::Error> {
+    map.iter().collect::<BTreeMap<_, _>>().serialize(serializer)
+}
+
+/// Location of a node in its .webc file: byte range plus 1-based line/column
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
 pub struct App {
     pub name: String,
     pub theme: Option<String>,
     pub layout: Option<String>,
-    pub routes: HashMap<String, String>,
+    pub routes: Vec<Route>,
+    pub file: String,
+    pub span: Span,
 }
 
-#[derive(Debug, Clone)]
+/// `"/about": AboutPage` inside `routes { ... }`
+#[derive(Debug, Clone, Serialize)]
+pub struct Route {
+    pub path: String,
+    pub target: String,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, Serialize)]
 pub struct Layout {
     pub name: String,
+    /// `layout Docs extends MainLayout`: rendered inside the parent's slot
+    pub extends: Option<String>,
     pub content: Vec<Element>,
+    /// Source file the definition was parsed from, empty for generated ones
+    pub file: String,
+    pub span: Span,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct Page {
     pub name: String,
+    pub layout: Option<String>,
+    pub data: Vec<DataSource>,
     pub content: Vec<Element>,
+    pub file: String,
+    pub span: Span,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct Component {
     pub name: String,
+    /// Layout override when the component is rendered as a page
+    pub layout: Option<String>,
     pub props: Vec<Prop>,
+    /// Stores the view may use, the rest of its state is private to each instance
+    pub imports: Vec<StoreImport>,
+    /// `events { select: Item }`: what the component may `emit` to its parent
+    pub events: Vec<EventDecl>,
     pub state: Vec<StateVar>,
+    pub data: Vec<DataSource>,
+    pub resources: Vec<Resource>,
     pub view: Vec<Element>,
     pub style: Vec<StyleRule>,
+    /// `keyframes wobble { ... }` in the style section, usable as `transition:wobble`
+    pub keyframes: Vec<Keyframes>,
+    pub file: String,
+    pub span: Span,
+}
+
+/// `store Cart { items: List = [] }`: state shared by every component that
+/// declares `use Cart`, read and written as `Cart.items`
+#[derive(Debug, Clone, Serialize)]
+pub struct Store {
+    pub name: String,
+    pub fields: Vec<StateVar>,
+    pub file: String,
+    pub span: Span,
+}
+
+/// `use Cart` inside a component
+#[derive(Debug, Clone, Serialize)]
+pub struct StoreImport {
+    pub name: String,
+    pub span: Span,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct Prop {
     pub name: String,
+    #[serde(rename = "type")]
     pub type_: Option<String>,
+    pub span: Span,
 }
 
-#[derive(Debug, Clone)]
+/// An event a component emits with `emit("select", item)`; the parent listens
+/// with `ItemList on:select={ selected = $detail }`
+#[derive(Debug, Clone, Serialize)]
+pub struct EventDecl {
+    pub name: String,
+    /// Type of the `$detail` payload, if the event carries one
+    #[serde(rename = "type")]
+    pub type_: Option<String>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, Serialize)]
 pub struct StateVar {
     pub name: String,
+    #[serde(rename = "type")]
     pub type_: String,
+    /// Initial value as a JSON literal: `0`, `"text"`, `[]`
     pub default_value: Option<String>,
+    pub span: Span,
+}
+
+/// `data posts = load("content/posts.json")` or `data posts = collection("blog")`,
+/// resolved at build time
+#[derive(Debug, Clone, Serialize)]
+pub struct DataSource {
+    pub name: String,
+    pub path: String,
+    pub kind: DataSourceKind,
+    pub span: Span,
+}
+
+/// `resource users = fetch("/api/users?page={page}")`, loaded in the browser and
+/// exposed to the view as `users.loading`, `users.error` and `users.data`
+#[derive(Debug, Clone, Serialize)]
+pub struct Resource {
+    pub name: String,
+    pub url: String,
+    pub span: Span,
+}
+
+impl Resource {
+    /// Names interpolated into the URL; the resource is fetched again when one changes
+    pub fn dependencies(&self) -> Vec<String> {
+        let mut names = Vec::new();
+        let mut rest = self.url.as_str();
+        while let Some(start) = rest.find('{') {
+            let Some(end) = rest[start..].find('}') else { break };
+            let name = rest[start + 1..start + end].trim().to_string();
+            if !name.is_empty() && !names.contains(&name) {
+                names.push(name);
+            }
+            rest = &rest[start + end + 1..];
+        }
+        names
+    }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub enum DataSourceKind {
+    File,
+    Collection,
+}
+
+impl DataSource {
+    /// Key under which the loaded value is stored in the build's data set
+    pub fn key(&self) -> String {
+        match self.kind {
+            DataSourceKind::File => self.path.clone(),
+            DataSourceKind::Collection => format!("collection:{}", self.path),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
 pub enum Element {
-    Text(String),
+    Text {
+        text: String,
+        span: Span,
+    },
     Tag {
         name: String,
         attributes: Vec<Attribute>,
         content: Vec<Element>,
+        span: Span,
+    },
+    Slot {
+        name: String,
+        span: Span,
     },
-    Slot(String),
     Component {
         name: String,
         attributes: Vec<Attribute>,
         content: Vec<Element>,
+        span: Span,
     },
-    Interpolation(String),
+    Interpolation {
+        expr: String,
+        span: Span,
+    },
+    For {
+        item: String,
+        iterable: String,
+        content: Vec<Element>,
+        span: Span,
+    },
+    /// `if node.children { ... } else { ... }`, evaluated at build time when
+    /// the condition is known then, otherwise in the browser
+    If {
+        condition: String,
+        content: Vec<Element>,
+        else_content: Vec<Element>,
+        span: Span,
+    },
+    Raw(RawBlock),
 }
 
-#[derive(Debug, Clone)]
+/// Escape hatch passed through without escaping: `raw html """..."""`,
+/// `script src="..."` and `style global { ... }`
+#[derive(Debug, Clone, Serialize)]
+pub struct RawBlock {
+    pub kind: RawKind,
+    pub content: String,
+    pub src: Option<String>,
+    pub attributes: Vec<Attribute>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub enum RawKind {
+    Html,
+    Script,
+    Style,
+}
+
+impl RawKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            RawKind::Html => "raw html",
+            RawKind::Script => "script",
+            RawKind::Style => "style global",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
 pub struct Attribute {
     pub name: String,
     pub value: AttributeValue,
+    pub span: Span,
+}
+
+impl Attribute {
+    /// `on:keydown.ctrl.s` → `("keydown", ["ctrl", "s"])`; `None` for other attributes
+    pub fn event(&self) -> Option<(&str, Vec<&str>)> {
+        let mut parts = self.name.strip_prefix("on:")?.split('.');
+        let event = parts.next()?;
+        Some((event, parts.collect()))
+    }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub enum AttributeValue {
     String(String),
     Expression(String),
     Boolean(bool),
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct StyleRule {
     pub selector: String,
     pub properties: Vec<StyleProperty>,
+    pub span: Span,
+}
+
+/// Custom transition: the animation runs forwards on enter and reversed on leave
+#[derive(Debug, Clone, Serialize)]
+pub struct Keyframes {
+    pub name: String,
+    /// CSS between the braces, emitted verbatim inside `@keyframes`
+    pub body: String,
+    pub span: Span,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct StyleProperty {
     pub name: String,
     pub value: String,
 }
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn document_serializes_in_name_order_with_spans() {
+        let src = "component Zeta { view { p \"z\" } }\ncomponent Alpha {\n  props { title: String }\n  view { h1 \"{title}\" }\n}\n";
+        let doc = crate::parser::parse_webc(src).expect("parse ok");
+        let json = serde_json::to_value(&doc).expect("serializes");
+        let names: Vec<&String> = json["components"].as_object().expect("components").keys().collect();
+        assert_eq!(names, vec!["Alpha", "Zeta"]);
+        let prop = &json["components"]["Alpha"]["props"][0];
+        assert_eq!((prop["name"].as_str(), prop["type"].as_str()), (Some("title"), Some("String")));
+        assert_eq!((prop["span"]["line"].as_u64(), prop["span"]["column"].as_u64()), (Some(3), Some(11)));
+    }
+}
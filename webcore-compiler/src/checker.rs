@@ -0,0 +1,595 @@
+//! Semantic checks run after parsing and before code generation
+//!
+//! The parser only knows about syntax; everything that needs the whole
+//! project (which components exist, what a view may refer to) is checked
+//! here and reported with the span of the offending node.
+
+use crate::ast::*;
+use crate::html;
+use crate::ir::{self, expression_names, parse_emit};
+use crate::parser::ParseError;
+use std::collections::{BTreeSet, HashSet};
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    pub file: String,
+    pub span: Span,
+    /// Id of the lint rule that reported it, shown as `warning[img-alt]`
+    pub rule: Option<&'static str>,
+}
+
+impl Diagnostic {
+    pub fn error(file: &str, span: Span, message: impl Into<String>) -> Self {
+        Self { severity: Severity::Error, message: message.into(), file: file.to_string(), span, rule: None }
+    }
+
+    pub fn warning(file: &str, span: Span, message: impl Into<String>) -> Self {
+        Self { severity: Severity::Warning, message: message.into(), file: file.to_string(), span, rule: None }
+    }
+
+    pub fn with_rule(self, rule: &'static str) -> Self {
+        Self { rule: Some(rule), ..self }
+    }
+
+    /// `file:line:column`, or just the file when the node has no position
+    pub fn location(&self) -> String {
+        if self.span.line == 0 {
+            self.file.clone()
+        } else {
+            format!("{}:{}:{}", self.file, self.span.line, self.span.column)
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let level = match self.severity {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        match self.rule {
+            Some(rule) => write!(f, "{}[{}]: {}", level, rule, self.message)?,
+            None => write!(f, "{}: {}", level, self.message)?,
+        }
+        if !self.file.is_empty() {
+            write!(f, "\n  --> {}", self.location())?;
+        }
+        Ok(())
+    }
+}
+
+pub fn has_errors(diagnostics: &[Diagnostic]) -> bool {
+    diagnostics.iter().any(|d| d.severity == Severity::Error)
+}
+
+/// Merge a parsed file into the project, reporting names that are already taken
+pub fn merge_definitions(document: &mut WebCoreDocument, parsed: WebCoreDocument, diagnostics: &mut Vec<Diagnostic>) {
+    document.global_styles.extend(parsed.global_styles);
+    if let Some(app) = parsed.app {
+        match &document.app {
+            Some(existing) => diagnostics.push(Diagnostic::error(&app.file, Span::default(),
+                format!("app '{}' is already defined in {}", app.name, existing.file))),
+            None => document.app = Some(app),
+        }
+    }
+    for (name, layout) in parsed.layouts {
+        match document.layouts.get(&name) {
+            Some(existing) => diagnostics.push(duplicate("layout", &name, &layout.file, layout.span, &existing.file, existing.span)),
+            None => { document.layouts.insert(name, layout); }
+        }
+    }
+    for (name, page) in parsed.pages {
+        match document.pages.get(&name) {
+            Some(existing) => diagnostics.push(duplicate("page", &name, &page.file, page.span, &existing.file, existing.span)),
+            None => { document.pages.insert(name, page); }
+        }
+    }
+    for (name, store) in parsed.stores {
+        match document.stores.get(&name) {
+            Some(existing) => diagnostics.push(duplicate("store", &name, &store.file, store.span, &existing.file, existing.span)),
+            None => { document.stores.insert(name, store); }
+        }
+    }
+    for (name, component) in parsed.components {
+        match document.components.get(&name) {
+            Some(existing) => diagnostics.push(duplicate("component", &name, &component.file, component.span, &existing.file, existing.span)),
+            None => { document.components.insert(name, component); }
+        }
+    }
+}
+
+/// A file that failed to parse, at the token the parser stopped on; a name
+/// defined twice points at both definitions
+pub fn parse_error(file: &str, error: &ParseError) -> Diagnostic {
+    match error {
+        ParseError::Duplicate { kind, name, span, first } => duplicate(kind, name, file, *span, file, *first),
+        ParseError::At { span, error } => Diagnostic::error(file, *span, format!("parse error: {}", error)),
+        error => Diagnostic::error(file, Span::default(), format!("parse error: {}", error)),
+    }
+}
+
+fn duplicate(kind: &str, name: &str, file: &str, span: Span, first_file: &str, first_span: Span) -> Diagnostic {
+    let first = Diagnostic::error(first_file, first_span, "").location();
+    Diagnostic::error(file, span, format!("{} '{}' is already defined at {}", kind, name, first))
+}
+
+pub fn check_document(document: &WebCoreDocument) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+
+    if let Some(app) = &document.app {
+        if let Some(layout) = &app.layout {
+            check_layout_ref(document, layout, &app.file, Span::default(), &mut diagnostics);
+        }
+        for route in &app.routes {
+            if !document.components.contains_key(&route.target) && !document.pages.contains_key(&route.target) {
+                diagnostics.push(Diagnostic::error(&app.file, route.span,
+                    format!("route '{}' points to unknown component '{}'", route.path, route.target)));
+            }
+        }
+    }
+
+    for layout in document.layouts.values() {
+        if let Some(parent) = &layout.extends {
+            check_layout_ref(document, parent, &layout.file, layout.span, &mut diagnostics);
+        }
+        if !contains_slot(&layout.content) {
+            diagnostics.push(Diagnostic::warning(&layout.file, layout.span,
+                format!("layout '{}' has no slot, page content will not be rendered", layout.name)));
+        }
+        // A layout renders in the scope of each page it wraps: a name is fine
+        // if every one of them defines it
+        let mut scopes: Vec<Vec<String>> = document.pages.values()
+            .filter(|page| ir::resolve_layout_chain(document, page).is_ok_and(|chain| chain.iter().any(|l| l.name == layout.name)))
+            .map(|page| page.data.iter().map(|d| d.name.clone()).collect())
+            .collect();
+        if scopes.is_empty() {
+            scopes.push(Vec::new());
+        }
+        let mut reported = HashSet::new();
+        for scope in scopes {
+            let mut found = Vec::new();
+            ViewCheck::new(document, &layout.file, scope).check(&layout.content, &mut found);
+            diagnostics.extend(found.into_iter().filter(|d| reported.insert((d.span.start, d.message.clone()))));
+        }
+    }
+
+    for page in document.pages.values() {
+        if let Some(layout) = &page.layout {
+            check_layout_ref(document, layout, &page.file, page.span, &mut diagnostics);
+        }
+        let names = page.data.iter().map(|d| d.name.clone()).collect();
+        let mut view = ViewCheck::new(document, &page.file, names);
+        view.check(&page.content, &mut diagnostics);
+    }
+
+    for component in document.components.values() {
+        if let Some(layout) = &component.layout {
+            check_layout_ref(document, layout, &component.file, component.span, &mut diagnostics);
+        }
+        let names = component.props.iter().map(|p| p.name.clone())
+            .chain(component.state.iter().map(|s| s.name.clone()))
+            .chain(component.data.iter().map(|d| d.name.clone()))
+            .chain(component.resources.iter().map(|r| r.name.clone()))
+            .chain(component.imports.iter().map(|i| i.name.clone()))
+            .collect();
+        let mut view = ViewCheck::new(document, &component.file, names);
+        view.component = Some(component);
+        for import in &component.imports {
+            if !document.stores.contains_key(&import.name) {
+                diagnostics.push(Diagnostic::error(&component.file, import.span, format!("unknown store '{}'", import.name)));
+            }
+        }
+        for resource in &component.resources {
+            view.check_expression(&resource.dependencies().join(" "), resource.span, "resource URL", &mut diagnostics);
+        }
+        view.check(&component.view, &mut diagnostics);
+        for resource in &component.resources {
+            if !view.used.contains(&resource.name) {
+                diagnostics.push(Diagnostic::warning(&component.file, resource.span,
+                    format!("resource '{}' of component '{}' is never used", resource.name, component.name)));
+            }
+        }
+        for state in &component.state {
+            if !view.used.contains(&state.name) {
+                diagnostics.push(Diagnostic::warning(&component.file, state.span,
+                    format!("state '{}' of component '{}' is never used", state.name, component.name)));
+            }
+        }
+        for import in &component.imports {
+            if document.stores.contains_key(&import.name) && !view.used.contains(&import.name) {
+                diagnostics.push(Diagnostic::warning(&component.file, import.span,
+                    format!("store '{}' is imported by component '{}' but never used", import.name, component.name)));
+            }
+        }
+        for event in &component.events {
+            if !view.emitted.contains(&event.name) {
+                diagnostics.push(Diagnostic::warning(&component.file, event.span,
+                    format!("event '{}' of component '{}' is never emitted", event.name, component.name)));
+            }
+        }
+        for prop in &component.props {
+            if !view.used.contains(&prop.name) {
+                diagnostics.push(Diagnostic::warning(&component.file, prop.span,
+                    format!("prop '{}' of component '{}' is never used", prop.name, component.name)));
+            }
+        }
+    }
+
+    check_component_cycles(document, &mut diagnostics);
+
+    diagnostics.sort_by(|a, b| (&a.file, a.span.start).cmp(&(&b.file, b.span.start)));
+    diagnostics
+}
+
+// A component used inside its own view (directly or through others) expands
+// forever unless every step of the cycle sits under an `if` or `for`, which
+// stop once the data runs out. Only unconditional uses form edges here.
+fn check_component_cycles(document: &WebCoreDocument, diagnostics: &mut Vec<Diagnostic>) {
+    let mut names: Vec<&String> = document.components.keys().collect();
+    names.sort();
+    let mut reported: HashSet<BTreeSet<String>> = HashSet::new();
+    let mut done: HashSet<String> = HashSet::new();
+    for name in names {
+        let mut path = Vec::new();
+        find_cycles(document, name, &mut path, &mut done, &mut reported, diagnostics);
+    }
+}
+
+fn find_cycles(
+    document: &WebCoreDocument,
+    name: &str,
+    path: &mut Vec<String>,
+    done: &mut HashSet<String>,
+    reported: &mut HashSet<BTreeSet<String>>,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    if done.contains(name) {
+        return;
+    }
+    let Some(component) = document.components.get(name) else { return };
+    path.push(name.to_string());
+    let mut uses = Vec::new();
+    unconditional_uses(&component.view, &mut uses);
+    for (child, span) in uses {
+        if let Some(start) = path.iter().position(|p| p == &child) {
+            let members: BTreeSet<String> = path[start..].iter().cloned().collect();
+            if reported.insert(members) {
+                let mut cycle = path[start..].to_vec();
+                cycle.push(child.clone());
+                diagnostics.push(Diagnostic::error(&component.file, span, format!(
+                    "component cycle {}: wrap the recursive use in `if` or `for` so it can terminate",
+                    cycle.join(" -> "))));
+            }
+        } else {
+            find_cycles(document, &child, path, done, reported, diagnostics);
+        }
+    }
+    path.pop();
+    done.insert(name.to_string());
+}
+
+fn unconditional_uses(elements: &[Element], out: &mut Vec<(String, Span)>) {
+    for element in elements {
+        match element {
+            Element::Component { name, content, span, .. } => {
+                out.push((name.clone(), *span));
+                unconditional_uses(content, out);
+            }
+            Element::Tag { content, .. } => unconditional_uses(content, out),
+            Element::For { .. } | Element::If { .. } => {}
+            Element::Text { .. } | Element::Slot { .. } | Element::Interpolation { .. } | Element::Raw(_) => {}
+        }
+    }
+}
+
+fn check_layout_ref(document: &WebCoreDocument, name: &str, file: &str, span: Span, diagnostics: &mut Vec<Diagnostic>) {
+    if !document.layouts.contains_key(name) {
+        diagnostics.push(Diagnostic::error(file, span, format!("unknown layout '{}'", name)));
+    }
+}
+
+fn contains_slot(elements: &[Element]) -> bool {
+    elements.iter().any(|element| match element {
+        Element::Slot { .. } => true,
+        Element::Tag { content, .. } | Element::Component { content, .. } | Element::For { content, .. } => contains_slot(content),
+        Element::If { content, else_content, .. } => contains_slot(content) || contains_slot(else_content),
+        _ => false,
+    })
+}
+
+// Walks one view with the names it may refer to, recording which were used
+struct ViewCheck<'a> {
+    document: &'a WebCoreDocument,
+    file: &'a str,
+    scope: Vec<String>,
+    used: HashSet<String>,
+    // Component whose view this is, for checking its `emit` calls
+    component: Option<&'a Component>,
+    emitted: HashSet<String>,
+}
+
+impl<'a> ViewCheck<'a> {
+    fn new(document: &'a WebCoreDocument, file: &'a str, scope: Vec<String>) -> Self {
+        Self { document, file, scope, used: HashSet::new(), component: None, emitted: HashSet::new() }
+    }
+
+    fn check(&mut self, elements: &[Element], diagnostics: &mut Vec<Diagnostic>) {
+        for element in elements {
+            match element {
+                Element::Tag { name, attributes, content, span } => {
+                    if html::is_void(name) && !content.is_empty() {
+                        diagnostics.push(Diagnostic::error(self.file, *span,
+                            format!("{} is a void element and cannot have content", name)));
+                    }
+                    self.check_attributes(attributes, diagnostics);
+                    self.check(content, diagnostics);
+                }
+                Element::Component { name, attributes, content, span } => {
+                    match self.document.components.get(name) {
+                        Some(component) => self.check_listeners(component, attributes, diagnostics),
+                        None => diagnostics.push(Diagnostic::error(self.file, *span, format!("unknown component '{}'", name))),
+                    }
+                    self.check_attributes(attributes, diagnostics);
+                    self.check(content, diagnostics);
+                }
+                Element::Interpolation { expr, span } => self.check_expression(expr, *span, "interpolation", diagnostics),
+                Element::For { item, iterable, content, span } => {
+                    self.check_expression(iterable, *span, "for loop", diagnostics);
+                    self.scope.push(item.clone());
+                    self.check(content, diagnostics);
+                    self.scope.pop();
+                }
+                Element::If { condition, content, else_content, span } => {
+                    self.check_expression(condition, *span, "if condition", diagnostics);
+                    self.check(content, diagnostics);
+                    self.check(else_content, diagnostics);
+                }
+                Element::Text { .. } | Element::Slot { .. } | Element::Raw(_) => {}
+            }
+        }
+    }
+
+    // `ItemList on:select={ ... }` must name an event the component declares
+    fn check_listeners(&self, component: &Component, attributes: &[Attribute], diagnostics: &mut Vec<Diagnostic>) {
+        for attr in attributes {
+            let (Some((event, modifiers)), AttributeValue::Expression(expr)) = (attr.event(), &attr.value) else { continue };
+            if !modifiers.is_empty() {
+                diagnostics.push(Diagnostic::error(self.file, attr.span,
+                    format!("event modifiers only apply to DOM events, not to '{}' of component '{}'", event, component.name)));
+                continue;
+            }
+            match component.events.iter().find(|e| e.name == event) {
+                None => diagnostics.push(Diagnostic::error(self.file, attr.span,
+                    format!("component '{}' does not emit '{}'", component.name, event))),
+                Some(declared) if declared.type_.is_none() && expr.contains("$detail") => diagnostics.push(Diagnostic::warning(self.file, attr.span,
+                    format!("event '{}' of component '{}' has no payload, `$detail` is undefined", event, component.name))),
+                Some(_) => {}
+            }
+        }
+    }
+
+    fn check_emit(&mut self, expr: &str, span: Span, diagnostics: &mut Vec<Diagnostic>) {
+        let Some((event, payload)) = parse_emit(expr) else { return };
+        let Some(component) = self.component else {
+            diagnostics.push(Diagnostic::error(self.file, span, format!("emit(\"{}\") can only be used inside a component", event)));
+            return;
+        };
+        self.emitted.insert(event.clone());
+        match component.events.iter().find(|e| e.name == event) {
+            None => diagnostics.push(Diagnostic::error(self.file, span, format!(
+                "component '{}' emits undeclared event '{}', add it to `events {{ ... }}`", component.name, event))),
+            Some(EventDecl { type_: Some(type_), .. }) if payload.is_none() => diagnostics.push(Diagnostic::error(self.file, span,
+                format!("event '{}' of component '{}' carries a {}, pass it to emit(\"{}\", ...)", event, component.name, type_, event))),
+            Some(_) => {}
+        }
+    }
+
+    fn check_attributes(&mut self, attributes: &[Attribute], diagnostics: &mut Vec<Diagnostic>) {
+        for attr in attributes {
+            // Transition options are checked with the directives themselves
+            if crate::transition::is_directive(attr) {
+                continue;
+            }
+            if let AttributeValue::Expression(expr) = &attr.value {
+                if let Some((event, modifiers)) = attr.event() {
+                    if let Err(message) = crate::modifiers::validate(event, &modifiers) {
+                        diagnostics.push(Diagnostic::error(self.file, attr.span, message));
+                    }
+                    self.check_emit(expr, attr.span, diagnostics);
+                }
+                let context = if attr.name.starts_with("on:") { "event handler" } else { "attribute" };
+                self.check_expression(expr, attr.span, context, diagnostics);
+            }
+            // `href="/x/{label}"`: each segment is filled in like an interpolation
+            if let AttributeValue::String(value) = &attr.value {
+                for segment in crate::data::segments(value) {
+                    self.check_expression(segment, attr.span, "attribute", diagnostics);
+                }
+            }
+        }
+    }
+
+    fn check_expression(&mut self, expr: &str, span: Span, context: &str, diagnostics: &mut Vec<Diagnostic>) {
+        for name in expression_names(expr) {
+            if self.scope.contains(&name) {
+                self.used.insert(name);
+            } else if self.document.stores.contains_key(&name) {
+                diagnostics.push(Diagnostic::error(self.file, span, format!("store '{}' is not imported, add `use {}` to the component", name, name)));
+            } else {
+                diagnostics.push(Diagnostic::error(self.file, span, format!("'{}' is not defined (in {})", name, context)));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_webc_file;
+
+    #[test]
+    fn reports_undefined_names_and_unused_state() {
+        let src = r#"
+app Demo {
+  routes {
+    "/": HomePage
+    "/missing": NowherePage
+  }
+}
+layout MainLayout { header { "Site" } }
+component HomePage {
+  state { count: Number = 0 unused: Number = 1 }
+  view {
+    p "{count} {post.title}"
+    button on:click={ count = max(0, total - 1) } "Less"
+    Cardd
+  }
+}
+"#;
+        let doc = parse_webc_file(src, "src/app.webc").expect("parse ok");
+        let diagnostics = check_document(&doc);
+        let messages: Vec<String> = diagnostics.iter().map(|d| d.to_string()).collect();
+        assert!(messages.iter().any(|m| m.starts_with("error: route '/missing' points to unknown component 'NowherePage'") && m.ends_with("src/app.webc:5:5")));
+        assert!(messages.iter().any(|m| m.starts_with("warning: layout 'MainLayout' has no slot")));
+        assert!(messages.iter().any(|m| m.starts_with("error: 'post' is not defined (in interpolation)") && m.ends_with(":12:17")));
+        assert!(messages.iter().any(|m| m.starts_with("error: 'total' is not defined (in event handler)")));
+        assert!(messages.iter().any(|m| m.starts_with("error: unknown component 'Cardd'")));
+        assert!(messages.iter().any(|m| m.starts_with("warning: state 'unused' of component 'HomePage' is never used")));
+        assert!(!messages.iter().any(|m| m.contains("'count'")));
+    }
+
+    #[test]
+    fn layouts_see_the_data_of_the_pages_they_wrap() {
+        let src = r#"
+layout MainLayout { header { "{site.name}" } slot }
+layout Plain { p "{site.name}" slot }
+page "home" {
+  data site = load("./data/site.json")
+  h1 "Home"
+}
+page "about" layout Plain { h1 "About" }
+page "about" { h1 "Again" }
+"#;
+        let err = parse_webc_file(src, "src/pages/home.webc").expect_err("duplicate page");
+        assert_eq!(parse_error("src/pages/home.webc", &err).to_string(),
+            "error: page 'about' is already defined at src/pages/home.webc:8:6\n  --> src/pages/home.webc:9:6");
+        let err = parse_webc_file("page \"x\" {\n  h1 \"x\"\n}\n}\n", "src/pages/x.webc").expect_err("stray brace");
+        assert_eq!(parse_error("src/pages/x.webc", &err).to_string(),
+            "error: parse error: unexpected token RightBrace\n  --> src/pages/x.webc:4:1");
+
+        let doc = parse_webc_file(src.replace("page \"about\" { h1 \"Again\" }", "").as_str(), "src/pages/home.webc").expect("parse ok");
+        let messages: Vec<String> = check_document(&doc).iter().map(|d| d.to_string()).collect();
+        assert_eq!(messages, vec!["error: 'site' is not defined (in interpolation)\n  --> src/pages/home.webc:3:20"]);
+    }
+
+    #[test]
+    fn unconditional_component_cycles_are_reported_once() {
+        let src = r#"
+component Outer { view { div { Inner } } }
+component Inner { view { Outer } }
+component SelfRef { view { SelfRef } }
+component Tree {
+  props { node: Object }
+  view { if node.children { for child in node.children { Tree node={child} } } }
+}
+"#;
+        let doc = parse_webc_file(src, "src/components/cycle.webc").expect("parse ok");
+        let cycles: Vec<String> = check_document(&doc).into_iter()
+            .filter(|d| d.message.starts_with("component cycle"))
+            .map(|d| d.to_string())
+            .collect();
+        assert_eq!(cycles.len(), 2);
+        assert!(cycles.iter().any(|m| m.starts_with("error: component cycle Inner -> Outer -> Inner") && m.ends_with("cycle.webc:2:32")));
+        assert!(cycles.iter().any(|m| m.starts_with("error: component cycle SelfRef -> SelfRef")));
+    }
+
+    #[test]
+    fn duplicate_definitions_across_files_are_errors() {
+        let mut document = parse_webc_file("component Card { view { p \"A\" } }", "src/components/a.webc").expect("parse ok");
+        let second = parse_webc_file("\ncomponent Card { view { p \"B\" } }", "src/components/b.webc").expect("parse ok");
+        let mut diagnostics = Vec::new();
+        merge_definitions(&mut document, second, &mut diagnostics);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].to_string(),
+            "error: component 'Card' is already defined at src/components/a.webc:1:11\n  --> src/components/b.webc:2:11");
+        assert!(has_errors(&diagnostics));
+    }
+
+    #[test]
+    fn component_events_are_checked_on_both_sides() {
+        let src = r#"
+component ItemList {
+  props { items: List }
+  events { select: Item clear }
+  view {
+    for item in items { li on:click={ emit("select", item) } "{item.name}" }
+    button on:click={ emit("remove") } "Remove"
+  }
+}
+component Picker {
+  state { selected: Item = "" }
+  view {
+    p "{selected}"
+    ItemList items={selected} on:select={ selected = $detail } on:choose={ selected = $detail }
+  }
+}
+"#;
+        let doc = parse_webc_file(src, "src/components/list.webc").expect("parse ok");
+        let messages: Vec<String> = check_document(&doc).iter().map(|d| d.to_string()).collect();
+        assert!(messages.iter().any(|m| m.starts_with("error: component 'ItemList' emits undeclared event 'remove'")));
+        assert!(messages.iter().any(|m| m.starts_with("warning: event 'clear' of component 'ItemList' is never emitted")));
+        assert!(messages.iter().any(|m| m.starts_with("error: component 'ItemList' does not emit 'choose'") && m.ends_with("list.webc:14:64")));
+        assert!(!messages.iter().any(|m| m.contains("'select'") || m.contains("'$detail'")));
+    }
+
+    #[test]
+    fn stores_must_be_imported_to_be_used() {
+        let src = r#"
+store Cart { items: List = [] total: Number = -1.5 open: Boolean = false }
+component CartBadge {
+  use Cart
+  use Wishlist
+  view { span "{Cart.items}" }
+}
+component Header {
+  use Cart
+  view { p "{Cart.total}" }
+}
+component Sidebar {
+  view { p "{Cart.total}" }
+}
+"#;
+        let doc = parse_webc_file(src, "src/stores/cart.webc").expect("parse ok");
+        let defaults: Vec<Option<String>> = doc.stores["Cart"].fields.iter().map(|f| f.default_value.clone()).collect();
+        assert_eq!(defaults, vec![Some("[]".to_string()), Some("-1.5".to_string()), Some("false".to_string())]);
+        let messages: Vec<String> = check_document(&doc).iter().map(|d| d.to_string()).collect();
+        assert!(messages.iter().any(|m| m.starts_with("error: unknown store 'Wishlist'")));
+        assert!(messages.iter().any(|m| m.starts_with("error: store 'Cart' is not imported, add `use Cart` to the component") && m.ends_with("cart.webc:13:14")));
+        assert_eq!(messages.len(), 2);
+    }
+
+    #[test]
+    fn void_elements_cannot_have_content() {
+        let src = "page \"p\" {\n  img src=\"/a.png\" alt=\"A\" {}\n  br { \"text\" }\n}\n";
+        let doc = parse_webc_file(src, "src/pages/p.webc").expect("parse ok");
+        let messages: Vec<String> = check_document(&doc).iter().map(|d| d.to_string()).collect();
+        assert_eq!(messages, vec!["error: br is a void element and cannot have content\n  --> src/pages/p.webc:3:3"]);
+    }
+
+    #[test]
+    fn braces_in_attribute_strings_are_checked() {
+        let src = "component Card {\n  props { label: String }\n  view { a href=\"/x/{label}\" title=\"{missing}\" \"go\" }\n}\n";
+        let doc = parse_webc_file(src, "src/components/card.webc").expect("parse ok");
+        let messages: Vec<String> = check_document(&doc).iter().map(|d| d.message.clone()).collect();
+        assert_eq!(messages, vec!["'missing' is not defined (in attribute)"]);
+    }
+}
@@ -1,18 +1,42 @@
 //! CSS Code Generator
 
+use crate::ast::StyleRule;
+use crate::ir::ComponentIR;
 use crate::theme::Theme;
 
 pub fn generate_css() -> String {
     "/* CSS output placeholder */".to_string()
 }
 
+/// Scoped style rules of the components, one block per component
+pub fn generate_component_css(components: &[ComponentIR]) -> String {
+    let mut css = String::new();
+    for component in components {
+        css.push_str(&format!("/* {} */\n", component.name));
+        css.push_str(&generate_rules(&component.rules));
+    }
+    css
+}
+
+pub fn generate_rules(rules: &[StyleRule]) -> String {
+    let mut css = String::new();
+    for rule in rules {
+        css.push_str(&format!("{} {{\n", rule.selector));
+        for property in &rule.properties {
+            css.push_str(&format!("  {}: {};\n", property.name, property.value));
+        }
+        css.push_str("}\n");
+    }
+    css
+}
+
 pub fn generate_theme_css(theme: &Theme) -> String {
     let mut css = String::new();
     css.push_str(":root {\n");
     
     // Generate color variables
     for (key, value) in &theme.colors {
-        css.push_str(&format!("  --color-{}: {};\n", key.replace("-", "-"), value));
+        css.push_str(&format!("  --color-{}: {};\n", key, value));
     }
     
     // Generate font variables
@@ -33,3 +57,13 @@ pub fn generate_theme_css(theme: &Theme) -> String {
     css.push_str("}\n");
     css
 }
+
+/// Styles for elements produced from Markdown content, driven by theme variables
+pub fn generate_content_css() -> String {
+    let mut css = String::new();
+    css.push_str(".md-heading {\n  font-family: var(--font-heading, inherit);\n  color: var(--color-text, inherit);\n}\n");
+    css.push_str(".md-link {\n  color: var(--color-primary, inherit);\n}\n");
+    css.push_str(".md-code {\n  font-family: var(--font-mono, monospace);\n  background: var(--color-background, transparent);\n  border-radius: var(--radius-card, 0);\n  padding: 1em;\n  overflow-x: auto;\n}\n");
+    css.push_str(".md-image {\n  max-width: 100%;\n  border-radius: var(--radius-card, 0);\n}\n");
+    css
+}
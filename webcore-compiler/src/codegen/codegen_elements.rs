@@ -0,0 +1,296 @@
+//! Custom Elements target: `webc build --target elements`
+//!
+//! Prints a component lowered by `ir::lower_element` as an ES module defining
+//! `<wc-kebab-name>`. Props are observed attributes and properties, the style
+//! and keyframes live in the shadow root and `emit(...)` dispatches a
+//! `CustomEvent`. The shadow root is rendered once; a state change only
+//! updates the text, attributes and `if`/`for` blocks bound to it, so focus,
+//! caret and input values survive handlers.
+
+use crate::ast::*;
+use crate::codegen::{codegen_css, codegen_html, codegen_js};
+use crate::data::DataSet;
+use crate::ir::{self, parse_emit, ElementIR, Node};
+use crate::theme::Theme;
+
+// Runtime shared by every generated module: state, bindings, prop conversion and modifiers
+const HELPERS: &str = r#"const KEYS = { enter: ['Enter'], esc: ['Escape'], escape: ['Escape'], space: [' '], tab: ['Tab'],
+  delete: ['Delete', 'Backspace'], up: ['ArrowUp'], down: ['ArrowDown'], left: ['ArrowLeft'], right: ['ArrowRight'] };
+const SYSTEM_KEYS = { ctrl: 'ctrlKey', shift: 'shiftKey', alt: 'altKey', meta: 'metaKey' };
+
+function convert(type, value) {
+  if (value === null) return type === 'Boolean' ? false : null;
+  if (type === 'Number') return Number(value);
+  if (type === 'Boolean') return value !== 'false';
+  if (type === 'String' || type === null) return value;
+  try { return JSON.parse(value); } catch (error) { return value; }
+}
+
+function accepts(event, modifiers) {
+  for (const modifier of modifiers) {
+    if (SYSTEM_KEYS[modifier] && !event[SYSTEM_KEYS[modifier]]) return false;
+    if (KEYS[modifier] && KEYS[modifier].indexOf(event.key) === -1) return false;
+    if (modifier.length === 1 && String(event.key).toLowerCase() !== modifier) return false;
+  }
+  if (modifiers.indexOf('prevent') !== -1) event.preventDefault();
+  if (modifiers.indexOf('stop') !== -1) event.stopPropagation();
+  return true;
+}
+
+// Same text as the build prints, so the rendered values are kept as they are
+function toText(value) {
+  if (value === undefined || value === null) return '';
+  if (Array.isArray(value)) return value.map(toText).join(', ');
+  return typeof value === 'object' ? JSON.stringify(value) : String(value);
+}
+
+// The values of one element, and what to update when one of them changes
+class State {
+  constructor(values) {
+    this.values = values;
+    this.listeners = {};
+  }
+
+  get(name) { return this.values[name]; }
+
+  set(name, value) {
+    this.values[name] = value;
+    (this.listeners[name] || []).slice().forEach((listener) => listener());
+  }
+
+  subscribe(name, listener) {
+    (this.listeners[name] = this.listeners[name] || []).push(listener);
+    return () => { this.listeners[name] = this.listeners[name].filter((l) => l !== listener); };
+  }
+}
+
+function read(state, path, locals) {
+  const parts = path.split('.');
+  let value = (locals && parts[0] in locals) ? locals[parts[0]] : state.get(parts[0]);
+  for (let i = 1; i < parts.length && value !== undefined && value !== null; i++) value = value[parts[i]];
+  return value;
+}
+
+function write(state, path, value) {
+  const parts = path.split('.');
+  if (parts.length === 1) return state.set(path, value);
+  const root = Object.assign({}, state.get(parts[0]));
+  let target = root;
+  for (let i = 1; i < parts.length - 1; i++) {
+    target[parts[i]] = Object.assign({}, target[parts[i]]);
+    target = target[parts[i]];
+  }
+  target[parts[parts.length - 1]] = value;
+  state.set(parts[0], root);
+}
+
+// Keeps the text, attributes and `if`/`for` blocks under `root` in sync with the
+// host's state and attaches the listeners; `locals` holds loop variables
+function bind(root, host, locals) {
+  const state = host._state;
+  const disposers = [];
+  const watch = (path, update) => {
+    update();
+    const name = path.split('.')[0];
+    if (!(locals && name in locals)) disposers.push(state.subscribe(name, update));
+  };
+  root.querySelectorAll('[data-webcore-interpolation]').forEach((element) => {
+    const path = element.getAttribute('data-webcore-interpolation');
+    watch(path, () => {
+      const text = toText(read(state, path, locals));
+      if (element.textContent !== text) element.textContent = text;
+    });
+  });
+  root.querySelectorAll('[data-webcore-attrs]').forEach((element) => {
+    const followed = JSON.parse(element.getAttribute('data-webcore-attrs'));
+    Object.keys(followed).forEach((name) => watch(followed[name], () => {
+      const value = read(state, followed[name], locals);
+      if (value === undefined || value === null || value === false) element.removeAttribute(name);
+      else element.setAttribute(name, value === true ? '' : typeof value === 'object' ? JSON.stringify(value) : String(value));
+    }));
+  });
+  root.querySelectorAll('[data-webcore-on]').forEach((element) => {
+    element.getAttribute('data-webcore-on').split(' ').forEach((entry) => {
+      const [type, id] = entry.split(':');
+      const [modifiers, handler] = HANDLERS[id];
+      let fired = false;
+      element.addEventListener(type, ($event) => {
+        if (fired || !accepts($event, modifiers)) return;
+        if (modifiers.indexOf('once') !== -1) fired = true;
+        handler(host, (path) => read(state, path, locals), (path, value) => write(state, path, value), $event, $event.detail);
+      }, { passive: modifiers.indexOf('passive') !== -1 });
+    });
+  });
+  root.querySelectorAll('template[data-webcore-if], template[data-webcore-unless], template[data-webcore-each]').forEach((template) => {
+    const path = template.getAttribute('data-webcore-if') || template.getAttribute('data-webcore-unless') || template.getAttribute('data-webcore-each');
+    const name = template.getAttribute('data-webcore-as');
+    let rendered = [];
+    const clear = () => rendered.forEach((block) => {
+      block.disposers.forEach((dispose) => dispose());
+      block.nodes.forEach((node) => node.remove());
+    });
+    watch(path, () => {
+      const value = read(state, path, locals);
+      const truthy = Array.isArray(value) ? value.length > 0 : !!value;
+      let scopes = [];
+      if (name) {
+        scopes = (Array.isArray(value) ? value : Object.values(value || {})).map((item) => Object.assign({}, locals, { [name]: item }));
+      } else if (template.hasAttribute('data-webcore-if') ? truthy : !truthy) {
+        scopes = [locals];
+      }
+      clear();
+      rendered = scopes.map((scope) => {
+        const fragment = template.content.cloneNode(true);
+        const nodes = Array.from(fragment.childNodes);
+        const blockDisposers = bind(fragment, host, scope);
+        template.parentNode.insertBefore(fragment, template);
+        return { nodes: nodes, disposers: blockDisposers };
+      });
+    });
+    disposers.push(clear);
+  });
+  return disposers;
+}
+"#;
+
+/// ES module defining the component as a custom element
+pub fn generate_element(document: &WebCoreDocument, component: &Component, data: &DataSet, theme: Option<&Theme>) -> Result<String, String> {
+    let element = ir::lower_element(document, component, data, theme)?;
+    if let Some(block) = find_script(&element.nodes) {
+        return Err(format!("{} blocks are not supported by the elements target", block.kind.label()));
+    }
+    Ok(render_element(&element, component))
+}
+
+fn render_element(element: &ElementIR, component: &Component) -> String {
+    let class_name = format!("Wc{}", element.name);
+    let mut js = String::new();
+    js.push_str(&format!("// <{}>: generated by WebCore from {}\n", element.tag, component.file));
+    for import in &element.elements {
+        js.push_str(&format!("import './{}.js';\n", import));
+    }
+    js.push('\n');
+    js.push_str(HELPERS);
+    js.push('\n');
+
+    let template = format!("<style>{}</style>{}", element_css(element), codegen_html::render_nodes(&element.nodes));
+    js.push_str(&format!("const TEMPLATE = {};\n\n", serde_json::Value::String(template)));
+
+    let props: Vec<String> = component.props.iter()
+        .map(|p| format!("'{}': ['{}', {}]", ir::kebab_case(&p.name), p.name,
+            p.type_.as_ref().map(|t| format!("'{}'", t)).unwrap_or_else(|| "null".to_string())))
+        .collect();
+    js.push_str(&format!("// Observed attribute → [property, type]\nconst PROPS = {{ {} }};\n\n", props.join(", ")));
+
+    js.push_str("// Handlers by id: modifiers, then the compiled code\nconst HANDLERS = {\n");
+    for handler in &element.handlers {
+        let modifiers: Vec<String> = handler.modifiers.iter().map(|m| format!("'{}'", m)).collect();
+        js.push_str(&format!("  '{}': [[{}], function(host, readPath, writePath, $event, $detail) {{ {}; }}],\n",
+            handler.id, modifiers.join(", "), compile_handler(&handler.expression)));
+    }
+    js.push_str("};\n\n");
+
+    js.push_str(&format!("class {} extends HTMLElement {{\n", class_name));
+    js.push_str("  static get observedAttributes() { return Object.keys(PROPS); }\n\n");
+    js.push_str("  constructor() {\n");
+    js.push_str("    super();\n");
+    js.push_str("    this.attachShadow({ mode: 'open' });\n");
+    js.push_str(&format!("    this._state = new State({});\n", serde_json::Value::Object(element.state.clone())));
+    js.push_str("    this.shadowRoot.innerHTML = TEMPLATE;\n");
+    js.push_str("    bind(this.shadowRoot, this, null);\n");
+    js.push_str("  }\n\n");
+    js.push_str("  attributeChangedCallback(name, previous, value) {\n");
+    js.push_str("    const [property, type] = PROPS[name];\n");
+    js.push_str("    this._state.set(property, convert(type, value));\n");
+    js.push_str("  }\n");
+    for prop in &component.props {
+        js.push_str(&format!("\n  get {0}() {{ return this._state.get('{0}'); }}\n", prop.name));
+        js.push_str(&format!("  set {0}(value) {{ this._state.set('{0}', value); }}\n", prop.name));
+    }
+    js.push_str("}\n\n");
+    js.push_str(&format!("if (!customElements.get('{0}')) customElements.define('{0}', {1});\n", element.tag, class_name));
+    js.push_str(&format!("export default {};\n", class_name));
+    js
+}
+
+/// `index.js` importing every generated element
+pub fn generate_index(components: &[&Component]) -> String {
+    let mut js = String::from("// Every WebCore component as a custom element\n");
+    for component in components {
+        js.push_str(&format!("import './{}.js';\n", ir::tag_name(&component.name)));
+    }
+    js
+}
+
+fn element_css(element: &ElementIR) -> String {
+    let mut css = String::from(":host { display: block; }\n");
+    css.push_str(&codegen_css::generate_rules(&element.rules));
+    for frames in &element.keyframes {
+        css.push_str(&format!("@keyframes {} {{\n{}\n}}\n", frames.name, frames.body.trim()));
+    }
+    css
+}
+
+// `emit(...)` dispatches a CustomEvent from the element, anything else runs on its state
+fn compile_handler(expr: &str) -> String {
+    match parse_emit(expr) {
+        Some((event, payload)) => format!("host.dispatchEvent(new CustomEvent('{}', {{ detail: {}, bubbles: true, composed: true }}))",
+            event, codegen_js::compile_payload(payload.as_deref())),
+        None => codegen_js::compile_expression(expr, "Math"),
+    }
+}
+
+// Scripts would not run inside a shadow root set through innerHTML
+fn find_script(nodes: &[Node]) -> Option<&RawBlock> {
+    nodes.iter().find_map(|node| match node {
+        Node::Raw(block) if block.kind == RawKind::Script => Some(block),
+        Node::Element { children, .. } | Node::Each { children, .. } | Node::Outlet(children) => find_script(children),
+        Node::If { then, otherwise, .. } => find_script(then).or_else(|| find_script(otherwise)),
+        _ => None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_webc;
+
+    #[test]
+    fn component_becomes_a_custom_element_module() {
+        let src = r#"
+component ItemList {
+  props { items: List heading: String }
+  events { select: Item }
+  state { count: Number = 0 }
+  view {
+    h2 "{heading}"
+    slot content
+    ul { for item in items { li { button on:click.stop={ emit("select", item) } "{item.name}" } } }
+    Badge itemCount={count} on:select={ count += 1 }
+  }
+  style { ul { padding: "0" color: color-primary } }
+}
+component Badge { props { itemCount: Number } view { span "{itemCount}" } }
+component Card { props { title: String } view { h2 "{title}" } }
+"#;
+        let doc = parse_webc(src).expect("parse ok");
+        let theme = crate::theme::parse_theme("[theme]\nname = \"t\"\n[theme.colors]\nprimary = \"#1E88E5\"\n[theme.fonts]\n[theme.radius]\n[theme.breakpoints]\n").expect("theme");
+        let js = generate_element(&doc, &doc.components["ItemList"], &DataSet::new(), Some(&theme)).expect("module");
+        assert_eq!(ir::tag_name("ItemList"), "wc-item-list");
+        assert!(js.starts_with("// <wc-item-list>: generated by WebCore from \nimport './wc-badge.js';\n"));
+        assert!(js.contains("const PROPS = { 'items': ['items', 'List'], 'heading': ['heading', 'String'] };"));
+        assert!(js.contains("this._state = new State({\"count\":0,\"heading\":null,\"items\":null});"));
+        assert!(js.contains("ul {\\n  padding: 0;\\n  color: var(--color-primary);\\n}"));
+        assert!(js.contains("<h2><span data-webcore-interpolation=\\\"heading\\\"></span></h2><slot></slot><ul><template data-webcore-each=\\\"items\\\" data-webcore-as=\\\"item\\\"><li><button data-webcore-on=\\\"click:h"));
+        assert!(js.contains("<wc-badge item-count=\\\"0\\\" data-webcore-on=\\\"select:h"));
+        assert!(js.contains("data-webcore-attrs=\\\"{&quot;item-count&quot;:&quot;count&quot;}\\\"></wc-badge>"));
+        assert!(js.contains("[['stop'], function(host, readPath, writePath, $event, $detail) { host.dispatchEvent(new CustomEvent('select', { detail: readPath('item', null), bubbles: true, composed: true })); }],"));
+        assert!(js.contains("function(host, readPath, writePath, $event, $detail) { writePath('count', (readPath('count', null) || 0) + (1)); }"));
+        // Only the bound nodes change after a handler
+        assert!(!js.contains("render()"));
+        assert!(js.contains("customElements.define('wc-item-list', WcItemList);"));
+
+        let error = generate_element(&doc, &doc.components["Card"], &DataSet::new(), None).unwrap_err();
+        assert_eq!(error, "prop 'title' would hide the element's own 'title' property; rename it");
+    }
+}
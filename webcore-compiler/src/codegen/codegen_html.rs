@@ -1,475 +1,316 @@
-//! HTML Code Generator
+//! HTML Code Generator: prints a lowered page (see `ir`)
 
+use crate::assets::{GlobalAssets, Script};
 use crate::ast::*;
+use crate::data::DataSet;
+use crate::i18n::Catalog;
+use crate::codegen::codegen_js;
+use crate::ir::{self, Attr, HandlerMapping, Node, PageIR};
+use crate::security::CspBuilder;
 
 // Options passed from the build to influence the page shell
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct HtmlPageOptions {
     pub lang: String,
     pub title: String,
-}
-
-#[derive(Debug, Clone)]
-pub struct HandlerMapping {
-    pub id: String,
-    pub event_type: String,
-    pub expression: String,
+    /// Relative prefix from the page to the dist root ("" or "../" for nested pages)
+    pub base_path: String,
+    /// Stylesheets the build wrote, relative to the dist root
+    pub stylesheets: Vec<String>,
+    /// Emit a Content-Security-Policy meta tag
+    pub csp: bool,
+    /// The policy shared by every page of the tree instead of the page's own,
+    /// so the SPA router can swap any page in under the one it started with
+    pub policy: Option<String>,
+    /// Messages for `t(...)` when building one tree per locale
+    pub catalog: Option<Catalog>,
+    /// `(hreflang, href)` of this page in every locale
+    pub alternates: Vec<(String, String)>,
+    /// Site path of the output tree (`/fr/`), which root-absolute links are
+    /// moved under; `None` leaves them as written
+    pub root: Option<String>,
+    /// `[app] router = "spa"`: mark the page content for the router to swap
+    pub spa: bool,
+    /// `[global]` scripts, stylesheets, meta and link tags from webc.toml
+    pub global: GlobalAssets,
 }
 
 pub struct HtmlGenerationResult {
     pub html: String,
     pub handlers: Vec<HandlerMapping>,
+    /// Raw escape hatches that ended up in the page, for the build report
+    pub raw_blocks: Vec<RawBlock>,
+    /// What the page's own policy allows, with `csp`
+    pub csp: Option<CspBuilder>,
 }
 
-pub fn generate_html(document: &WebCoreDocument, page_name: &str, options: &HtmlPageOptions) -> Result<HtmlGenerationResult, String> {
-    // Find the page
-    let page = document.pages.get(page_name)
-        .ok_or_else(|| format!("Page '{}' not found", page_name))?;
-    
-    // Find the layout (try MainLayout first, then default)
-    let layout = document.layouts.get("MainLayout")
-        .or_else(|| document.layouts.get("default"))
-        .ok_or_else(|| "No layout found (tried MainLayout and default)".to_string())?;
-    
-    // Generate HTML by combining layout and page content
+pub fn generate_html(document: &WebCoreDocument, page_name: &str, options: &HtmlPageOptions, data: &DataSet) -> Result<HtmlGenerationResult, String> {
+    let page = ir::lower_page(document, page_name, data, options.catalog.as_ref())?;
+    Ok(render_page(page, options))
+}
+
+/// The page shell around the lowered page
+pub fn render_page(mut page: PageIR, options: &HtmlPageOptions) -> HtmlGenerationResult {
+    if !options.spa {
+        page.nodes = without_outlet(page.nodes);
+    }
+    if let Some(root) = &options.root {
+        page.nodes = under_root(page.nodes, root);
+    }
     let mut html = String::new();
     html.push_str("<!DOCTYPE html>\n");
     html.push_str(&format!("<html lang=\"{}\">\n<head>\n", html_escape(&options.lang)));
     html.push_str("  <meta charset=\"UTF-8\">\n");
     html.push_str("  <meta name=\"viewport\" content=\"width=device-width, initial-scale=1.0\">\n");
+    for attrs in &options.global.meta {
+        let attrs: String = attrs.iter().map(|(name, value)| format!(" {}=\"{}\"", name, html_escape(value))).collect();
+        html.push_str(&format!("  <meta{}>\n", attrs));
+    }
+    // The page's own handlers; webcore.js is shared by every page
+    let handlers_js = (!page.handlers.is_empty())
+        .then(|| codegen_js::generate_handlers_js(&page.handlers).replace("</", "<\\/"));
+    let mut sources = None;
+    if options.csp {
+        let mut csp = CspBuilder::new();
+        for block in &page.raw_blocks {
+            csp.add_raw_block(block);
+        }
+        for_each_style_attribute(&page.nodes, &mut |value| csp.add_style_attribute(value));
+        if let Some(js) = &handlers_js {
+            csp.add_inline_script(js);
+        }
+        for script in options.global.before.iter().chain(&options.global.after) {
+            csp.add_script_src(&script.src.href(""));
+        }
+        for stylesheet in &options.global.stylesheets {
+            csp.add_style_src(&stylesheet.href(""));
+        }
+        let policy = options.policy.clone().unwrap_or_else(|| csp.build());
+        html.push_str(&format!("  <meta http-equiv=\"Content-Security-Policy\" content=\"{}\">\n", html_escape(&policy)));
+        sources = Some(csp);
+    }
     html.push_str(&format!("  <title>{}</title>\n", html_escape(&options.title)));
-    html.push_str("  <link rel=\"stylesheet\" href=\"theme.css\">\n");
+    for (lang, href) in &options.alternates {
+        html.push_str(&format!("  <link rel=\"alternate\" hreflang=\"{}\" href=\"{}\">\n", html_escape(lang), html_escape(href)));
+    }
+    for stylesheet in &options.stylesheets {
+        html.push_str(&format!("  <link rel=\"stylesheet\" href=\"{}{}\">\n", options.base_path, html_escape(stylesheet)));
+    }
+    for stylesheet in &options.global.stylesheets {
+        html.push_str(&format!("  <link rel=\"stylesheet\" href=\"{}\">\n", html_escape(&stylesheet.href(&options.base_path))));
+    }
+    for attrs in &options.global.links {
+        let attrs: String = attrs.iter()
+            .map(|(name, value)| format!(" {}=\"{}\"", name, html_escape(&value.href(&options.base_path))))
+            .collect();
+        html.push_str(&format!("  <link{}>\n", attrs));
+    }
+    for script in &options.global.before {
+        html.push_str(&script_tag(script, &options.base_path));
+    }
     html.push_str("</head>\n<body>\n");
-    
-    // Generate layout content, replacing slots with page content
-    let (layout_content, handlers) = generate_layout_with_page_and_components(layout, page, document)?;
-    html.push_str(&layout_content);
-    
-    html.push_str("  <script src=\"webcore.js\"></script>\n");
-    html.push_str("</body>\n</html>");
-    
-    Ok(HtmlGenerationResult { html, handlers })
-}
+    html.push_str(&render_nodes(&page.nodes));
 
-fn generate_layout_with_page(layout: &Layout, page: &Page) -> Result<String, String> {
-    generate_elements_with_slot_replacement(&layout.content, &page.content)
-}
+    if !page.state.is_empty() {
+        let json = serde_json::to_string(&page.state).unwrap_or_default();
+        html.push_str(&format!("<script type=\"application/json\" id=\"webcore-state\">{}</script>\n", json.replace("</", "<\\/")));
+    }
+    html.push_str(&format!("  <script src=\"{}webcore.js\"></script>\n", options.base_path));
+    if let Some(js) = &handlers_js {
+        html.push_str(&format!("  <script>{}</script>\n", js));
+    }
+    for script in &options.global.after {
+        html.push_str(&script_tag(script, &options.base_path));
+    }
+    html.push_str("</body>\n</html>");
 
-fn generate_layout_with_page_and_components(layout: &Layout, page: &Page, document: &WebCoreDocument) -> Result<(String, Vec<HandlerMapping>), String> {
-    generate_elements_with_slot_replacement_and_components(&layout.content, &page.content, document)
+    HtmlGenerationResult { html, handlers: page.handlers, raw_blocks: page.raw_blocks, csp: sources }
 }
 
-fn generate_elements(elements: &[Element]) -> Result<String, String> {
-    let mut result = String::new();
-    for element in elements {
-        result.push_str(&generate_element(element)?);
+fn script_tag(script: &Script, base_path: &str) -> String {
+    let mut attrs = format!(" src=\"{}\"", html_escape(&script.src.href(base_path)));
+    if let Some(kind) = &script.kind {
+        attrs.push_str(&format!(" type=\"{}\"", html_escape(kind)));
+    }
+    if script.is_async {
+        attrs.push_str(" async");
     }
-    Ok(result)
+    if script.defer {
+        attrs.push_str(" defer");
+    }
+    format!("  <script{}></script>\n", attrs)
 }
 
-fn generate_element(element: &Element) -> Result<String, String> {
-    match element {
-        Element::Text(text) => Ok(html_escape(text)),
-        Element::Tag { name, attributes, content } => {
-            // Map custom tags
-            if name == "text" {
-                // Render only the inner content without wrapping tag
-                return generate_elements(content);
-            }
-
-            let mapped_name = if name == "link" { "a" } else { name.as_str() };
-            let is_link = mapped_name == "a";
-            let mut resolved_href: Option<String> = None;
-
-            let mut result = String::new();
-            result.push_str(&format!("<{}", mapped_name));
-            
-            // Generate attributes
-            for attr in attributes {
-                match &attr.value {
-                    AttributeValue::String(value) => {
-                        if is_link && attr.name == "to" {
-                            resolved_href = Some(value.clone());
-                        } else {
-                            result.push_str(&format!(" {}=\"{}\"", attr.name, html_escape(value)));
-                        }
-                    }
-                    AttributeValue::Boolean(true) => {
-                        result.push_str(&format!(" {}", attr.name));
-                    }
-                    AttributeValue::Boolean(false) => {}
-                    AttributeValue::Expression(expr) => {
-                        if attr.name.starts_with("on:") {
-                            // Event handler: on:click={ count += 1 }
-                            let event_type = attr.name.strip_prefix("on:").unwrap_or("click");
-                            result.push_str(&format!(" data-webcore-event=\"{}\" data-webcore-handler=\"{}\"", event_type, expr));
-                        } else {
-                            result.push_str(&format!(" {}=\"{{}}\"", attr.name));
-                        }
-                    }
-                }
-            }
-
-            // Default href for link→a if missing
-            if is_link {
-                if let Some(h) = resolved_href {
-                    result.push_str(&format!(" href=\"{}\"", html_escape(&h)));
-                } else if !attributes.iter().any(|a| a.name == "href") {
-                    result.push_str(" href=\"#\"");
-                }
-            }
-            
-            result.push('>');
-            result.push_str(&generate_elements(content)?);
-            result.push_str(&format!("</{}>", mapped_name));
-            Ok(result)
-        }
-        Element::Slot(name) => Ok(format!("<!-- Slot: {} -->", name)),
-        Element::Component { name, attributes, content } => {
-            let mut result = String::new();
-            result.push_str(&format!("<{}", name));
-            
-            // Generate attributes
-            for attr in attributes {
-                match &attr.value {
-                    AttributeValue::String(value) => {
-                        result.push_str(&format!(" {}=\"{}\"", attr.name, html_escape(value)));
-                    }
-                    AttributeValue::Boolean(true) => {
-                        result.push_str(&format!(" {}", attr.name));
-                    }
-                    AttributeValue::Boolean(false) => {}
-                    AttributeValue::Expression(expr) => {
-                        if attr.name.starts_with("on:") {
-                            // Event handler: on:click={ count += 1 }
-                            let event_type = attr.name.strip_prefix("on:").unwrap_or("click");
-                            result.push_str(&format!(" data-webcore-event=\"{}\" data-webcore-handler=\"{}\"", event_type, expr));
-                        } else {
-                            result.push_str(&format!(" {}=\"{{}}\"", attr.name));
-                        }
-                    }
-                }
-            }
-            
-            result.push('>');
-            result.push_str(&generate_elements(content)?);
-            result.push_str(&format!("</{}>", name));
-            Ok(result)
-        }
-        Element::Interpolation(expr) => {
-            // Support mixed text like "prefix {var} suffix"
-            if let (Some(start), Some(end)) = (expr.find('{'), expr.find('}')) {
-                let prefix = html_escape(&expr[..start]);
-                let var_name = expr[start + 1..end].to_string();
-                let suffix = html_escape(&expr[end + 1..]);
-                Ok(format!("{}<span data-webcore-interpolation=\"{}\">0</span>{}", prefix, var_name, suffix))
-            } else {
-                Ok(format!("<span data-webcore-interpolation=\"{}\">0</span>", html_escape(expr)))
-            }
-        }
-    }
+// Outside SPA mode nothing is swapped, so the outlet leaves no trace in the markup
+fn without_outlet(nodes: Vec<Node>) -> Vec<Node> {
+    nodes.into_iter().flat_map(|node| match node {
+        Node::Outlet(children) => children,
+        Node::Element { tag, attributes, children } => vec![Node::Element { tag, attributes, children: without_outlet(children) }],
+        Node::If { condition, then, otherwise } => vec![Node::If { condition, then: without_outlet(then), otherwise: without_outlet(otherwise) }],
+        Node::Each { iterable, item, children } => vec![Node::Each { iterable, item, children: without_outlet(children) }],
+        other => vec![other],
+    }).collect()
 }
 
-fn generate_elements_with_slot_replacement(elements: &[Element], page_content: &[Element]) -> Result<String, String> {
-    let mut result = String::new();
-    for element in elements {
-        match element {
-            Element::Slot(slot_name) => {
-                if slot_name == "content" {
-                    // Replace content slot with page content
-                    result.push_str(&generate_elements(page_content)?);
-                } else {
-                    result.push_str(&format!("<!-- Slot: {} -->", slot_name));
-                }
-            }
-            Element::Tag { name, attributes, content: _content } => {
-                let mut tag_result = String::new();
-                tag_result.push_str(&format!("<{}", name));
-                
-                // Generate attributes
+// `style="..."` values written into the page, which its policy must list
+fn for_each_style_attribute(nodes: &[Node], visit: &mut impl FnMut(&str)) {
+    for node in nodes {
+        match node {
+            Node::Element { attributes, children, .. } => {
                 for attr in attributes {
-                    match &attr.value {
-                        AttributeValue::String(value) => {
-                            tag_result.push_str(&format!(" {}=\"{}\"", attr.name, html_escape(value)));
-                        }
-                        AttributeValue::Boolean(true) => {
-                            tag_result.push_str(&format!(" {}", attr.name));
-                        }
-                        AttributeValue::Boolean(false) => {}
-                        AttributeValue::Expression(_expr) => {
-                            tag_result.push_str(&format!(" {}=\"{{}}\"", attr.name));
-                        }
+                    match attr {
+                        Attr::Value { name, value } if name == "style" => visit(value),
+                        Attr::Binding { name, initial: Some(value), .. } if name == "style" => visit(value),
+                        _ => {}
                     }
                 }
-                
-                tag_result.push('>');
-                // This function shouldn't be called in the new system - it uses old logic
-                tag_result.push_str("<!-- OLD LOGIC CALLED -->");
-                tag_result.push_str(&format!("</{}>", name));
-                result.push_str(&tag_result);
+                for_each_style_attribute(children, visit);
             }
-            _ => {
-                // This function shouldn't be called in the new system - it uses old logic
-                result.push_str("<!-- OLD LOGIC CALLED -->");
+            Node::If { then, otherwise, .. } => {
+                for_each_style_attribute(then, visit);
+                for_each_style_attribute(otherwise, visit);
             }
+            Node::Each { children, .. } | Node::Outlet(children) => for_each_style_attribute(children, visit),
+            _ => {}
         }
     }
-    Ok(result)
 }
 
-fn generate_elements_with_slot_replacement_and_components(elements: &[Element], page_content: &[Element], document: &WebCoreDocument) -> Result<(String, Vec<HandlerMapping>), String> {
-    let mut result = String::new();
-    let mut all_handlers = Vec::new();
-    
-    for element in elements {
-        match element {
-            Element::Slot(slot_name) => {
-                if slot_name == "content" {
-                    // Replace content slot with page content
-                    let (content_html, content_handlers) = generate_elements_with_components(page_content, document)?;
-                    result.push_str(&content_html);
-                    all_handlers.extend(content_handlers);
-                } else {
-                    result.push_str(&format!("<!-- Slot: {} -->", slot_name));
-                }
-            }
-            Element::Tag { name, attributes, content } => {
-                let mut tag_result = String::new();
-                if name == "text" {
-                    // Render only inner content
-                    let (content_html, content_handlers) = generate_elements_with_components(content, document)?;
-                    result.push_str(&content_html);
-                    all_handlers.extend(content_handlers);
-                    continue;
+// `a href="/about.html"` → `/fr/about.html` in the fr/ tree; protocol-relative
+// `//host` links are left alone
+fn under_root(nodes: Vec<Node>, root: &str) -> Vec<Node> {
+    nodes.into_iter().map(|node| match node {
+        Node::Element { tag, attributes, children } => {
+            let attributes = attributes.into_iter().map(|attr| match attr {
+                Attr::Value { name, value } if tag == "a" && name == "href" && value.starts_with('/') && !value.starts_with("//") => {
+                    Attr::Value { name, value: format!("{}{}", root, &value[1..]) }
                 }
+                other => other,
+            }).collect();
+            Node::Element { tag, attributes, children: under_root(children, root) }
+        }
+        Node::If { condition, then, otherwise } => Node::If { condition, then: under_root(then, root), otherwise: under_root(otherwise, root) },
+        Node::Each { iterable, item, children } => Node::Each { iterable, item, children: under_root(children, root) },
+        Node::Outlet(children) => Node::Outlet(under_root(children, root)),
+        other => other,
+    }).collect()
+}
 
-                let mapped_name = if name == "link" { "a" } else { name.as_str() };
-                let is_link = mapped_name == "a";
-                let mut resolved_href: Option<String> = None;
-                tag_result.push_str(&format!("<{}", mapped_name));
-                
-                // Generate attributes
-                for attr in attributes {
-                    match &attr.value {
-                        AttributeValue::String(value) => {
-                            if is_link && attr.name == "to" {
-                                resolved_href = Some(value.clone());
-                            } else {
-                                tag_result.push_str(&format!(" {}=\"{}\"", attr.name, html_escape(value)));
-                            }
-                        }
-                        AttributeValue::Boolean(true) => {
-                            tag_result.push_str(&format!(" {}", attr.name));
-                        }
-                        AttributeValue::Boolean(false) => {}
-                        AttributeValue::Expression(_expr) => {
-                            tag_result.push_str(&format!(" {}=\"{{}}\"", attr.name));
-                        }
-                    }
-                }
+pub fn render_nodes(nodes: &[Node]) -> String {
+    let mut html = String::new();
+    for node in nodes {
+        render_node(node, &mut html);
+    }
+    html
+}
 
-                if is_link {
-                    if let Some(h) = resolved_href {
-                        tag_result.push_str(&format!(" href=\"{}\"", html_escape(&h)));
-                    } else if !attributes.iter().any(|a| a.name == "href") {
-                        tag_result.push_str(" href=\"#\"");
+fn render_node(node: &Node, html: &mut String) {
+    match node {
+        Node::Text(text) => html.push_str(&html_escape(text)),
+        Node::Element { tag, attributes, children } => {
+            html.push_str(&format!("<{}", tag));
+            // `click:h1f2e3d4c5b keydown:h0a1b2c3d4e`, read by the runtime's delegated listeners
+            let mut bound = Vec::new();
+            // Attribute name → state path, for the elements runtime
+            let mut followed = serde_json::Map::new();
+            for attr in attributes {
+                match attr {
+                    Attr::Handler { id, event } => bound.push(format!("{}:{}", event, id)),
+                    Attr::Binding { name, path, .. } => {
+                        followed.insert(name.clone(), serde_json::Value::String(path.clone()));
+                        render_attr(attr, html);
                     }
+                    _ => render_attr(attr, html),
                 }
-                
-                tag_result.push('>');
-                // Inside regular tags within the layout, do not perform slot replacement again
-                let (content_html, content_handlers) = generate_elements_with_components(content, document)?;
-                tag_result.push_str(&content_html);
-                tag_result.push_str(&format!("</{}>", mapped_name));
-                result.push_str(&tag_result);
-                all_handlers.extend(content_handlers);
             }
-            Element::Component { name, attributes, content } => {
-                // Find the component definition
-                if let Some(component) = document.components.get(name) {
-                    // Replace component with its view content
-                    let (component_html, component_handlers) = generate_elements_with_components(&component.view, document)?;
-                    result.push_str(&component_html);
-                    all_handlers.extend(component_handlers);
-                } else {
-                    // Component not found, generate as HTML element
-                    let mut comp_result = String::new();
-                    comp_result.push_str(&format!("<{}", name));
-                    
-                    // Generate attributes
-                    for attr in attributes {
-                        match &attr.value {
-                            AttributeValue::String(value) => {
-                                comp_result.push_str(&format!(" {}=\"{}\"", attr.name, html_escape(value)));
-                            }
-                            AttributeValue::Boolean(true) => {
-                                comp_result.push_str(&format!(" {}", attr.name));
-                            }
-                            AttributeValue::Boolean(false) => {}
-                            AttributeValue::Expression(_expr) => {
-                                comp_result.push_str(&format!(" {}=\"{{}}\"", attr.name));
-                            }
-                        }
-                    }
-                    
-                    comp_result.push('>');
-                    let (content_html, content_handlers) = generate_elements_with_components(content, document)?;
-                    comp_result.push_str(&content_html);
-                    comp_result.push_str(&format!("</{}>", name));
-                    result.push_str(&comp_result);
-                    all_handlers.extend(content_handlers);
-                }
+            if !bound.is_empty() {
+                html.push_str(&format!(" data-webcore-on=\"{}\"", html_escape(&bound.join(" "))));
+            }
+            if !followed.is_empty() {
+                html.push_str(&format!(" data-webcore-attrs=\"{}\"", html_escape(&serde_json::Value::Object(followed).to_string())));
             }
-            _ => {
-                let (element_html, element_handlers) = generate_element_with_components(element, document)?;
-                result.push_str(&element_html);
-                all_handlers.extend(element_handlers);
+            html.push('>');
+            // The checker rejects content on void elements, they have no end tag
+            if crate::html::is_void(tag) {
+                return;
             }
+            for child in children {
+                render_node(child, html);
+            }
+            html.push_str(&format!("</{}>", tag));
+        }
+        // Printed with the initial value, which the runtime keeps until the state changes
+        Node::Binding { path, initial } => html.push_str(&format!(
+            "<span data-webcore-interpolation=\"{}\">{}</span>", html_escape(path), html_escape(initial))),
+        Node::Translation { path, forms, initial } => html.push_str(&format!(
+            "<span data-webcore-i18n=\"{}\" data-webcore-forms=\"{}\">{}</span>",
+            html_escape(path), html_escape(&forms.to_string()), html_escape(initial))),
+        // Both branches ship as templates and the runtime inserts the matching one
+        Node::If { condition, then, otherwise } => {
+            html.push_str(&format!("<template data-webcore-if=\"{}\">{}</template>", html_escape(condition), render_nodes(then)));
+            if !otherwise.is_empty() {
+                html.push_str(&format!("<template data-webcore-unless=\"{}\">{}</template>", html_escape(condition), render_nodes(otherwise)));
+            }
+        }
+        Node::Each { iterable, item, children } => html.push_str(&format!(
+            "<template data-webcore-each=\"{}\" data-webcore-as=\"{}\">{}</template>", html_escape(iterable), html_escape(item), render_nodes(children))),
+        Node::Raw(block) => html.push_str(&generate_raw(block)),
+        Node::Slot(name) => html.push_str(&format!("<!-- Slot: {} -->", name)),
+        Node::Outlet(children) => {
+            html.push_str("<!--webcore:outlet-->");
+            for child in children {
+                render_node(child, html);
+            }
+            html.push_str("<!--/webcore:outlet-->");
         }
     }
-    Ok((result, all_handlers))
 }
 
-fn generate_elements_with_components(elements: &[Element], document: &WebCoreDocument) -> Result<(String, Vec<HandlerMapping>), String> {
-    let mut result = String::new();
-    let mut all_handlers = Vec::new();
-    let mut counter = 0;
-    
-    for element in elements {
-        let (element_html, handlers) = generate_element_with_components_counter(element, document, &mut counter)?;
-        result.push_str(&element_html);
-        all_handlers.extend(handlers);
-    }
-    Ok((result, all_handlers))
-}
-
-fn generate_element_with_components(element: &Element, document: &WebCoreDocument) -> Result<(String, Vec<HandlerMapping>), String> {
-    generate_element_with_components_counter(element, document, &mut 0)
+fn render_attr(attr: &Attr, html: &mut String) {
+    match attr {
+        Attr::Value { name, value } => html.push_str(&format!(" {}=\"{}\"", name, html_escape(value))),
+        Attr::Flag(name) => html.push_str(&format!(" {}", name)),
+        // Collected into one `data-webcore-on` by the element
+        Attr::Handler { .. } => {}
+        // Read by the runtime when the element enters or leaves a runtime `if`/`for` block
+        Attr::Directive { kind, name, options } => {
+            html.push_str(&format!(" data-webcore-{}=\"{}\"", kind, html_escape(name)));
+            if let Some(options) = options {
+                html.push_str(&format!(" data-webcore-{}-options=\"{}\"", kind, html_escape(&options.to_string())));
+            }
+        }
+        Attr::Unbound(name) => html.push_str(&format!(" {}=\"{{}}\"", name)),
+        Attr::Binding { name, initial: Some(value), .. } => html.push_str(&format!(" {}=\"{}\"", name, html_escape(value))),
+        Attr::Binding { initial: None, .. } => {}
+    }
 }
 
-fn generate_element_with_components_counter(element: &Element, document: &WebCoreDocument, counter: &mut usize) -> Result<(String, Vec<HandlerMapping>), String> {
-    match element {
-        Element::Text(text) => Ok((html_escape(text), Vec::new())),
-        Element::Tag { name, attributes, content } => {
-            let mut result = String::new();
-            let mut handlers = Vec::new();
-            if name == "text" {
-                let (content_html, content_handlers) = generate_elements_with_components(content, document)?;
-                return Ok((content_html, content_handlers));
+// Raw content is marked so it stays recognisable in the generated output
+fn generate_raw(block: &RawBlock) -> String {
+    match block.kind {
+        RawKind::Html => format!("<!-- webcore:raw html -->{}<!-- /webcore:raw -->", block.content),
+        RawKind::Script => {
+            let mut result = String::from("<script data-webcore-raw");
+            if let Some(src) = &block.src {
+                result.push_str(&format!(" src=\"{}\"", html_escape(src)));
             }
-
-            let mapped_name = if name == "link" { "a" } else { name.as_str() };
-            let is_link = mapped_name == "a";
-            let mut resolved_href: Option<String> = None;
-            result.push_str(&format!("<{}", mapped_name));
-            
-            // Generate attributes
-            for attr in attributes {
+            for attr in &block.attributes {
                 match &attr.value {
-                    AttributeValue::String(value) => {
-                        if is_link && attr.name == "to" {
-                            resolved_href = Some(value.clone());
-                        } else {
-                            result.push_str(&format!(" {}=\"{}\"", attr.name, html_escape(value)));
-                        }
-                    }
-                    AttributeValue::Boolean(true) => {
-                        result.push_str(&format!(" {}", attr.name));
-                    }
-                    AttributeValue::Boolean(false) => {}
-                    AttributeValue::Expression(expr) => {
-                        if attr.name.starts_with("on:") {
-                            // Event handler: on:click={ count += 1 }
-                            let event_type = attr.name.strip_prefix("on:").unwrap_or("click");
-                            *counter += 1;
-                            let handler_id = format!("btn{}", counter);
-                            
-                            // Add handler to our collection
-                            handlers.push(HandlerMapping {
-                                id: handler_id.clone(),
-                                event_type: event_type.to_string(),
-                                expression: expr.clone(),
-                            });
-                            
-                            // Use native HTML5 event attributes with simple IDs
-                            match event_type {
-                                "click" => result.push_str(&format!(" id=\"{}\" onclick=\"webcore_handle_click('{}')\"", handler_id, handler_id)),
-                                "submit" => result.push_str(&format!(" id=\"{}\" onsubmit=\"webcore_handle_submit('{}')\"", handler_id, handler_id)),
-                                "change" => result.push_str(&format!(" id=\"{}\" onchange=\"webcore_handle_change('{}')\"", handler_id, handler_id)),
-                                "input" => result.push_str(&format!(" id=\"{}\" oninput=\"webcore_handle_input('{}')\"", handler_id, handler_id)),
-                                _ => result.push_str(&format!(" id=\"{}\" on{}=\"webcore_handle_event('{}', '{}')\"", handler_id, event_type, event_type, handler_id)),
-                            }
-                        } else {
-                            result.push_str(&format!(" {}=\"{{}}\"", attr.name));
-                        }
-                    }
+                    AttributeValue::String(value) => result.push_str(&format!(" {}=\"{}\"", attr.name, html_escape(value))),
+                    AttributeValue::Boolean(true) => result.push_str(&format!(" {}", attr.name)),
+                    _ => {}
                 }
             }
-
-            if is_link {
-                if let Some(h) = resolved_href {
-                    result.push_str(&format!(" href=\"{}\"", html_escape(&h)));
-                } else if !attributes.iter().any(|a| a.name == "href") {
-                    result.push_str(" href=\"#\"");
-                }
-            }
-            
             result.push('>');
-            let (content_html, content_handlers) = generate_elements_with_components(content, document)?;
-            result.push_str(&content_html);
-            result.push_str(&format!("</{}>", mapped_name));
-            handlers.extend(content_handlers);
-            Ok((result, handlers))
-        }
-        Element::Slot(name) => Ok((format!("<!-- Slot: {} -->", name), Vec::new())),
-        Element::Component { name, attributes, content } => {
-            // Find the component definition
-            if let Some(component) = document.components.get(name) {
-                // Replace component with its view content
-                generate_elements_with_components(&component.view, document)
-            } else {
-                // Component not found, generate as HTML element
-                let mut result = String::new();
-                result.push_str(&format!("<{}", name));
-                
-                // Generate attributes
-                for attr in attributes {
-                    match &attr.value {
-                        AttributeValue::String(value) => {
-                            result.push_str(&format!(" {}=\"{}\"", attr.name, html_escape(value)));
-                        }
-                        AttributeValue::Boolean(true) => {
-                            result.push_str(&format!(" {}", attr.name));
-                        }
-                        AttributeValue::Boolean(false) => {}
-                        AttributeValue::Expression(_expr) => {
-                            result.push_str(&format!(" {}=\"{{}}\"", attr.name));
-                        }
-                    }
-                }
-                
-                result.push('>');
-                let (content_html, content_handlers) = generate_elements_with_components(content, document)?;
-                result.push_str(&content_html);
-                result.push_str(&format!("</{}>", name));
-                Ok((result, content_handlers))
-            }
-        }
-        Element::Interpolation(expr) => {
-            // Support mixed text like "prefix {var} suffix"
-            if let (Some(start), Some(end)) = (expr.find('{'), expr.find('}')) {
-                let prefix = html_escape(&expr[..start]);
-                let var_name = expr[start + 1..end].to_string();
-                let suffix = html_escape(&expr[end + 1..]);
-                Ok((format!("{}<span data-webcore-interpolation=\"{}\">0</span>{}", prefix, var_name, suffix), Vec::new()))
-            } else {
-                Ok((format!("<span data-webcore-interpolation=\"{}\">0</span>", html_escape(expr)), Vec::new()))
-            }
+            result.push_str(&block.content);
+            result.push_str("</script>");
+            result
         }
+        RawKind::Style => format!("<style data-webcore-raw>{}</style>", block.content),
     }
 }
 
-fn html_escape(text: &str) -> String {
+pub fn html_escape(text: &str) -> String {
     text.replace('&', "&amp;")
         .replace('<', "&lt;")
         .replace('>', "&gt;")
@@ -482,25 +323,320 @@ mod tests {
     use super::*;
 
     #[test]
-    fn event_fallback_uses_on_event_attribute() {
+    fn handlers_bind_through_a_data_attribute_and_keep_user_ids() {
         // Build minimal doc with a button using an unknown event
         let mut doc = WebCoreDocument {
             app: None,
             layouts: std::collections::HashMap::new(),
             pages: std::collections::HashMap::new(),
             components: std::collections::HashMap::new(),
+            stores: std::collections::HashMap::new(),
+            global_styles: vec![],
         };
-        doc.layouts.insert("MainLayout".to_string(), Layout { name: "MainLayout".to_string(), content: vec![
-            Element::Slot("content".to_string())
-        ]});
-        doc.pages.insert("test".to_string(), Page { name: "test".to_string(), content: vec![
+        doc.layouts.insert("MainLayout".to_string(), Layout { name: "MainLayout".to_string(), extends: None, content: vec![
+            Element::Slot { name: "content".to_string(), span: Span::default() }
+        ], file: String::new(), span: Span::default() });
+        doc.pages.insert("test".to_string(), Page { name: "test".to_string(), layout: None, data: vec![], content: vec![
             Element::Tag { name: "button".to_string(), attributes: vec![
-                Attribute { name: "on:foo".to_string(), value: AttributeValue::Expression("count += 1".to_string()) }
-            ], content: vec![] }
-        ]});
+                Attribute { name: "id".to_string(), value: AttributeValue::String("save".to_string()), span: Span::default() },
+                Attribute { name: "on:foo".to_string(), value: AttributeValue::Expression("count += 1".to_string()), span: Span::default() }
+            ], content: vec![], span: Span::default() }
+        ], file: String::new(), span: Span::default() });
+
+        let res = generate_html(&doc, "test", &HtmlPageOptions::default(), &DataSet::new()).expect("html ok");
+        let id = &res.handlers[0].id;
+        assert!(res.html.contains(&format!("<button id=\"save\" data-webcore-on=\"foo:{}\"></button>", id)));
+        assert!(res.html.contains(&format!("<script>window.__webcore_register__(function(writePath) {{ return {{\n  '{}': function(readPath, $event, $detail) {{", id)));
+        assert!(!res.html.contains(" onfoo="));
+    }
+
+    #[test]
+    fn for_loop_renders_build_time_data() {
+        let src = r#"
+layout MainLayout { slot }
+page "blog" {
+  data posts = load("posts.json")
+  for post in posts {
+    link to={post.slug} "{post.title}"
+    p { "By {post.author}" }
+    img src="/img{post.slug}.png" alt="{post.title} ({post.author})"
+  }
+}
+"#;
+        let doc = crate::parser::parse_webc(src).expect("parse ok");
+        let mut data = DataSet::new();
+        data.insert("posts.json".to_string(), serde_json::json!([
+            { "title": "Hello <world>", "slug": "/hello", "author": "Ann" },
+            { "title": "Second", "slug": "/second", "author": "Bob" }
+        ]));
+        let res = generate_html(&doc, "blog", &HtmlPageOptions::default(), &data).expect("html ok");
+        assert!(res.html.contains("<a href=\"/hello\">Hello &lt;world&gt;</a><p>By Ann</p><img src=\"/img/hello.png\" alt=\"Hello &lt;world&gt; (Ann)\"><a href=\"/second\">Second</a>"), "{}", res.html);
+    }
+
+    #[test]
+    fn nested_layouts_wrap_page_inside_parent_slot() {
+        let src = r#"
+app Site { layout: MainLayout }
+layout MainLayout { header { "Site" } main { slot } }
+layout Docs extends MainLayout { aside { "Sidebar" } article { slot } }
+layout Marketing { section { Panel { slot } } }
+component Panel { view { div { slot } } }
+page "guide" layout Docs { h1 "Guide" }
+page "promo" layout Marketing { h1 "Promo" }
+page "home" { h1 "Home" }
+"#;
+        let doc = crate::parser::parse_webc(src).expect("parse ok");
+        let guide = generate_html(&doc, "guide", &HtmlPageOptions::default(), &DataSet::new()).expect("html ok").html;
+        assert!(guide.contains("<header>Site</header><main><aside>Sidebar</aside><article><h1>Guide</h1></article></main>"));
+        let promo = generate_html(&doc, "promo", &HtmlPageOptions::default(), &DataSet::new()).expect("html ok").html;
+        assert!(promo.contains("<section><div><h1>Promo</h1></div></section>"), "{}", promo);
+        assert!(!promo.contains("<header>"));
+        let home = generate_html(&doc, "home", &HtmlPageOptions::default(), &DataSet::new()).expect("html ok").html;
+        assert!(home.contains("<main><h1>Home</h1></main>"));
+    }
+
+    #[test]
+    fn layout_inheritance_cycle_is_an_error() {
+        let src = "layout A extends B { slot }\nlayout B extends A { slot }\npage \"p\" layout A { h1 \"x\" }";
+        let doc = crate::parser::parse_webc(src).expect("parse ok");
+        let err = generate_html(&doc, "p", &HtmlPageOptions::default(), &DataSet::new()).err().expect("cycle detected");
+        assert!(err.contains("A -> B -> A"));
+    }
+
+    #[test]
+    fn translations_resolve_from_the_locale_catalog() {
+        let src = r#"
+layout MainLayout { slot }
+component CartPage {
+  state { count: Number = 0 }
+  view {
+    h1 t("nav.home")
+    a title={t("nav.home")} href="/" "Home"
+    p t("cart.items", 3)
+    p "Cart: {t('cart.items', count)}"
+  }
+}
+page "cart" { CartPage }
+"#;
+        let doc = crate::parser::parse_webc(src).expect("parse ok");
+        let catalog = crate::i18n::parse_catalog("en", "[nav]\nhome = \"Home\"\n[cart.items]\none = \"{count} item\"\nother = \"{count} items\"\n").expect("catalog");
+        let opts = HtmlPageOptions { catalog: Some(catalog), root: Some("/en/".to_string()), ..Default::default() };
+        let res = generate_html(&doc, "cart", &opts, &DataSet::new()).expect("html ok");
+        assert!(res.html.contains("<h1>Home</h1><a title=\"Home\" href=\"/en/\">Home</a><p>3 items</p>"));
+        assert!(res.html.contains("<span data-webcore-i18n=\"c1$count\" data-webcore-forms=\"{&quot;one&quot;:&quot;{count} item&quot;"));
+    }
+
+    #[test]
+    fn locale_pages_link_their_alternates() {
+        let doc = crate::parser::parse_webc("layout MainLayout { slot }\npage \"cart\" { h1 \"Cart\" }").expect("parse ok");
+        let opts = HtmlPageOptions {
+            lang: "en".to_string(),
+            alternates: vec![("fr".to_string(), "https://example.com/fr/cart.html".to_string())],
+            ..Default::default()
+        };
+        let res = generate_html(&doc, "cart", &opts, &DataSet::new()).expect("html ok");
+        assert!(res.html.contains("<html lang=\"en\">"));
+        assert!(res.html.contains("<link rel=\"alternate\" hreflang=\"fr\" href=\"https://example.com/fr/cart.html\">"));
+    }
+
+    #[test]
+    fn recursive_component_stops_when_data_runs_out() {
+        let src = r#"
+layout MainLayout { slot }
+component TreeNode {
+  props { node: Object }
+  view {
+    li {
+      text "{node.name}"
+      if node.children {
+        ul { for child in node.children { TreeNode node={child} } }
+      }
+    }
+  }
+}
+page "tree" {
+  data root = load("tree.json")
+  ul { TreeNode node={root} }
+}
+"#;
+        let doc = crate::parser::parse_webc(src).expect("parse ok");
+        let mut data = DataSet::new();
+        data.insert("tree.json".to_string(), serde_json::json!({
+            "name": "root",
+            "children": [{ "name": "a" }, { "name": "b", "children": [{ "name": "c" }] }]
+        }));
+        let res = generate_html(&doc, "tree", &HtmlPageOptions::default(), &data).expect("html ok");
+        assert!(res.html.contains("<ul><li>root<ul><li>a</li><li>b<ul><li>c</li></ul></li></ul></li></ul>"));
+    }
+
+    #[test]
+    fn endless_recursion_stops_at_the_depth_limit() {
+        let src = r#"
+layout MainLayout { slot }
+component Forever {
+  props { node: Object }
+  view { if node { Forever node={node} } }
+}
+page "forever" {
+  data root = load("tree.json")
+  Forever node={root}
+}
+"#;
+        let doc = crate::parser::parse_webc(src).expect("parse ok");
+        let mut data = DataSet::new();
+        data.insert("tree.json".to_string(), serde_json::json!({ "name": "root" }));
+        let err = generate_html(&doc, "forever", &HtmlPageOptions::default(), &data).err().expect("depth limit hit");
+        assert!(err.contains("Component 'Forever' is nested more than 64 levels deep"));
+    }
+
+    #[test]
+    fn resource_branches_render_as_runtime_templates() {
+        let src = r#"
+layout MainLayout { slot }
+component Users {
+  resource users = fetch("/api/users")
+  view {
+    button on:click={refresh(users)} "Reload"
+    if users.loading { p transition:fade={ duration: 150 } "Loading" } else {
+      ul { for user in users.data { li "{user.name}" } }
+    }
+  }
+}
+page "users" { Users }
+"#;
+        let doc = crate::parser::parse_webc(src).expect("parse ok");
+        let res = generate_html(&doc, "users", &HtmlPageOptions::default(), &DataSet::new()).expect("html ok");
+        assert!(res.html.contains("<template data-webcore-if=\"c1$users.loading\"><p data-webcore-transition=\"fade\" data-webcore-transition-options=\"{&quot;duration&quot;:150}\">Loading</p></template>"));
+        assert!(res.html.contains("<template data-webcore-unless=\"c1$users.loading\"><ul><template data-webcore-each=\"c1$users.data\" data-webcore-as=\"user\">"));
+        assert!(res.html.contains("\"resources\":[{\"dependencies\":[],\"name\":\"c1$users\",\"url\":\"/api/users\"}]"));
+        assert!(res.html.contains("window.__webcore_resources__['c1$users']()"), "{}", res.html);
+    }
+
+    #[test]
+    fn component_listeners_receive_emitted_payloads() {
+        let src = r#"
+layout MainLayout { slot }
+component ItemList {
+  props { items: List }
+  events { select: Item }
+  view { ul { for item in items { li { button on:click={ emit("select", item) } "{item.name}" } } } }
+}
+page "shop" {
+  data products = load("products.json")
+  ItemList items={products} on:select={ selected = $detail }
+}
+"#;
+        let doc = crate::parser::parse_webc(src).expect("parse ok");
+        let mut data = DataSet::new();
+        data.insert("products.json".to_string(), serde_json::json!([{ "name": "Tea" }]));
+        let res = generate_html(&doc, "shop", &HtmlPageOptions::default(), &data).expect("html ok");
+        let listener = res.handlers.iter().find(|h| h.kind == ir::HandlerKind::Component).expect("listener");
+        assert_eq!((listener.id.as_str(), listener.expression.as_str(), listener.instance.as_deref()), ("c1:select", "selected=$detail", None));
+        let emit = res.handlers.iter().find(|h| h.kind == ir::HandlerKind::Dom).expect("button handler");
+        assert_eq!((emit.expression.as_str(), emit.instance.as_deref()), ("emit(\"select\", {\"name\":\"Tea\"})", Some("c1")));
+        assert!(res.html.contains(&format!("<button data-webcore-on=\"click:{}\">Tea</button>", emit.id)));
+    }
 
-        let opts = HtmlPageOptions { lang: "fr".to_string(), title: "t".to_string() };
-        let res = generate_html(&doc, "test", &opts).expect("html ok");
-        assert!(res.html.contains("onfoo=\"webcore_handle_event('foo',"));
+    #[test]
+    fn handlers_in_a_runtime_loop_read_the_row_they_were_rendered_for() {
+        let src = r#"
+layout MainLayout { slot }
+component ItemList {
+  state { items: List = [] }
+  events { select: Item }
+  view { for item in items { button on:click={ emit("select", item) } "{item.name}" } }
+}
+page "shop" { ItemList on:select={ selected = $detail } }
+"#;
+        let doc = crate::parser::parse_webc(src).expect("parse ok");
+        let res = generate_html(&doc, "shop", &HtmlPageOptions::default(), &DataSet::new()).expect("html ok");
+        assert!(res.html.contains("<template data-webcore-each=\"c1$items\" data-webcore-as=\"item\">"), "{}", res.html);
+        let emit = res.handlers.iter().find(|h| h.kind == ir::HandlerKind::Dom).expect("button handler");
+        // `readPath` is given to each call, scoped to the template row of the element
+        assert!(res.html.contains(&format!("'{}': function(readPath, $event, $detail) {{\n    try {{\n      window.__webcore_emit__('c1', 'select', readPath('item', null))", emit.id)), "{}", res.html);
     }
+
+    #[test]
+    fn event_modifiers_are_kept_on_the_handler() {
+        let src = r#"
+layout MainLayout { slot }
+page "editor" {
+  form on:submit.prevent={ saved = true } { input on:keydown.ctrl.s={ draft = $event.target.value } }
+  div on:scroll.passive={ scrolled = true }
+}
+"#;
+        let doc = crate::parser::parse_webc(src).expect("parse ok");
+        let res = generate_html(&doc, "editor", &HtmlPageOptions::default(), &DataSet::new()).expect("html ok");
+        let summary: Vec<(&str, String, &str)> = res.handlers.iter()
+            .map(|h| (h.event_type.as_str(), h.modifiers.join("."), h.expression.as_str())).collect();
+        assert_eq!(summary, vec![
+            ("submit", "prevent".to_string(), "saved=true"),
+            ("keydown", "ctrl.s".to_string(), "draft=$event.target.value"),
+            ("scroll", "passive".to_string(), "scrolled=true"),
+        ]);
+        assert!(res.html.contains(&format!("<form data-webcore-on=\"submit:{}\">", res.handlers[0].id)));
+        assert!(res.html.contains(&format!("<div data-webcore-on=\"scroll:{}\"></div>", res.handlers[2].id)));
+    }
+
+    #[test]
+    fn component_state_is_private_to_each_instance() {
+        let src = r#"
+layout MainLayout { slot }
+component Counter {
+  props { label: String }
+  state { count: Number = 0 }
+  view { button on:click={ count += 1 } "{label} {count}" }
+}
+page "twice" {
+  div { Counter label="A" }
+  div { Counter label="B" }
+}
+"#;
+        let doc = crate::parser::parse_webc(src).expect("parse ok");
+        let res = generate_html(&doc, "twice", &HtmlPageOptions::default(), &DataSet::new()).expect("html ok");
+        let expressions: Vec<&str> = res.handlers.iter().map(|h| h.expression.as_str()).collect();
+        assert_eq!(expressions, vec!["c1$count+=1", "c2$count+=1"]);
+        // Same component, same position in its view: the path to each instance tells them apart
+        assert_ne!(res.handlers[0].id, res.handlers[1].id);
+        let again = generate_html(&doc, "twice", &HtmlPageOptions::default(), &DataSet::new()).expect("html ok");
+        assert_eq!(again.handlers[0].id, res.handlers[0].id);
+        assert!(res.html.contains("A <span data-webcore-interpolation=\"c1$count\">"));
+        assert!(res.html.contains("<script type=\"application/json\" id=\"webcore-state\">{\"resources\":[],\"values\":{\"c1$count\":0,\"c2$count\":0}}</script>"));
+    }
+
+    #[test]
+    fn interpolations_are_rendered_with_their_initial_values() {
+        let src = r#"
+layout MainLayout { main { slot } }
+store Cart { items: List = ["tea", "cake"] }
+component Badge {
+  props { label: String }
+  view { span { "{label}" } }
+}
+component Greeting {
+  use Cart
+  state { name: String = "Ada" }
+  resource users = fetch("/api/users")
+  view { p { "{name}" } Badge label={name} {} span { "{Cart.items}" } span { "{users.loading}" } }
+}
+page "home" {
+  Greeting
+}
+"#;
+        let doc = crate::parser::parse_webc(src).expect("parse ok");
+        let res = generate_html(&doc, "home", &HtmlPageOptions::default(), &DataSet::new()).expect("html ok");
+        assert!(res.html.contains("<p><span data-webcore-interpolation=\"c1$name\">Ada</span></p>"));
+        assert!(res.html.contains("<span><span data-webcore-interpolation=\"c1$name\">Ada</span></span>"));
+        assert!(res.html.contains("<span data-webcore-interpolation=\"Cart.items\">tea, cake</span>"));
+        assert!(res.html.contains("<span data-webcore-interpolation=\"c1$users.loading\">true</span>"));
+    }
+
+    #[test]
+    fn void_elements_have_no_end_tag() {
+        let src = "layout MainLayout { slot }\npage \"p\" { img src=\"/a.png\" alt=\"A\" {} br {} input type=\"text\" {} }";
+        let doc = crate::parser::parse_webc(src).expect("parse ok");
+        let res = generate_html(&doc, "p", &HtmlPageOptions::default(), &DataSet::new()).expect("html ok");
+        assert!(res.html.contains("<body>\n<img src=\"/a.png\" alt=\"A\"><br><input type=\"text\">"));
+    }
+
 }
@@ -1,66 +1,110 @@
 //! JavaScript Code Generator for WebCore Runtime
 
 use crate::ast::*;
-use crate::codegen::codegen_html::HandlerMapping;
-
-fn compile_expression(expr: &str) -> String {
-    let mut compiled = expr.to_string();
-    
-    // Handle += operator (e.g., count += 1)
-    if compiled.contains("+=") {
-        let parts: Vec<&str> = compiled.split("+=").collect();
-        if parts.len() == 2 {
-            let var_name = parts[0].trim();
-            let value = parts[1].trim();
-            compiled = format!("window.__webcore_state__.set('{}', (window.__webcore_state__.get('{}') || 0) + {})", 
-                             var_name, var_name, value);
-        }
-    }
-    // Handle -= operator (e.g., count -= 1)
-    else if compiled.contains("-=") {
-        let parts: Vec<&str> = compiled.split("-=").collect();
-        if parts.len() == 2 {
-            let var_name = parts[0].trim();
-            let value = parts[1].trim();
-            compiled = format!("window.__webcore_state__.set('{}', (window.__webcore_state__.get('{}') || 0) - {})", 
-                             var_name, var_name, value);
-        }
+use crate::ir::{map_names, parse_emit, HandlerKind, HandlerMapping};
+
+/// Handler code over `readPath(path, locals)` and `writePath(path, value)`;
+/// `utils` holds `max`, `min` and the other helpers
+pub(crate) fn compile_expression(expr: &str, utils: &str) -> String {
+    let expr = expr.trim();
+    
+    // Handle refresh(users): fetch a resource again
+    if let Some(name) = expr.strip_prefix("refresh(").and_then(|rest| rest.strip_suffix(')')) {
+        return format!("window.__webcore_resources__['{}'] && window.__webcore_resources__['{}']()", name.trim(), name.trim());
     }
-    // Handle = operator (e.g., count = max(0, count - 1))
-    else if compiled.contains("=") && !compiled.contains("==") && !compiled.contains("!=") {
-        let parts: Vec<&str> = compiled.split("=").collect();
-        if parts.len() == 2 {
-            let var_name = parts[0].trim();
-            let mut value_expr = parts[1].trim().to_string();
-            
-            // Replace max() calls
-            value_expr = value_expr.replace("max(", "window.__webcore_utils__.max(");
-            value_expr = value_expr.replace("min(", "window.__webcore_utils__.min(");
-            
-            // Replace variable references with state getters
-            value_expr = value_expr.replace("count", "window.__webcore_state__.get('count')");
-            
-            compiled = format!("window.__webcore_state__.set('{}', {})", var_name, value_expr);
+    
+    // Handle += / -= (e.g., count += 1, Cart.total -= price)
+    for op in ["+=", "-="] {
+        if let Some((target, value)) = expr.split_once(op) {
+            let target = target.trim();
+            return format!("writePath('{}', (readPath('{}', null) || 0) {} ({}))",
+                           target, target, &op[..1], compile_value(value, utils));
         }
     }
-    // Handle other expressions
-    else {
-        // Replace max() calls
-        compiled = compiled.replace("max(", "window.__webcore_utils__.max(");
-        compiled = compiled.replace("min(", "window.__webcore_utils__.min(");
-        
-        // Replace variable references with state getters
-        compiled = compiled.replace("count", "window.__webcore_state__.get('count')");
+    
+    // Handle = (e.g., count = max(0, count - 1))
+    if let Some(index) = assignment_index(expr) {
+        return format!("writePath('{}', {})", expr[..index].trim(), compile_value(&expr[index + 1..], utils));
     }
     
+    compile_value(expr, utils)
+}
+
+// Position of a plain `=`, not part of `==`, `!=`, `<=`, `>=` or `=>`
+fn assignment_index(expr: &str) -> Option<usize> {
+    let bytes = expr.as_bytes();
+    (0..bytes.len()).find(|&i| {
+        bytes[i] == b'='
+            && !matches!(bytes.get(i + 1), Some(b'=') | Some(b'>'))
+            && !(i > 0 && matches!(bytes[i - 1], b'=' | b'!' | b'<' | b'>'))
+    })
+}
+
+// State reads go through readPath; helper calls map to the utils table
+fn compile_value(expr: &str, utils: &str) -> String {
+    let mut compiled = map_names(expr.trim(), |name| Some(format!("readPath('{}', null)", name)));
+    for helper in ["max", "min", "abs", "round", "floor", "ceil"] {
+        compiled = compiled.replace(&format!("{}(", helper), &format!("{}.{}(", utils, helper));
+    }
     compiled
 }
 
+/// The payload of `emit("select", item)`. Build-time payloads were already
+/// inlined as JSON; other names are read from the loop variables, then state.
+pub(crate) fn compile_payload(payload: Option<&str>) -> String {
+    match payload {
+        None => "undefined".to_string(),
+        Some(p) if p.starts_with('$') || p.starts_with(['{', '[', '"', '\'']) || p.parse::<f64>().is_ok()
+            || ["true", "false", "null"].contains(&p) => p.to_string(),
+        Some(p) => format!("readPath('{}', null)", p),
+    }
+}
+
+// `emit("select", item)`: call the parent's listener for this component instance
+fn compile_emit(event: &str, payload: Option<&str>, instance: Option<&str>) -> String {
+    let instance = instance.map(|i| format!("'{}'", i)).unwrap_or_else(|| "null".to_string());
+    format!("window.__webcore_emit__({}, '{}', {})", instance, event, compile_payload(payload))
+}
+
 pub fn generate_js() -> String {
     "// JS output placeholder".to_string()
 }
 
-pub fn generate_runtime_js(handlers: &[HandlerMapping]) -> String {
+/// The compiled handlers of one page, registered with the runtime by a script
+/// in the page itself: handler ids are only unique within their page. The
+/// runtime passes in `writePath`, and to each call a `readPath` that sees the
+/// loop variables of the template the element was rendered from
+pub fn generate_handlers_js(handlers: &[HandlerMapping]) -> String {
+    let mut js = String::new();
+    js.push_str("window.__webcore_register__(function(writePath) { return {\n");
+    for handler in handlers {
+        js.push_str(&format!("  '{}': function(readPath, $event, $detail) {{\n", handler.id));
+        js.push_str("    try {\n");
+
+        // Compile the expression to use state management
+        let compiled_expr = match parse_emit(&handler.expression) {
+            Some((event, payload)) => compile_emit(&event, payload.as_deref(), handler.instance.as_deref()),
+            None => compile_expression(&handler.expression, "window.__webcore_utils__"),
+        };
+        js.push_str(&format!("      {}\n", compiled_expr));
+
+        js.push_str("    } catch (error) {\n");
+        js.push_str("      console.error('Error executing handler:', error);\n");
+        js.push_str("    }\n");
+        js.push_str("  },\n");
+    }
+    js.push_str("}; }, {\n");
+    // Event and modifiers (`on:keydown.ctrl.s`) of each handler bound to an element
+    for handler in handlers.iter().filter(|h| h.kind == HandlerKind::Dom) {
+        let modifiers: Vec<String> = handler.modifiers.iter().map(|m| format!("'{}'", m)).collect();
+        js.push_str(&format!("  '{}': {{ event: '{}', modifiers: [{}] }},\n", handler.id, handler.event_type, modifiers.join(", ")));
+    }
+    js.push_str("});");
+    js
+}
+
+/// The runtime shared by every page; `router` adds the SPA router
+pub fn generate_runtime_js(router: bool) -> String {
     let mut js = String::new();
     
     // WebCore Runtime
@@ -68,22 +112,13 @@ pub fn generate_runtime_js(handlers: &[HandlerMapping]) -> String {
     js.push_str("(function() {\n");
     js.push_str("  'use strict';\n\n");
     
-    // Generate compiled handlers
+    // Filled by the page's own script (see `generate_handlers_js`)
     js.push_str("  // Compiled Event Handlers\n");
-    js.push_str("  window.__webcore_handlers__ = {\n");
-    for handler in handlers {
-        js.push_str(&format!("    '{}': function() {{\n", handler.id));
-        js.push_str("      try {\n");
-        
-        // Compile the expression to use state management
-        let compiled_expr = compile_expression(&handler.expression);
-        js.push_str(&format!("        {}\n", compiled_expr));
-        
-        js.push_str("      } catch (error) {\n");
-        js.push_str("        console.error('Error executing handler:', error);\n");
-        js.push_str("      }\n");
-        js.push_str("    },\n");
-    }
+    js.push_str("  window.__webcore_handlers__ = {};\n");
+    js.push_str("  window.__webcore_events__ = {};\n");
+    js.push_str("  window.__webcore_register__ = function(handlers, events) {\n");
+    js.push_str("    Object.assign(window.__webcore_handlers__, handlers(writePath));\n");
+    js.push_str("    Object.assign(window.__webcore_events__, events);\n");
     js.push_str("  };\n\n");
     
     // State management
@@ -108,6 +143,10 @@ pub fn generate_runtime_js(handlers: &[HandlerMapping]) -> String {
     js.push_str("        this.listeners.set(key, []);\n");
     js.push_str("      }\n");
     js.push_str("      this.listeners.get(key).push(callback);\n");
+    js.push_str("      return () => {\n");
+    js.push_str("        const callbacks = this.listeners.get(key) || [];\n");
+    js.push_str("        this.listeners.set(key, callbacks.filter(c => c !== callback));\n");
+    js.push_str("      };\n");
     js.push_str("    }\n\n");
     
     js.push_str("    notify(key, value) {\n");
@@ -119,21 +158,76 @@ pub fn generate_runtime_js(handlers: &[HandlerMapping]) -> String {
     // Global state instance
     js.push_str("  window.__webcore_state__ = new WebCoreState();\n");
     js.push_str("  \n");
-    js.push_str("  // Initialize default state\n");
-    js.push_str("  window.__webcore_state__.set('count', 0);\n\n");
     
     // Event handlers
     js.push_str("  // Event Handlers\n");
-    js.push_str("  function handleEvent(event, handler) {\n");
-    js.push_str("    event.preventDefault();\n");
-    js.push_str("    try {\n");
-    js.push_str("      handler();\n");
-    js.push_str("    } catch (error) {\n");
-    js.push_str("      console.error('WebCore event handler error:', error);\n");
+    js.push_str("  const KEYS = { enter: ['Enter'], esc: ['Escape'], escape: ['Escape'], space: [' '], tab: ['Tab'],\n");
+    js.push_str("    delete: ['Delete', 'Backspace'], up: ['ArrowUp'], down: ['ArrowDown'], left: ['ArrowLeft'], right: ['ArrowRight'] };\n");
+    js.push_str("  const SYSTEM_KEYS = { ctrl: 'ctrlKey', shift: 'shiftKey', alt: 'altKey', meta: 'metaKey' };\n");
+    js.push_str("  // `.once` handlers, by id, with the elements they already ran for: every\n");
+    js.push_str("  // row of a `for` shares the id but fires once of its own\n");
+    js.push_str("  const fired = {};\n\n");
+    js.push_str("  // Applies the handler's modifiers to the event, then runs it with `$event`;\n");
+    js.push_str("  // `locals` are the loop variables of the element's template, if any\n");
+    js.push_str("  function dispatch(handlerId, event, locals) {\n");
+    js.push_str("    const handler = window.__webcore_handlers__[handlerId];\n");
+    js.push_str("    const once = fired[handlerId] || (fired[handlerId] = new WeakSet());\n");
+    js.push_str("    if (!handler || once.has(event.currentTarget)) return;\n");
+    js.push_str("    const modifiers = (window.__webcore_events__[handlerId] || {}).modifiers || [];\n");
+    js.push_str("    for (const modifier of modifiers) {\n");
+    js.push_str("      if (SYSTEM_KEYS[modifier] && !event[SYSTEM_KEYS[modifier]]) return;\n");
+    js.push_str("      if (KEYS[modifier] && KEYS[modifier].indexOf(event.key) === -1) return;\n");
+    js.push_str("      if (modifier.length === 1 && String(event.key).toLowerCase() !== modifier) return;\n");
     js.push_str("    }\n");
+    js.push_str("    if (modifiers.indexOf('prevent') !== -1) event.preventDefault();\n");
+    js.push_str("    if (modifiers.indexOf('stop') !== -1) event.stopPropagation();\n");
+    js.push_str("    if (modifiers.indexOf('once') !== -1) once.add(event.currentTarget);\n");
+    js.push_str("    handler(function(path) { return readPath(path, locals); }, event);\n");
+    js.push_str("  }\n\n");
+    js.push_str("  // Events that do not bubble only reach the handlers of their target\n");
+    js.push_str("  const NON_BUBBLING = ['focus', 'blur', 'mouseenter', 'mouseleave', 'pointerenter', 'pointerleave', 'load', 'error', 'scroll', 'toggle'];\n\n");
+    js.push_str("  function boundIds(element, type) {\n");
+    js.push_str("    const bound = element.getAttribute && element.getAttribute('data-webcore-on');\n");
+    js.push_str("    if (!bound) return [];\n");
+    js.push_str("    return bound.split(' ').filter(function(entry) { return entry.split(':')[0] === type; })\n");
+    js.push_str("      .map(function(entry) { return entry.split(':')[1]; });\n");
+    js.push_str("  }\n\n");
+    js.push_str("  // `$event.currentTarget` is the element the handler is bound on, as it would\n");
+    js.push_str("  // be with a listener of its own rather than the document's\n");
+    js.push_str("  function boundEvent(event, element) {\n");
+    js.push_str("    return new Proxy(event, {\n");
+    js.push_str("      get: function(target, key) {\n");
+    js.push_str("        if (key === 'currentTarget') return element;\n");
+    js.push_str("        const value = target[key];\n");
+    js.push_str("        return typeof value === 'function' ? value.bind(target) : value;\n");
+    js.push_str("      }\n");
+    js.push_str("    });\n");
+    js.push_str("  }\n\n");
+    js.push_str("  // One listener per event type on the document, walking up from the target\n");
+    js.push_str("  // like the event would; elements rendered later need no binding of their own\n");
+    js.push_str("  function delegate(type) {\n");
+    js.push_str("    const bubbles = NON_BUBBLING.indexOf(type) === -1;\n");
+    js.push_str("    document.addEventListener(type, function(event) {\n");
+    js.push_str("      let element = event.target;\n");
+    js.push_str("      while (element && element !== document) {\n");
+    js.push_str("        boundIds(element, type).forEach(function(handlerId) {\n");
+    js.push_str("          const entry = window.__webcore_events__[handlerId];\n");
+    js.push_str("          if (entry && entry.modifiers.indexOf('passive') === -1) dispatch(handlerId, boundEvent(event, element), element.__webcore_locals__);\n");
+    js.push_str("        });\n");
+    js.push_str("        if (!bubbles || event.cancelBubble) break;\n");
+    js.push_str("        element = element.parentNode;\n");
+    js.push_str("      }\n");
+    js.push_str("    }, !bubbles);\n");
     js.push_str("  }\n\n");
     
     // Utility functions
+    // Component events: `emit` calls the listener the parent attached to the instance
+    js.push_str("  // Component Events\n");
+    js.push_str("  window.__webcore_emit__ = function(instance, event, detail) {\n");
+    js.push_str("    const listener = instance && window.__webcore_handlers__[instance + ':' + event];\n");
+    js.push_str("    if (listener) listener(function(path) { return readPath(path, null); }, null, detail);\n");
+    js.push_str("  };\n\n");
+    
     js.push_str("  // Utility Functions\n");
     js.push_str("  window.__webcore_utils__ = {\n");
     js.push_str("    max: Math.max,\n");
@@ -144,53 +238,46 @@ pub fn generate_runtime_js(handlers: &[HandlerMapping]) -> String {
     js.push_str("    ceil: Math.ceil\n");
     js.push_str("  };\n\n");
     
+    js.push_str(&generate_binding_js());
+    js.push_str(&generate_resource_js());
+
+    js.push_str("  // State of the component instances on a page; after a swap the layout's\n");
+    js.push_str("  // instances (`l1$...`) are still on screen and keep theirs\n");
+    js.push_str("  function loadPageState(doc, swapped) {\n");
+    js.push_str("    const pageState = doc.getElementById('webcore-state');\n");
+    js.push_str("    if (!pageState) return;\n");
+    js.push_str("    const initial = JSON.parse(pageState.textContent);\n");
+    js.push_str("    const reset = function(key) { return !swapped || !/^l\\d+\\$/.test(key); };\n");
+    js.push_str("    Object.keys(initial.values || {}).filter(reset).forEach(function(key) {\n");
+    js.push_str("      window.__webcore_state__.set(key, initial.values[key]);\n");
+    js.push_str("    });\n");
+    js.push_str("    (initial.resources || []).filter(function(resource) { return reset(resource.name); }).forEach(function(resource) {\n");
+    js.push_str("      window.__webcore_define_resource__(resource.name, resource.url, resource.dependencies);\n");
+    js.push_str("    });\n");
+    js.push_str("  }\n\n");
+    js.push_str("  // Delegation is set up once per event type; passive handlers are bound on\n");
+    js.push_str("  // their elements instead (see `bind`)\n");
+    js.push_str("  const delegated = new Set();\n");
+    js.push_str("  function listen() {\n");
+    js.push_str("    Object.keys(window.__webcore_events__).forEach(function(handlerId) {\n");
+    js.push_str("      const entry = window.__webcore_events__[handlerId];\n");
+    js.push_str("      if (entry.modifiers.indexOf('passive') === -1 && !delegated.has(entry.event)) {\n");
+    js.push_str("        delegated.add(entry.event);\n");
+    js.push_str("        delegate(entry.event);\n");
+    js.push_str("      }\n");
+    js.push_str("    });\n");
+    js.push_str("  }\n\n");
+    if router {
+        js.push_str(&generate_router_js());
+    }
+
     // Initialize
     js.push_str("  // Initialize WebCore\n");
     js.push_str("  document.addEventListener('DOMContentLoaded', function() {\n");
     js.push_str("    console.log('WebCore Runtime initialized');\n");
-    js.push_str("    \n");
-    js.push_str("    // Initialize interpolations\n");
-    js.push_str("    const interpolations = document.querySelectorAll('[data-webcore-interpolation]');\n");
-    js.push_str("    interpolations.forEach(function(element) {\n");
-    js.push_str("      const varName = element.getAttribute('data-webcore-interpolation');\n");
-    js.push_str("      const updateText = function() {\n");
-    js.push_str("        const value = window.__webcore_state__.get(varName);\n");
-    js.push_str("        element.textContent = value !== undefined ? value : '';\n");
-    js.push_str("      };\n");
-    js.push_str("      updateText();\n");
-    js.push_str("      window.__webcore_state__.subscribe(varName, updateText);\n");
-    js.push_str("    });\n");
-    js.push_str("    \n");
-    js.push_str("    // Global HTML5 event handlers\n");
-    js.push_str("    window.webcore_handle_click = function(handlerId) {\n");
-    js.push_str("      if (window.__webcore_handlers__[handlerId]) {\n");
-    js.push_str("        window.__webcore_handlers__[handlerId]();\n");
-    js.push_str("      }\n");
-    js.push_str("    };\n");
-    js.push_str("    \n");
-    js.push_str("    window.webcore_handle_submit = function(handlerId) {\n");
-    js.push_str("      if (window.__webcore_handlers__[handlerId]) {\n");
-    js.push_str("        window.__webcore_handlers__[handlerId]();\n");
-    js.push_str("      }\n");
-    js.push_str("    };\n");
-    js.push_str("    \n");
-    js.push_str("    window.webcore_handle_change = function(handlerId) {\n");
-    js.push_str("      if (window.__webcore_handlers__[handlerId]) {\n");
-    js.push_str("        window.__webcore_handlers__[handlerId]();\n");
-    js.push_str("      }\n");
-    js.push_str("    };\n");
-    js.push_str("    \n");
-    js.push_str("    window.webcore_handle_input = function(handlerId) {\n");
-    js.push_str("      if (window.__webcore_handlers__[handlerId]) {\n");
-    js.push_str("        window.__webcore_handlers__[handlerId]();\n");
-    js.push_str("      }\n");
-    js.push_str("    };\n");
-    js.push_str("    \n");
-    js.push_str("    window.webcore_handle_event = function(eventType, handlerId) {\n");
-    js.push_str("      if (window.__webcore_handlers__[handlerId]) {\n");
-    js.push_str("        window.__webcore_handlers__[handlerId]();\n");
-    js.push_str("      }\n");
-    js.push_str("    };\n");
+    js.push_str("    loadPageState(document);\n");
+    js.push_str("    listen();\n");
+    js.push_str(if router { "    startRouter();\n" } else { "    bind(document, null);\n" });
     js.push_str("  });\n");
     
     js.push_str("})();\n");
@@ -198,19 +285,423 @@ pub fn generate_runtime_js(handlers: &[HandlerMapping]) -> String {
     js
 }
 
-pub fn generate_component_js(component: &Component) -> String {
+// Keeps the DOM in sync with state: interpolations, runtime translations and the
+// `<template>` blocks emitted for `if`/`for` over values only known in the browser.
+// `locals` holds loop variables of the template being rendered.
+fn generate_binding_js() -> String {
     let mut js = String::new();
-    
-    js.push_str(&format!("// Component: {}\n", component.name));
-    
-    // Generate state initialization
-    for state_var in &component.state {
-        js.push_str(&format!(
-            "window.__webcore_state__.set('{}', {});\n",
-            state_var.name,
-            state_var.default_value.as_ref().unwrap_or(&"null".to_string())
-        ));
-    }
-    
+    js.push_str("  // DOM Bindings\n");
+    js.push_str("  function readPath(path, locals) {\n");
+    js.push_str("    const parts = path.split('.');\n");
+    js.push_str("    let value = (locals && parts[0] in locals) ? locals[parts[0]] : window.__webcore_state__.get(parts[0]);\n");
+    js.push_str("    for (let i = 1; i < parts.length && value !== undefined && value !== null; i++) {\n");
+    js.push_str("      value = value[parts[i]];\n");
+    js.push_str("    }\n");
+    js.push_str("    return value;\n");
+    js.push_str("  }\n\n");
+    js.push_str("  // `Cart.items = ...` replaces the store object so its subscribers are notified\n");
+    js.push_str("  function writePath(path, value) {\n");
+    js.push_str("    const parts = path.split('.');\n");
+    js.push_str("    if (parts.length === 1) {\n");
+    js.push_str("      window.__webcore_state__.set(path, value);\n");
+    js.push_str("      return;\n");
+    js.push_str("    }\n");
+    js.push_str("    const root = Object.assign({}, window.__webcore_state__.get(parts[0]));\n");
+    js.push_str("    let target = root;\n");
+    js.push_str("    for (let i = 1; i < parts.length - 1; i++) {\n");
+    js.push_str("      target[parts[i]] = Object.assign({}, target[parts[i]]);\n");
+    js.push_str("      target = target[parts[i]];\n");
+    js.push_str("    }\n");
+    js.push_str("    target[parts[parts.length - 1]] = value;\n");
+    js.push_str("    window.__webcore_state__.set(parts[0], root);\n");
+    js.push_str("  }\n\n");
+    js.push_str("  function watch(path, locals, update, disposers) {\n");
+    js.push_str("    update();\n");
+    js.push_str("    const root = path.split('.')[0];\n");
+    js.push_str("    if (!(locals && root in locals)) {\n");
+    js.push_str("      disposers.push(window.__webcore_state__.subscribe(root, update));\n");
+    js.push_str("      // Resources are only fetched once something on the page shows them\n");
+    js.push_str("      const resource = window.__webcore_resources__[root];\n");
+    js.push_str("      if (resource && !resource.started) resource();\n");
+    js.push_str("    }\n");
+    js.push_str("  }\n\n");
+    js.push_str("  // Same text as the build prints, so server-rendered values are kept as they are\n");
+    js.push_str("  function toText(value) {\n");
+    js.push_str("    if (value === undefined || value === null) return '';\n");
+    js.push_str("    if (Array.isArray(value)) return value.map(toText).join(', ');\n");
+    js.push_str("    return typeof value === 'object' ? JSON.stringify(value) : String(value);\n");
+    js.push_str("  }\n\n");
+    js.push_str("  function setText(element, text) {\n");
+    js.push_str("    if (element.textContent !== text) element.textContent = text;\n");
+    js.push_str("  }\n\n");
+    js.push_str("  const pluralRules = new Intl.PluralRules(document.documentElement.lang || undefined);\n\n");
+    js.push_str("  // `accept` limits the binding to some of the elements under `root`\n");
+    js.push_str("  function bind(root, locals, accept) {\n");
+    js.push_str("    const disposers = [];\n");
+    js.push_str("    const select = function(selector) {\n");
+    js.push_str("      return Array.from(root.querySelectorAll(selector)).filter(function(element) { return !accept || accept(element); });\n");
+    js.push_str("    };\n");
+    js.push_str("    select('[data-webcore-interpolation]').forEach(function(element) {\n");
+    js.push_str("      const path = element.getAttribute('data-webcore-interpolation');\n");
+    js.push_str("      watch(path, locals, function() {\n");
+    js.push_str("        setText(element, toText(readPath(path, locals)));\n");
+    js.push_str("      }, disposers);\n");
+    js.push_str("    });\n");
+    js.push_str("    // Passive handlers need a listener of their own so the browser knows up\n");
+    js.push_str("    // front; elements of a template get it when the template is rendered,\n");
+    js.push_str("    // along with the loop variables their handlers read\n");
+    js.push_str("    select('[data-webcore-on]').forEach(function(element) {\n");
+    js.push_str("      if (locals) element.__webcore_locals__ = locals;\n");
+    js.push_str("      element.getAttribute('data-webcore-on').split(' ').forEach(function(bound) {\n");
+    js.push_str("        const handlerId = bound.split(':')[1];\n");
+    js.push_str("        const entry = window.__webcore_events__[handlerId];\n");
+    js.push_str("        if (!entry || entry.modifiers.indexOf('passive') === -1) return;\n");
+    js.push_str("        element.addEventListener(entry.event, function(event) { dispatch(handlerId, event, locals); }, { passive: true });\n");
+    js.push_str("      });\n");
+    js.push_str("    });\n");
+    js.push_str("    // Translations whose count is runtime state: pick the plural form here\n");
+    js.push_str("    select('[data-webcore-i18n]').forEach(function(element) {\n");
+    js.push_str("      const path = element.getAttribute('data-webcore-i18n');\n");
+    js.push_str("      const forms = JSON.parse(element.getAttribute('data-webcore-forms') || '{}');\n");
+    js.push_str("      watch(path, locals, function() {\n");
+    js.push_str("        const value = readPath(path, locals);\n");
+    js.push_str("        const n = Number(value);\n");
+    js.push_str("        const form = (n === 0 && forms.zero !== undefined) ? forms.zero : (forms[pluralRules.select(n)] || forms.other || '');\n");
+    js.push_str("        setText(element, form.split('{count}').join(toText(value)).split('{0}').join(toText(value)));\n");
+    js.push_str("      }, disposers);\n");
+    js.push_str("    });\n");
+    js.push_str("    select('template[data-webcore-if], template[data-webcore-unless], template[data-webcore-each]').forEach(function(template) {\n");
+    js.push_str("      const path = template.getAttribute('data-webcore-if') || template.getAttribute('data-webcore-unless') || template.getAttribute('data-webcore-each');\n");
+    js.push_str("      const name = template.getAttribute('data-webcore-as');\n");
+    js.push_str("      let rendered = [];\n");
+    js.push_str("      let first = true;\n");
+    js.push_str("      watch(path, locals, function() {\n");
+    js.push_str("        const value = readPath(path, locals);\n");
+    js.push_str("        const truthy = Array.isArray(value) ? value.length > 0 : !!value;\n");
+    js.push_str("        let scopes = [];\n");
+    js.push_str("        if (template.hasAttribute('data-webcore-each')) {\n");
+    js.push_str("          scopes = (Array.isArray(value) ? value : Object.values(value || {})).map(function(item) {\n");
+    js.push_str("            return Object.assign({}, locals, { [name]: item });\n");
+    js.push_str("          });\n");
+    js.push_str("        } else if (template.hasAttribute('data-webcore-if') ? truthy : !truthy) {\n");
+    js.push_str("          scopes = [locals];\n");
+    js.push_str("        }\n");
+    js.push_str("        // Items still in the list are swapped in place; only the others enter or leave\n");
+    js.push_str("        const keys = scopes.map(function(scope) { return name ? itemKey(scope[name]) : null; });\n");
+    js.push_str("        const positions = measure(rendered);\n");
+    js.push_str("        rendered.forEach(function(block) {\n");
+    js.push_str("          block.disposers.forEach(d => d());\n");
+    js.push_str("          const kept = block.key !== null && keys.indexOf(block.key) !== -1;\n");
+    js.push_str("          block.nodes.forEach(function(node) { kept ? node.remove() : leave(node); });\n");
+    js.push_str("        });\n");
+    js.push_str("        const previous = rendered.map(function(block) { return block.key; });\n");
+    js.push_str("        rendered = [];\n");
+    js.push_str("        scopes.forEach(function(scope, index) {\n");
+    js.push_str("          const fragment = template.content.cloneNode(true);\n");
+    js.push_str("          const nodes = Array.from(fragment.childNodes);\n");
+    js.push_str("          const blockDisposers = bind(fragment, scope);\n");
+    js.push_str("          template.parentNode.insertBefore(fragment, template);\n");
+    js.push_str("          const key = keys[index];\n");
+    js.push_str("          if (!first && (key === null || previous.indexOf(key) === -1)) nodes.forEach(enter);\n");
+    js.push_str("          rendered.push({ nodes: nodes, disposers: blockDisposers, key: key });\n");
+    js.push_str("        });\n");
+    js.push_str("        flip(rendered, positions);\n");
+    js.push_str("        first = false;\n");
+    js.push_str("      }, disposers);\n");
+    js.push_str("    });\n");
+    js.push_str("    return disposers;\n");
+    js.push_str("  }\n\n");
+    js.push_str("  // Transitions: `wc-<name>-enter-*` / `wc-<name>-leave-*` classes, the leaving\n");
+    js.push_str("  // element stays in the DOM until its transition or animation ends\n");
+    js.push_str("  function transitionOptions(element, kind) {\n");
+    js.push_str("    return JSON.parse(element.getAttribute('data-webcore-' + kind + '-options') || '{}');\n");
+    js.push_str("  }\n\n");
+    js.push_str("  function runTransition(element, phase, done) {\n");
+    js.push_str("    const options = transitionOptions(element, 'transition');\n");
+    js.push_str("    if (options.duration !== undefined) element.style.setProperty('--wc-duration', options.duration + 'ms');\n");
+    js.push_str("    if (options.delay !== undefined) element.style.setProperty('--wc-delay', options.delay + 'ms');\n");
+    js.push_str("    if (options.easing !== undefined) element.style.setProperty('--wc-easing', options.easing);\n");
+    js.push_str("    const prefix = 'wc-' + element.getAttribute('data-webcore-transition') + '-' + phase;\n");
+    js.push_str("    let finished = false;\n");
+    js.push_str("    const finish = function(event) {\n");
+    js.push_str("      if (finished || (event && event.target !== element)) return;\n");
+    js.push_str("      finished = true;\n");
+    js.push_str("      element.classList.remove(prefix + '-active', prefix + '-to');\n");
+    js.push_str("      done();\n");
+    js.push_str("    };\n");
+    js.push_str("    element.classList.add(prefix + '-from', prefix + '-active');\n");
+    js.push_str("    element.addEventListener('transitionend', finish);\n");
+    js.push_str("    element.addEventListener('animationend', finish);\n");
+    js.push_str("    requestAnimationFrame(function() {\n");
+    js.push_str("      requestAnimationFrame(function() {\n");
+    js.push_str("        element.classList.remove(prefix + '-from');\n");
+    js.push_str("        element.classList.add(prefix + '-to');\n");
+    js.push_str("      });\n");
+    js.push_str("    });\n");
+    js.push_str("    // Ends the transition even when no CSS ran (unknown name, reduced motion)\n");
+    js.push_str("    setTimeout(finish, (options.duration !== undefined ? options.duration : 300) + (options.delay || 0) + 50);\n");
+    js.push_str("  }\n\n");
+    js.push_str("  function enter(node) {\n");
+    js.push_str("    if (node.nodeType === 1 && node.hasAttribute('data-webcore-transition')) runTransition(node, 'enter', function() {});\n");
+    js.push_str("  }\n\n");
+    js.push_str("  function leave(node) {\n");
+    js.push_str("    if (node.nodeType === 1 && node.hasAttribute('data-webcore-transition') && node.isConnected) {\n");
+    js.push_str("      runTransition(node, 'leave', function() { node.remove(); });\n");
+    js.push_str("    } else {\n");
+    js.push_str("      node.remove();\n");
+    js.push_str("    }\n");
+    js.push_str("  }\n\n");
+    js.push_str("  function itemKey(item) {\n");
+    js.push_str("    return item !== null && typeof item === 'object' && item.id !== undefined ? 'id:' + item.id : JSON.stringify(item);\n");
+    js.push_str("  }\n\n");
+    js.push_str("  // animate:flip: elements that moved slide from their old position to the new one\n");
+    js.push_str("  function measure(blocks) {\n");
+    js.push_str("    const positions = new Map();\n");
+    js.push_str("    blocks.forEach(function(block) {\n");
+    js.push_str("      block.nodes.forEach(function(node) {\n");
+    js.push_str("        if (block.key !== null && node.nodeType === 1 && node.hasAttribute('data-webcore-animate')) {\n");
+    js.push_str("          positions.set(block.key, node.getBoundingClientRect());\n");
+    js.push_str("        }\n");
+    js.push_str("      });\n");
+    js.push_str("    });\n");
+    js.push_str("    return positions;\n");
+    js.push_str("  }\n\n");
+    js.push_str("  function flip(blocks, positions) {\n");
+    js.push_str("    blocks.forEach(function(block) {\n");
+    js.push_str("      const before = positions.get(block.key);\n");
+    js.push_str("      block.nodes.forEach(function(node) {\n");
+    js.push_str("        if (!before || node.nodeType !== 1 || !node.hasAttribute('data-webcore-animate')) return;\n");
+    js.push_str("        const after = node.getBoundingClientRect();\n");
+    js.push_str("        const dx = before.left - after.left;\n");
+    js.push_str("        const dy = before.top - after.top;\n");
+    js.push_str("        if (!dx && !dy) return;\n");
+    js.push_str("        const options = transitionOptions(node, 'animate');\n");
+    js.push_str("        node.style.transition = 'none';\n");
+    js.push_str("        node.style.transform = 'translate(' + dx + 'px, ' + dy + 'px)';\n");
+    js.push_str("        node.getBoundingClientRect();\n");
+    js.push_str("        node.style.transition = 'transform ' + (options.duration !== undefined ? options.duration : 300) + 'ms '\n");
+    js.push_str("          + (options.easing || 'ease') + ' ' + (options.delay || 0) + 'ms';\n");
+    js.push_str("        node.style.transform = '';\n");
+    js.push_str("        node.addEventListener('transitionend', function() { node.style.transition = ''; }, { once: true });\n");
+    js.push_str("      });\n");
+    js.push_str("    });\n");
+    js.push_str("  }\n\n");
+    js
+}
+
+// `[app] router = "spa"`: internal links and history entries fetch the prerendered
+// page and swap what sits between its outlet markers, so the layout, the stores
+// and the runtime stay. Anything that cannot be swapped loads the page normally
+fn generate_router_js() -> String {
+    let mut js = String::new();
+    js.push_str("  // SPA Router\n");
+    js.push_str("  const router = { key: Date.now(), path: location.pathname + location.search, request: 0, disposers: [], positions: {} };\n\n");
+    js.push_str("  function outlet(doc) {\n");
+    js.push_str("    const walker = doc.createTreeWalker(doc.body, NodeFilter.SHOW_COMMENT);\n");
+    js.push_str("    let start = null;\n");
+    js.push_str("    while (walker.nextNode()) {\n");
+    js.push_str("      if (walker.currentNode.data === 'webcore:outlet') start = walker.currentNode;\n");
+    js.push_str("      else if (start && walker.currentNode.data === '/webcore:outlet') return { start: start, end: walker.currentNode };\n");
+    js.push_str("    }\n");
+    js.push_str("    return null;\n");
+    js.push_str("  }\n\n");
+    js.push_str("  function inOutlet(bounds, node) {\n");
+    js.push_str("    return !!(bounds.start.compareDocumentPosition(node) & Node.DOCUMENT_POSITION_FOLLOWING)\n");
+    js.push_str("      && !!(bounds.end.compareDocumentPosition(node) & Node.DOCUMENT_POSITION_PRECEDING);\n");
+    js.push_str("  }\n\n");
+    js.push_str("  function policy(doc) {\n");
+    js.push_str("    const meta = doc.querySelector('meta[http-equiv=\"Content-Security-Policy\"]');\n");
+    js.push_str("    return meta ? meta.content : '';\n");
+    js.push_str("  }\n\n");
+    js.push_str("  // Scripts from a parsed document never run; copies of them do\n");
+    js.push_str("  function revive(script) {\n");
+    js.push_str("    const copy = document.createElement('script');\n");
+    js.push_str("    Array.from(script.attributes).forEach(function(attribute) { copy.setAttribute(attribute.name, attribute.value); });\n");
+    js.push_str("    copy.textContent = script.textContent;\n");
+    js.push_str("    return copy;\n");
+    js.push_str("  }\n\n");
+    js.push_str("  function swap(next) {\n");
+    js.push_str("    const current = outlet(document);\n");
+    js.push_str("    const incoming = outlet(next);\n");
+    js.push_str("    // The page's handler script only runs under a policy that lists it; the\n");
+    js.push_str("    // build gives every page of the tree the same one\n");
+    js.push_str("    if (!current || !incoming || policy(next) !== policy(document)) return false;\n");
+    js.push_str("    router.disposers.forEach(function(dispose) { dispose(); });\n");
+    js.push_str("    let node = current.start.nextSibling;\n");
+    js.push_str("    while (node !== current.end) {\n");
+    js.push_str("      const following = node.nextSibling;\n");
+    js.push_str("      node.remove();\n");
+    js.push_str("      node = following;\n");
+    js.push_str("    }\n");
+    js.push_str("    const fragment = document.createDocumentFragment();\n");
+    js.push_str("    node = incoming.start.nextSibling;\n");
+    js.push_str("    while (node !== incoming.end) {\n");
+    js.push_str("      const following = node.nextSibling;\n");
+    js.push_str("      fragment.appendChild(document.adoptNode(node));\n");
+    js.push_str("      node = following;\n");
+    js.push_str("    }\n");
+    js.push_str("    fragment.querySelectorAll('script').forEach(function(script) { script.replaceWith(revive(script)); });\n");
+    js.push_str("    // Instance state and handlers are the new page's; stores and the layout keep their values\n");
+    js.push_str("    loadPageState(next, true);\n");
+    js.push_str("    next.querySelectorAll('body script:not([src]):not([type])').forEach(function(script) {\n");
+    js.push_str("      const copy = revive(script);\n");
+    js.push_str("      document.body.appendChild(copy);\n");
+    js.push_str("      copy.remove();\n");
+    js.push_str("    });\n");
+    js.push_str("    router.disposers = bind(fragment, null);\n");
+    js.push_str("    listen();\n");
+    js.push_str("    current.end.parentNode.insertBefore(fragment, current.end);\n");
+    js.push_str("    document.title = next.title;\n");
+    js.push_str("    return true;\n");
+    js.push_str("  }\n\n");
+    js.push_str("  // Screen readers follow focus: it moves to the new page's heading\n");
+    js.push_str("  function settle(scroll) {\n");
+    js.push_str("    const target = location.hash && document.getElementById(decodeURIComponent(location.hash.slice(1)));\n");
+    js.push_str("    if (target) target.scrollIntoView(); else window.scrollTo(scroll[0], scroll[1]);\n");
+    js.push_str("    const bounds = outlet(document);\n");
+    js.push_str("    const headings = Array.from(document.querySelectorAll('h1')).concat(Array.from(document.querySelectorAll('h2, h3, h4, h5, h6')));\n");
+    js.push_str("    const heading = headings.find(function(element) { return inOutlet(bounds, element); });\n");
+    js.push_str("    if (!heading) return;\n");
+    js.push_str("    if (!heading.hasAttribute('tabindex')) heading.setAttribute('tabindex', '-1');\n");
+    js.push_str("    heading.focus({ preventScroll: true });\n");
+    js.push_str("  }\n\n");
+    js.push_str("  // `push` adds a history entry for a followed link; going back or forward\n");
+    js.push_str("  // restores the scroll position the entry was left at\n");
+    js.push_str("  function navigate(url, push, scroll) {\n");
+    js.push_str("    const request = ++router.request;\n");
+    js.push_str("    fetch(url, { headers: { 'Accept': 'text/html' } })\n");
+    js.push_str("      .then(function(response) {\n");
+    js.push_str("        if (!response.ok || (response.headers.get('Content-Type') || '').indexOf('text/html') === -1) throw new Error(response.status);\n");
+    js.push_str("        return response.text();\n");
+    js.push_str("      })\n");
+    js.push_str("      .then(function(text) {\n");
+    js.push_str("        if (request !== router.request) return;\n");
+    js.push_str("        if (push) {\n");
+    js.push_str("          history.replaceState({ key: router.key, scroll: [window.scrollX, window.scrollY] }, '');\n");
+    js.push_str("          router.key = Date.now();\n");
+    js.push_str("        }\n");
+    js.push_str("        if (!swap(new DOMParser().parseFromString(text, 'text/html'))) throw new Error('not swappable');\n");
+    js.push_str("        if (push) history.pushState({ key: router.key }, '', url);\n");
+    js.push_str("        router.path = location.pathname + location.search;\n");
+    js.push_str("        settle(scroll);\n");
+    js.push_str("      })\n");
+    js.push_str("      .catch(function() {\n");
+    js.push_str("        if (request === router.request) location.assign(url);\n");
+    js.push_str("      });\n");
+    js.push_str("  }\n\n");
+    js.push_str("  function follow(event) {\n");
+    js.push_str("    const anchor = event.target.closest && event.target.closest('a[href]');\n");
+    js.push_str("    if (!anchor || event.defaultPrevented || event.button !== 0 || event.metaKey || event.ctrlKey || event.shiftKey || event.altKey) return;\n");
+    js.push_str("    if (anchor.hasAttribute('download') || (anchor.target && anchor.target !== '_self')) return;\n");
+    js.push_str("    const url = new URL(anchor.href, location.href);\n");
+    js.push_str("    if (url.origin !== location.origin) return;\n");
+    js.push_str("    // Anchors within the current page scroll as usual\n");
+    js.push_str("    if (url.pathname + url.search === router.path && url.hash) return;\n");
+    js.push_str("    event.preventDefault();\n");
+    js.push_str("    navigate(url.href, true, [0, 0]);\n");
+    js.push_str("  }\n\n");
+    js.push_str("  function startRouter() {\n");
+    js.push_str("    const bounds = outlet(document);\n");
+    js.push_str("    if (!bounds) {\n");
+    js.push_str("      bind(document, null);\n");
+    js.push_str("      return;\n");
+    js.push_str("    }\n");
+    js.push_str("    // The layout is bound once, the page until it is swapped\n");
+    js.push_str("    bind(document, null, function(element) { return !inOutlet(bounds, element); });\n");
+    js.push_str("    router.disposers = bind(document, null, function(element) { return inOutlet(bounds, element); });\n");
+    js.push_str("    history.scrollRestoration = 'manual';\n");
+    js.push_str("    history.replaceState({ key: router.key }, '');\n");
+    js.push_str("    // On the window, so handlers delegated on the document can prevent it first\n");
+    js.push_str("    window.addEventListener('click', follow);\n");
+    js.push_str("    window.addEventListener('popstate', function(event) {\n");
+    js.push_str("      const state = event.state || {};\n");
+    js.push_str("      router.positions[router.key] = [window.scrollX, window.scrollY];\n");
+    js.push_str("      router.key = state.key;\n");
+    js.push_str("      if (location.pathname + location.search === router.path) return;\n");
+    js.push_str("      navigate(location.href, false, router.positions[state.key] || state.scroll || [0, 0]);\n");
+    js.push_str("    });\n");
+    js.push_str("  }\n\n");
+    js
+}
+
+// `resource users = fetch(url)`: state holding { loading, error, data }, fetched when
+// first bound and again when a name interpolated into the URL changes; stale
+// responses are dropped. Resources are keyed like state, by instance (`c1$users`),
+// and the page the router swaps in replaces those of the previous one
+fn generate_resource_js() -> String {
+    let mut js = String::new();
+    js.push_str("  // Async Resources\n");
+    js.push_str("  window.__webcore_resources__ = {};\n");
+    js.push_str("  window.__webcore_define_resource__ = function(name, url, dependencies) {\n");
+    js.push_str("    const state = window.__webcore_state__;\n");
+    js.push_str("    const replaced = window.__webcore_resources__[name];\n");
+    js.push_str("    if (replaced) replaced.dispose();\n");
+    js.push_str("    let request = 0;\n");
+    js.push_str("    const load = function() {\n");
+    js.push_str("      load.started = true;\n");
+    js.push_str("      const current = ++request;\n");
+    js.push_str("      const previous = state.get(name) || {};\n");
+    js.push_str("      const resolved = url.replace(/\\{\\s*([\\w.$]+)\\s*\\}/g, function(_, path) {\n");
+    js.push_str("        const value = readPath(path, null);\n");
+    js.push_str("        return encodeURIComponent(value !== undefined && value !== null ? value : '');\n");
+    js.push_str("      });\n");
+    js.push_str("      state.set(name, { loading: true, error: null, data: previous.data !== undefined ? previous.data : null });\n");
+    js.push_str("      fetch(resolved, { headers: { 'Accept': 'application/json' } })\n");
+    js.push_str("        .then(function(response) {\n");
+    js.push_str("          if (!response.ok) throw new Error(response.status + ' ' + response.statusText);\n");
+    js.push_str("          return response.json();\n");
+    js.push_str("        })\n");
+    js.push_str("        .then(function(data) {\n");
+    js.push_str("          if (current === request) state.set(name, { loading: false, error: null, data: data });\n");
+    js.push_str("        })\n");
+    js.push_str("        .catch(function(error) {\n");
+    js.push_str("          if (current === request) state.set(name, { loading: false, error: String(error.message || error), data: null });\n");
+    js.push_str("        });\n");
+    js.push_str("    };\n");
+    js.push_str("    load.started = false;\n");
+    js.push_str("    window.__webcore_resources__[name] = load;\n");
+    js.push_str("    state.set(name, { loading: true, error: null, data: null });\n");
+    js.push_str("    const unsubscribers = dependencies.map(function(dependency) {\n");
+    js.push_str("      return state.subscribe(dependency, function() { if (load.started) load(); });\n");
+    js.push_str("    });\n");
+    js.push_str("    // Pending responses are dropped along with the subscriptions\n");
+    js.push_str("    load.dispose = function() {\n");
+    js.push_str("      request++;\n");
+    js.push_str("      unsubscribers.forEach(function(unsubscribe) { unsubscribe(); });\n");
+    js.push_str("    };\n");
+    js.push_str("  };\n\n");
     js
 }
+
+/// Initial value of a store, set once for the whole site
+pub fn generate_store_js(store: &Store) -> String {
+    let mut fields = serde_json::Map::new();
+    for field in &store.fields {
+        let value = field.default_value.as_deref()
+            .and_then(|v| serde_json::from_str(v).ok())
+            .unwrap_or(serde_json::Value::Null);
+        fields.insert(field.name.clone(), value);
+    }
+    format!("\n// Store: {}\nwindow.__webcore_state__.set('{}', {});\n", store.name, store.name, serde_json::Value::Object(fields))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn handlers_read_the_locals_of_the_row_they_were_bound_in() {
+        let runtime = generate_runtime_js(false);
+        assert!(runtime.contains("if (locals) element.__webcore_locals__ = locals;"));
+        assert!(runtime.contains("dispatch(handlerId, boundEvent(event, element), element.__webcore_locals__)"));
+        assert!(runtime.contains("handler(function(path) { return readPath(path, locals); }, event);"));
+    }
+
+    #[test]
+    fn a_swap_keeps_the_state_of_the_layout_instances() {
+        let runtime = generate_runtime_js(true);
+        assert!(runtime.contains("const reset = function(key) { return !swapped || !/^l\\d+\\$/.test(key); };"));
+        assert!(runtime.contains("loadPageState(next, true);"));
+        assert!(!generate_runtime_js(false).contains("loadPageState(next, true);"));
+    }
+}
@@ -0,0 +1,325 @@
+//! Markdown content collections (`src/content/**/*.md`)
+
+use crate::ast::*;
+use crate::data;
+use pulldown_cmark::{CodeBlockKind, Event, HeadingLevel, Options, Parser as MarkdownParser, Tag};
+use crate::project::ProjectInput;
+use serde_json::Value;
+
+/// One Markdown file turned into a page
+#[derive(Debug, Clone)]
+pub struct ContentEntry {
+    /// First directory under `src/content` (`blog` for `src/content/blog/a.md`)
+    pub collection: String,
+    pub slug: String,
+    /// The .md file, relative to the project root
+    pub file: String,
+    pub title: Option<String>,
+    pub date: Option<String>,
+    pub tags: Vec<String>,
+    pub layout: Option<String>,
+    pub front_matter: serde_json::Map<String, Value>,
+    pub body: Vec<Element>,
+}
+
+impl ContentEntry {
+    pub fn output_file(&self) -> String {
+        format!("{}.html", self.slug)
+    }
+
+    pub fn to_page(&self) -> Page {
+        Page {
+            name: self.slug.clone(),
+            layout: self.layout.clone(),
+            data: Vec::new(),
+            content: self.body.clone(),
+            file: String::new(),
+            span: Span::default(),
+        }
+    }
+
+    /// Value exposed to `.webc` views through `collection("...")`
+    pub fn to_value(&self) -> Value {
+        let mut entry = self.front_matter.clone();
+        entry.insert("title".to_string(), self.title.clone().map(Value::String).unwrap_or(Value::Null));
+        entry.insert("date".to_string(), self.date.clone().map(Value::String).unwrap_or(Value::Null));
+        entry.insert("tags".to_string(), Value::Array(self.tags.iter().cloned().map(Value::String).collect()));
+        entry.insert("slug".to_string(), Value::String(self.slug.clone()));
+        // Root-absolute; links to it are moved under the locale or site path when printed
+        entry.insert("url".to_string(), Value::String(format!("/{}", self.output_file())));
+        Value::Object(entry)
+    }
+}
+
+/// Every `.md` file under `content_dir`, ordered by slug
+pub fn load_collections(input: &ProjectInput, content_dir: &str) -> Result<Vec<ContentEntry>, String> {
+    let mut entries = Vec::new();
+    for (path, _) in input.files_in(content_dir) {
+        let Some(relative) = path.strip_prefix(content_dir).and_then(|p| p.strip_prefix('/')).and_then(|p| p.strip_suffix(".md")) else { continue };
+        let source = input.read_to_string(path)?;
+        let mut entry = parse_entry(relative, &source).map_err(|e| format!("Content error in {}: {}", path, e))?;
+        entry.file = path.to_string();
+        entries.push(entry);
+    }
+    entries.sort_by(|a, b| a.slug.cmp(&b.slug));
+    Ok(entries)
+}
+
+/// Parse a Markdown file given its path relative to `src/content`, without extension
+pub fn parse_entry(relative: &str, source: &str) -> Result<ContentEntry, String> {
+    let (front_matter, body) = split_front_matter(source)?;
+    let collection = match relative.split_once('/') {
+        Some((dir, _)) => dir.to_string(),
+        None => String::new(),
+    };
+    let text = |key: &str| front_matter.get(key).map(data::value_to_text).filter(|s| !s.is_empty());
+    let tags = match front_matter.get("tags") {
+        Some(Value::Array(items)) => items.iter().map(data::value_to_text).collect(),
+        Some(Value::String(s)) => s.split(',').map(|t| t.trim().to_string()).filter(|t| !t.is_empty()).collect(),
+        _ => Vec::new(),
+    };
+    let slug = text("slug").map(|s| s.trim_matches('/').to_string()).unwrap_or_else(|| relative.to_string());
+    if !is_safe_slug(&slug) {
+        return Err(format!("slug '{}' is not a safe file name, use letters, digits, '-', '_' and '.' in '/'-separated segments", slug));
+    }
+    Ok(ContentEntry {
+        collection,
+        slug,
+        file: String::new(),
+        title: text("title"),
+        date: text("date"),
+        tags,
+        layout: text("layout"),
+        body: markdown_to_elements(body),
+        front_matter,
+    })
+}
+
+// The slug becomes a path under dist/ and a link target: no `..`, no empty
+// segments and nothing that needs escaping
+fn is_safe_slug(slug: &str) -> bool {
+    slug.split('/').all(|segment| {
+        !segment.is_empty() && segment != "." && segment != ".."
+            && segment.chars().all(|c| c.is_alphanumeric() || matches!(c, '-' | '_' | '.'))
+    })
+}
+
+// `+++` fences TOML front matter, `---` fences YAML
+fn split_front_matter(source: &str) -> Result<(serde_json::Map<String, Value>, &str), String> {
+    let source = source.trim_start_matches('\u{feff}');
+    for (fence, is_toml) in [("+++", true), ("---", false)] {
+        let Some(rest) = source.strip_prefix(fence) else { continue };
+        let Some(rest) = rest.strip_prefix('\n').or_else(|| rest.strip_prefix("\r\n")) else { continue };
+        let end = rest.find(&format!("\n{}", fence))
+            .ok_or_else(|| format!("unterminated front matter (missing closing {})", fence))?;
+        let raw = &rest[..end];
+        let body = rest[end + 1 + fence.len()..].trim_start_matches(['\r', '\n']);
+        let value = if is_toml {
+            data::parse_data(raw, data::DataFormat::Toml)?
+        } else {
+            let yaml: serde_json::Value = serde_yaml::from_str(raw).map_err(|e| e.to_string())?;
+            if yaml.is_null() { Value::Object(Default::default()) } else { yaml }
+        };
+        return match value {
+            Value::Object(map) => Ok((map, body)),
+            _ => Err("front matter must be a table of key/value pairs".to_string()),
+        };
+    }
+    Ok((serde_json::Map::new(), source))
+}
+
+/// Collection values keyed for the build's data set, newest first
+pub fn collection_value(entries: &[ContentEntry], collection: &str) -> Value {
+    let mut items: Vec<&ContentEntry> = entries.iter()
+        .filter(|e| collection.is_empty() || e.collection == collection)
+        .collect();
+    items.sort_by(|a, b| b.date.cmp(&a.date).then_with(|| a.slug.cmp(&b.slug)));
+    Value::Array(items.into_iter().map(ContentEntry::to_value).collect())
+}
+
+// Open element while walking Markdown events
+struct Frame {
+    name: String,
+    attributes: Vec<Attribute>,
+    content: Vec<Element>,
+    // Image children are collected as alt text instead of content
+    alt: Option<String>,
+}
+
+fn class(value: &str) -> Attribute {
+    Attribute { name: "class".to_string(), value: AttributeValue::String(value.to_string()), span: Span::default() }
+}
+
+fn attr(name: &str, value: &str) -> Attribute {
+    Attribute { name: name.to_string(), value: AttributeValue::String(value.to_string()), span: Span::default() }
+}
+
+fn frame(name: &str, attributes: Vec<Attribute>) -> Frame {
+    Frame { name: name.to_string(), attributes, content: Vec::new(), alt: None }
+}
+
+/// Map Markdown to regular elements; headings, code, links and images get
+/// `md-*` classes styled from theme variables (see `codegen_css::generate_content_css`)
+pub fn markdown_to_elements(markdown: &str) -> Vec<Element> {
+    let options = Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH;
+    // "text" renders only its children
+    let mut stack = vec![frame("text", Vec::new())];
+
+    for event in MarkdownParser::new_ext(markdown, options) {
+        match event {
+            Event::Start(tag) => {
+                let next = match tag {
+                    Tag::Paragraph => frame("p", Vec::new()),
+                    Tag::Heading { level, .. } => {
+                        let name = match level {
+                            HeadingLevel::H1 => "h1",
+                            HeadingLevel::H2 => "h2",
+                            HeadingLevel::H3 => "h3",
+                            HeadingLevel::H4 => "h4",
+                            HeadingLevel::H5 => "h5",
+                            HeadingLevel::H6 => "h6",
+                        };
+                        frame(name, vec![class("md-heading")])
+                    }
+                    Tag::BlockQuote(_) => frame("blockquote", Vec::new()),
+                    Tag::CodeBlock(kind) => {
+                        // <pre class="md-code"><code class="language-x">
+                        stack.push(frame("pre", vec![class("md-code")]));
+                        match kind {
+                            CodeBlockKind::Fenced(lang) if !lang.is_empty() => {
+                                frame("code", vec![class(&format!("language-{}", lang))])
+                            }
+                            _ => frame("code", Vec::new()),
+                        }
+                    }
+                    Tag::List(Some(1)) => frame("ol", Vec::new()),
+                    Tag::List(Some(start)) => frame("ol", vec![attr("start", &start.to_string())]),
+                    Tag::List(None) => frame("ul", Vec::new()),
+                    Tag::Item => frame("li", Vec::new()),
+                    Tag::Emphasis => frame("em", Vec::new()),
+                    Tag::Strong => frame("strong", Vec::new()),
+                    Tag::Strikethrough => frame("del", Vec::new()),
+                    Tag::Link { dest_url, title, .. } => {
+                        let mut attributes = vec![class("md-link"), attr("href", &dest_url)];
+                        if !title.is_empty() {
+                            attributes.push(attr("title", &title));
+                        }
+                        frame("a", attributes)
+                    }
+                    Tag::Image { dest_url, title, .. } => {
+                        let mut attributes = vec![class("md-image"), attr("src", &dest_url)];
+                        if !title.is_empty() {
+                            attributes.push(attr("title", &title));
+                        }
+                        let mut image = frame("img", attributes);
+                        image.alt = Some(String::new());
+                        image
+                    }
+                    Tag::Table(_) => frame("table", Vec::new()),
+                    Tag::TableHead => {
+                        // Header cells come without a row of their own
+                        stack.push(frame("thead", Vec::new()));
+                        frame("tr", Vec::new())
+                    }
+                    Tag::TableRow => frame("tr", Vec::new()),
+                    Tag::TableCell if in_table_head(&stack) => frame("th", Vec::new()),
+                    Tag::TableCell => frame("td", Vec::new()),
+                    _ => frame("text", Vec::new()),
+                };
+                stack.push(next);
+            }
+            Event::End(_) => {
+                close_frame(&mut stack);
+                // Code blocks and table heads opened two frames
+                if stack.last().map(|f| f.name == "pre" || f.name == "thead").unwrap_or(false) {
+                    close_frame(&mut stack);
+                }
+            }
+            Event::Text(text) | Event::Code(text) if in_image(&stack) => {
+                if let Some(alt) = stack.last_mut().and_then(|f| f.alt.as_mut()) {
+                    alt.push_str(&text);
+                }
+            }
+            // Raw HTML inside Markdown stays escaped like any other text
+            Event::Text(text) | Event::Html(text) | Event::InlineHtml(text) => push(&mut stack, Element::Text { text: text.to_string(), span: Span::default() }),
+            Event::Code(code) => push(&mut stack, Element::Tag {
+                name: "code".to_string(),
+                attributes: Vec::new(),
+                content: vec![Element::Text { text: code.to_string(), span: Span::default() }],
+                span: Span::default(),
+            }),
+            Event::SoftBreak => push(&mut stack, Element::Text { text: "\n".to_string(), span: Span::default() }),
+            Event::HardBreak => push(&mut stack, Element::Tag { name: "br".to_string(), attributes: Vec::new(), content: Vec::new(), span: Span::default() }),
+            Event::Rule => push(&mut stack, Element::Tag { name: "hr".to_string(), attributes: Vec::new(), content: Vec::new(), span: Span::default() }),
+            _ => {}
+        }
+    }
+
+    while stack.len() > 1 {
+        close_frame(&mut stack);
+    }
+    stack.pop().map(|root| root.content).unwrap_or_default()
+}
+
+fn in_image(stack: &[Frame]) -> bool {
+    stack.last().map(|f| f.alt.is_some()).unwrap_or(false)
+}
+
+fn in_table_head(stack: &[Frame]) -> bool {
+    stack.len() >= 2 && stack[stack.len() - 2].name == "thead"
+}
+
+fn push(stack: &mut [Frame], element: Element) {
+    if let Some(top) = stack.last_mut() {
+        top.content.push(element);
+    }
+}
+
+fn close_frame(stack: &mut Vec<Frame>) {
+    if stack.len() < 2 {
+        return;
+    }
+    let Some(mut closed) = stack.pop() else { return };
+    if let Some(alt) = closed.alt.take() {
+        closed.attributes.push(attr("alt", &alt));
+    }
+    push(stack, Element::Tag { name: closed.name, attributes: closed.attributes, content: closed.content, span: Span::default() });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_front_matter_and_maps_markdown() {
+        let source = "---\ntitle: Hello\ndate: 2025-01-02\ntags: [rust, web]\nlayout: Docs\n---\n# Intro\n\nSee [docs](/docs) ![logo](/logo.png)\n\n```rust\nfn main() {}\n```\n";
+        let entry = parse_entry("blog/hello", source).expect("entry ok");
+        assert_eq!(entry.collection, "blog");
+        assert_eq!(entry.slug, "blog/hello");
+        assert_eq!(entry.title.as_deref(), Some("Hello"));
+        assert_eq!(entry.tags, vec!["rust".to_string(), "web".to_string()]);
+        assert_eq!(entry.layout.as_deref(), Some("Docs"));
+
+        assert!(matches!(&entry.body[0], Element::Tag { name, .. } if name == "h1"));
+        let Element::Tag { content, .. } = &entry.body[1] else { panic!("expected paragraph") };
+        assert!(content.iter().any(|e| matches!(e, Element::Tag { name, attributes, .. }
+            if name == "img" && attributes.iter().any(|a| a.name == "alt" && matches!(&a.value, AttributeValue::String(v) if v == "logo")))));
+        let Element::Tag { name, content, .. } = &entry.body[2] else { panic!("expected code block") };
+        assert_eq!(name, "pre");
+        assert!(matches!(&content[0], Element::Tag { name, .. } if name == "code"));
+    }
+
+    #[test]
+    fn toml_front_matter_and_collection_order() {
+        let older = parse_entry("blog/a", "+++\ntitle = \"A\"\ndate = 2024-05-01\n+++\nbody").expect("ok");
+        let newer = parse_entry("blog/b", "+++\ntitle = \"B\"\ndate = 2025-05-01\nslug = \"/b-post\"\n+++\nbody").expect("ok");
+        let value = collection_value(&[older, newer], "blog");
+        assert_eq!(value[0]["slug"], "b-post");
+        for slug in ["../../escaped", "a\\\\b", "a\\\" onmouseover=\\\"x", "a//b"] {
+            let source = format!("+++\nslug = \"{}\"\n+++\nbody", slug);
+            assert!(parse_entry("blog/c", &source).expect_err("unsafe slug").contains("is not a safe file name"), "{}", slug);
+        }
+        assert_eq!(value[0]["url"], "/b-post.html");
+        assert_eq!(value[1]["date"], "2024-05-01");
+    }
+}
@@ -0,0 +1,205 @@
+//! Build-time data sources (JSON, TOML, CSV)
+
+use crate::ast::*;
+use serde_json::Value;
+use crate::project::ProjectInput;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Loaded data files, keyed by the path used in `load("...")`
+pub type DataSet = HashMap<String, Value>;
+
+/// Names visible to a view while rendering (data sources, loop variables)
+pub type Scope = HashMap<String, Value>;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DataFormat {
+    Json,
+    Toml,
+    Csv,
+}
+
+impl DataFormat {
+    pub fn from_path(path: &str) -> Option<Self> {
+        match Path::new(path).extension().and_then(|e| e.to_str()) {
+            Some("json") => Some(DataFormat::Json),
+            Some("toml") => Some(DataFormat::Toml),
+            Some("csv") => Some(DataFormat::Csv),
+            _ => None,
+        }
+    }
+}
+
+pub fn parse_data(content: &str, format: DataFormat) -> Result<Value, String> {
+    match format {
+        DataFormat::Json => serde_json::from_str(content).map_err(|e| e.to_string()),
+        DataFormat::Toml => {
+            let value: toml::Value = toml::from_str(content).map_err(|e| e.to_string())?;
+            Ok(toml_to_json(value))
+        }
+        DataFormat::Csv => {
+            // Each row becomes an object keyed by the header line
+            let mut reader = csv::Reader::from_reader(content.as_bytes());
+            let headers = reader.headers().map_err(|e| e.to_string())?.clone();
+            let mut rows = Vec::new();
+            for record in reader.records() {
+                let record = record.map_err(|e| e.to_string())?;
+                let mut row = serde_json::Map::new();
+                for (key, value) in headers.iter().zip(record.iter()) {
+                    row.insert(key.to_string(), Value::String(value.to_string()));
+                }
+                rows.push(Value::Object(row));
+            }
+            Ok(Value::Array(rows))
+        }
+    }
+}
+
+// TOML dates would otherwise serialize as an internal wrapper object
+pub fn toml_to_json(value: toml::Value) -> Value {
+    match value {
+        toml::Value::String(s) => Value::String(s),
+        toml::Value::Integer(i) => Value::from(i),
+        toml::Value::Float(f) => Value::from(f),
+        toml::Value::Boolean(b) => Value::Bool(b),
+        toml::Value::Datetime(d) => Value::String(d.to_string()),
+        toml::Value::Array(items) => Value::Array(items.into_iter().map(toml_to_json).collect()),
+        toml::Value::Table(table) => Value::Object(table.into_iter().map(|(k, v)| (k, toml_to_json(v))).collect()),
+    }
+}
+
+pub fn load_data_file(input: &ProjectInput, path: &str) -> Result<Value, String> {
+    let format = DataFormat::from_path(path)
+        .ok_or_else(|| format!("Unsupported data format: {}", path))?;
+    let content = input.read_to_string(path).map_err(|e| format!("Failed to read data file {}: {}", path, e))?;
+    parse_data(&content, format).map_err(|e| format!("Failed to parse data file {}: {}", path, e))
+}
+
+/// Every data source declared by a page or component in the document
+pub fn data_sources(document: &WebCoreDocument) -> Vec<&DataSource> {
+    document.pages.values().flat_map(|p| p.data.iter())
+        .chain(document.components.values().flat_map(|c| c.data.iter()))
+        .collect()
+}
+
+pub fn load_document_data(document: &WebCoreDocument, input: &ProjectInput) -> Result<DataSet, String> {
+    let mut data = DataSet::new();
+    for source in data_sources(document) {
+        // Collections come from src/content and are added by the build
+        if source.kind == DataSourceKind::File && !data.contains_key(&source.path) {
+            data.insert(source.path.clone(), load_data_file(input, &source.path)?);
+        }
+    }
+    Ok(data)
+}
+
+pub fn data_paths(document: &WebCoreDocument) -> Vec<String> {
+    let mut paths: Vec<String> = data_sources(document).iter()
+        .filter(|s| s.kind == DataSourceKind::File)
+        .map(|s| s.path.clone())
+        .collect();
+    paths.sort();
+    paths.dedup();
+    paths
+}
+
+/// Bind the declared data sources of a page or component by name
+pub fn scope_for(sources: &[DataSource], data: &DataSet) -> Scope {
+    let mut scope = Scope::new();
+    for source in sources {
+        if let Some(value) = data.get(&source.key()) {
+            scope.insert(source.name.clone(), value.clone());
+        }
+    }
+    scope
+}
+
+/// Resolve a dotted path (`post.author.name`, `posts.0`) against the scope
+pub fn lookup<'a>(scope: &'a Scope, path: &str) -> Option<&'a Value> {
+    let mut parts = path.split('.');
+    let mut value = scope.get(parts.next()?.trim())?;
+    for part in parts {
+        let part = part.trim();
+        value = match value {
+            Value::Object(map) => map.get(part)?,
+            Value::Array(items) => items.get(part.parse::<usize>().ok()?)?,
+            _ => return None,
+        };
+    }
+    Some(value)
+}
+
+/// The expressions between braces in an attribute string: `"/x/{label}"` → `label`
+pub fn segments(text: &str) -> Vec<&str> {
+    let mut found = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find('{') {
+        let Some(end) = rest[start..].find('}').map(|i| start + i) else { break };
+        found.push(rest[start + 1..end].trim());
+        rest = &rest[end + 1..];
+    }
+    found
+}
+
+/// `"/posts/{post.slug}.html"` with the paths known in `scope` filled in; the
+/// other `{...}` are left as written
+pub fn interpolate(scope: &Scope, text: &str) -> String {
+    let mut result = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find('{') {
+        let Some(end) = rest[start..].find('}').map(|i| start + i) else { break };
+        result.push_str(&rest[..start]);
+        match lookup(scope, rest[start + 1..end].trim()) {
+            Some(value) => result.push_str(&value_to_text(value)),
+            None => result.push_str(&rest[start..=end]),
+        }
+        rest = &rest[end + 1..];
+    }
+    result.push_str(rest);
+    result
+}
+
+/// Truthiness used by `if`: empty lists, empty strings, zero, false and null are false
+pub fn is_truthy(value: &Value) -> bool {
+    match value {
+        Value::Null => false,
+        Value::Bool(b) => *b,
+        Value::Number(n) => n.as_f64() != Some(0.0),
+        Value::String(s) => !s.is_empty(),
+        Value::Array(items) => !items.is_empty(),
+        Value::Object(map) => !map.is_empty(),
+    }
+}
+
+pub fn value_to_text(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        Value::Array(items) => items.iter().map(value_to_text).collect::<Vec<_>>().join(", "),
+        // `1.0` reads "1" in the browser too
+        Value::Number(n) => match n.as_f64() {
+            Some(f) if n.is_f64() && f.fract() == 0.0 && f.abs() < 1e21 => format!("{:.0}", f + 0.0),
+            _ => n.to_string(),
+        },
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csv_rows_become_objects_and_paths_resolve() {
+        let value = parse_data("title,slug\nHello,hello\nWorld,world\n", DataFormat::Csv).expect("csv ok");
+        let mut scope = Scope::new();
+        scope.insert("posts".to_string(), value);
+        assert_eq!(lookup(&scope, "posts.1.title").map(value_to_text), Some("World".to_string()));
+
+        let toml = parse_data("[site]\nname = \"Blog\"\n", DataFormat::Toml).expect("toml ok");
+        scope.insert("config".to_string(), toml);
+        assert_eq!(lookup(&scope, "config.site.name").map(value_to_text), Some("Blog".to_string()));
+        assert_eq!(value_to_text(&serde_json::json!([1.0, 2.5, -0.0])), "1, 2.5, 0");
+        assert_eq!(interpolate(&scope, "/{posts.0.slug}.html?{count}"), "/hello.html?{count}");
+    }
+}
@@ -0,0 +1,349 @@
+//! `webc fmt`: rewrites .webc files in one canonical layout
+//!
+//! The layout is printed from `parser::parse_syntax`: the tokens of the file,
+//! the `//` comments between them and where each statement starts. Every
+//! statement goes on its own line, indented by two spaces per block, and a
+//! block holding one short statement without blocks of its own stays on one
+//! line. Spacing between
+//! tokens is fixed, `"""` strings without quotes or newlines become plain
+//! strings, definitions are separated by one blank line and other blank
+//! lines collapse to one. Raw CSS (`style global`, `keyframes`) is only
+//! re-indented. The result must tokenize exactly like the input or the file
+//! is left alone.
+
+use crate::parser::{self, Statement, Syntax, Token};
+use std::collections::HashMap;
+
+const INDENT: &str = "  ";
+const MAX_WIDTH: usize = 100;
+
+/// Canonical layout of `source`
+pub fn format_source(source: &str) -> Result<String, String> {
+    let syntax = parser::parse_syntax(source).map_err(|e| e.to_string())?;
+    let printer = Printer::new(source, &syntax);
+    let mut pos = 0;
+    let items = printer.items(&mut pos, syntax.tokens.len() - 1);
+    let mut output = String::new();
+    printer.body(&mut output, &items, 0, syntax.tokens.len() - 1, true);
+
+    if parser::tokenize(&output) != parser::tokenize(source) {
+        return Err("formatting would change the meaning of the file".to_string());
+    }
+    Ok(output)
+}
+
+// A token, or a `{ }` block with what it holds
+enum Item {
+    Token(usize),
+    Block(usize, usize, Vec<Item>),
+}
+
+impl Item {
+    fn first(&self) -> usize {
+        match self {
+            Item::Token(i) | Item::Block(i, _, _) => *i,
+        }
+    }
+
+    fn last(&self) -> usize {
+        match self {
+            Item::Token(i) | Item::Block(_, i, _) => *i,
+        }
+    }
+}
+
+struct Printer<'a> {
+    source: &'a str,
+    syntax: &'a Syntax,
+    // `{` token of a CSS block → its last token and the offset of its `}`
+    verbatim: HashMap<usize, (usize, usize)>,
+    // Comments on their own line, by the token they come before
+    leading: HashMap<usize, Vec<(usize, usize)>>,
+    // Comments ending a line, by the token they follow
+    trailing: HashMap<usize, (usize, usize)>,
+    // Start of every comment outside CSS, in order
+    starts: Vec<usize>,
+}
+
+impl<'a> Printer<'a> {
+    fn new(source: &'a str, syntax: &'a Syntax) -> Self {
+        let verbatim: HashMap<usize, (usize, usize)> = syntax.verbatim.iter().map(|&(open, last, close)| (open, (last, close))).collect();
+        let mut printer = Printer { source, syntax, verbatim, leading: HashMap::new(), trailing: HashMap::new(), starts: Vec::new() };
+        for &(start, end) in &syntax.comments {
+            let in_css = syntax.verbatim.iter().any(|&(open, _, close)| syntax.tokens[open].1 < start && start < close);
+            if in_css {
+                continue;
+            }
+            let next = syntax.tokens.partition_point(|t| t.1 < start);
+            let line_start = source[..start].rfind('\n').map_or(0, |i| i + 1);
+            if source[line_start..start].trim().is_empty() || next == 0 {
+                printer.leading.entry(next).or_default().push((start, end));
+            } else {
+                printer.trailing.insert(next - 1, (start, end));
+            }
+            printer.starts.push(start);
+        }
+        printer
+    }
+
+    // Tokens from `pos` up to `end` or the `}` closing the current block
+    fn items(&self, pos: &mut usize, end: usize) -> Vec<Item> {
+        let mut items = Vec::new();
+        while *pos < end {
+            let i = *pos;
+            match self.syntax.tokens[i].0 {
+                Token::LeftBrace => {
+                    if let Some(&(last, _)) = self.verbatim.get(&i) {
+                        items.push(Item::Block(i, last, Vec::new()));
+                        *pos = last + 1;
+                        continue;
+                    }
+                    *pos += 1;
+                    let children = self.items(pos, end);
+                    items.push(Item::Block(i, *pos, children));
+                    *pos += 1;
+                }
+                Token::RightBrace => return items,
+                _ => {
+                    items.push(Item::Token(i));
+                    *pos += 1;
+                }
+            }
+        }
+        items
+    }
+
+    fn comment(&self, (start, end): (usize, usize)) -> &'a str {
+        self.source[start..end].trim_end()
+    }
+
+    fn kind(&self, statement: &[Item]) -> Statement {
+        self.syntax.statements.get(&statement[0].first()).copied().unwrap_or(Statement::Declaration)
+    }
+
+    // Items split where statements start
+    fn statements<'i>(&self, items: &'i [Item]) -> Vec<&'i [Item]> {
+        let mut statements = Vec::new();
+        let mut start = 0;
+        for (n, item) in items.iter().enumerate() {
+            if n > start && self.syntax.statements.contains_key(&item.first()) {
+                statements.push(&items[start..n]);
+                start = n;
+            }
+        }
+        if start < items.len() {
+            statements.push(&items[start..]);
+        }
+        statements
+    }
+
+    // End of the source line an item finishes, its comment included
+    fn end_of(&self, item: &Item) -> usize {
+        let last = item.last();
+        if let Some(comment) = self.trailing.get(&last) {
+            return comment.1;
+        }
+        match (item, self.verbatim.get(&item.first())) {
+            (Item::Block(..), Some(&(_, close))) => close + 1,
+            _ => self.syntax.tokens[last].2,
+        }
+    }
+
+    // A blank line before `next` when the source had one, or always between
+    // top-level definitions
+    fn separate(&self, out: &mut String, cursor: Option<usize>, next: usize, force: bool) {
+        if let Some(cursor) = cursor {
+            if force || self.source[cursor..next].matches('\n').count() >= 2 {
+                out.push('\n');
+            }
+        }
+    }
+
+    // One statement per line at `level`, with the comments and blank lines
+    // around them, up to the `close` token
+    fn body(&self, out: &mut String, items: &[Item], level: usize, close: usize, top: bool) {
+        let indent = INDENT.repeat(level);
+        let mut cursor = None;
+        for statement in self.statements(items) {
+            let first = statement[0].first();
+            let mut force = top;
+            for &comment in self.leading.get(&first).into_iter().flatten() {
+                self.separate(out, cursor, comment.0, force);
+                force = false;
+                out.push_str(&format!("{}{}\n", indent, self.comment(comment)));
+                cursor = Some(comment.1);
+            }
+            self.separate(out, cursor, self.syntax.tokens[first].1, force);
+            let text = self.statement(statement, self.kind(statement), level, indent.len(), false).unwrap_or_default();
+            out.push_str(&format!("{}{}\n", indent, text));
+            cursor = statement.last().map(|item| self.end_of(item));
+        }
+        for &comment in self.leading.get(&close).into_iter().flatten() {
+            self.separate(out, cursor, comment.0, false);
+            out.push_str(&format!("{}{}\n", indent, self.comment(comment)));
+            cursor = Some(comment.1);
+        }
+    }
+
+    // The items of one statement starting at `column`; `flat` keeps it on one
+    // line or gives up
+    fn statement(&self, items: &[Item], kind: Statement, level: usize, column: usize, flat: bool) -> Option<String> {
+        let continuation = format!("\n{}", INDENT.repeat(level + 1));
+        let mut text = String::new();
+        let mut previous = None;
+        for (n, item) in items.iter().enumerate() {
+            if n > 0 {
+                if let Some(comments) = self.leading.get(&item.first()) {
+                    for &comment in comments {
+                        text.push_str(&continuation);
+                        text.push_str(self.comment(comment));
+                    }
+                    text.push_str(&continuation);
+                    previous = None;
+                }
+            }
+            text.push_str(self.space(previous, item.first(), kind == Statement::Element));
+            match item {
+                Item::Token(i) => text.push_str(&self.token(*i)),
+                Item::Block(open, close, children) => {
+                    let column = text.rfind('\n').map_or(column + text.len(), |i| text.len() - i - 1);
+                    text.push_str(&self.block(*open, *close, children, kind, level, column, flat)?);
+                }
+            }
+            previous = Some(item.last());
+            if let Some(&comment) = self.trailing.get(&item.last()) {
+                text.push(' ');
+                text.push_str(self.comment(comment));
+                if n + 1 < items.len() {
+                    text.push_str(&continuation);
+                    previous = None;
+                }
+            }
+        }
+        Some(text)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn block(&self, open: usize, close: usize, children: &[Item], parent: Statement, level: usize, column: usize, flat: bool) -> Option<String> {
+        if let Some(&(_, end)) = self.verbatim.get(&open) {
+            return if flat { None } else { Some(self.css(open, end, level)) };
+        }
+        let (from, to) = (self.syntax.tokens[open].1, self.syntax.tokens[close].1);
+        let commented = self.starts.iter().any(|&start| from < start && start < to);
+        if children.is_empty() && !commented {
+            return Some("{}".to_string());
+        }
+        // `href={post.url}`, `= { key: value }`: an expression, not statements
+        if !self.syntax.statements.contains_key(&children[0].first()) {
+            let inner = self.statement(children, Statement::Declaration, level, column + 2, flat)?;
+            return Some(if parent == Statement::Element { format!("{{{}}}", inner) } else { format!("{{ {} }}", inner) });
+        }
+        // Only the innermost block of statements may stay on one line
+        if flat {
+            return None;
+        }
+        let statements = self.statements(children);
+        if parent != Statement::Definition && !commented && statements.len() == 1 {
+            let statement = statements[0];
+            if let Some(inner) = self.statement(statement, self.kind(statement), level, column + 2, true) {
+                let inline = format!("{{ {} }}", inner);
+                if column + inline.len() <= MAX_WIDTH {
+                    return Some(inline);
+                }
+            }
+        }
+        let mut text = String::from("{");
+        if let Some(&comment) = self.trailing.get(&open) {
+            text.push(' ');
+            text.push_str(self.comment(comment));
+        }
+        text.push('\n');
+        self.body(&mut text, children, level + 1, close, false);
+        text.push_str(&INDENT.repeat(level));
+        text.push('}');
+        Some(text)
+    }
+
+    // CSS kept as written, re-indented one level below its `{`
+    fn css(&self, open: usize, end: usize, level: usize) -> String {
+        let css = parser::dedent(&self.source[self.syntax.tokens[open].1 + 1..end]);
+        if css.trim().is_empty() {
+            return "{}".to_string();
+        }
+        let indent = INDENT.repeat(level + 1);
+        let mut text = String::from("{\n");
+        for line in css.lines() {
+            if !line.trim().is_empty() {
+                text.push_str(&indent);
+                text.push_str(line.trim_end());
+            }
+            text.push('\n');
+        }
+        text.push_str(&INDENT.repeat(level));
+        text.push('}');
+        text
+    }
+
+    fn token(&self, i: usize) -> String {
+        let (token, start, _) = &self.syntax.tokens[i];
+        match token {
+            Token::Identifier(text) | Token::Number(text) => text.clone(),
+            Token::String(text) if self.source[*start..].starts_with("\"\"\"") && text.contains(['"', '\n']) => format!("\"\"\"{}\"\"\"", text),
+            Token::String(text) => format!("\"{}\"", text),
+            Token::LeftBrace => "{".to_string(),
+            Token::RightBrace => "}".to_string(),
+            Token::LeftParen => "(".to_string(),
+            Token::RightParen => ")".to_string(),
+            Token::LeftBracket => "[".to_string(),
+            Token::RightBracket => "]".to_string(),
+            Token::Colon => ":".to_string(),
+            Token::Equals => "=".to_string(),
+            Token::Comma => ",".to_string(),
+            Token::Dot => ".".to_string(),
+            Token::Arrow => "=>".to_string(),
+            Token::Plus => "+".to_string(),
+            Token::Minus => "-".to_string(),
+            Token::Bang => "!".to_string(),
+            Token::Eof => String::new(),
+        }
+    }
+
+    // Space between two tokens; `=` is tight in element attributes
+    fn space(&self, previous: Option<usize>, next: usize, attributes: bool) -> &'static str {
+        let Some(previous) = previous else { return "" };
+        match (&self.syntax.tokens[previous].0, &self.syntax.tokens[next].0) {
+            (Token::Dot, _) | (_, Token::Dot) => "",
+            (_, Token::Colon | Token::Comma | Token::RightParen | Token::RightBracket) => "",
+            (Token::LeftParen | Token::LeftBracket | Token::Bang, _) => "",
+            // `+=`, `-=`, `==`
+            (Token::Plus | Token::Minus | Token::Equals, Token::Equals) => "",
+            (Token::Identifier(_) | Token::RightParen | Token::RightBracket, Token::LeftParen | Token::LeftBracket) => "",
+            (Token::Equals, _) | (_, Token::Equals) if attributes => "",
+            (Token::Minus, _) if self.is_unary(previous) => "",
+            _ => " ",
+        }
+    }
+
+    fn is_unary(&self, minus: usize) -> bool {
+        minus == 0
+            || matches!(
+                self.syntax.tokens[minus - 1].0,
+                Token::Equals | Token::Plus | Token::Minus | Token::Arrow | Token::Bang | Token::Colon | Token::Comma
+                    | Token::LeftParen | Token::LeftBracket | Token::LeftBrace
+            )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn layout_is_canonical_and_comments_survive() {
+        let src = "component  Card{\n\n\n      // shown on the home page\n  props { title: String  subtitle :String }\n    view {\n h2   \"{title}\"   // heading\n\n\n      if title {p \"a   b\"}else{ p \"\"\"none\"\"\" }\n  a href = { title }  on:click.prevent={count=count+1} \"x\"\n\n  }\n  state { count: Number=-1 tags: List = [ \"a\",\"b\" ] }\n  style {\n    keyframes pop {\n        0% { opacity: 0 }\n    }\n  }\n}\nstyle global {\n    body{margin:0}\n\n    a { background: url(http://example.com/x.png) }\n}\n";
+        let formatted = format_source(src).expect("formats");
+        assert_eq!(formatted, "component Card {\n  // shown on the home page\n  props {\n    title: String\n    subtitle: String\n  }\n  view {\n    h2 \"{title}\" // heading\n\n    if title { p \"a   b\" } else { p \"none\" }\n    a href={title} on:click.prevent={count = count + 1} \"x\"\n  }\n  state {\n    count: Number = -1\n    tags: List = [\"a\", \"b\"]\n  }\n  style {\n    keyframes pop {\n      0% { opacity: 0 }\n    }\n  }\n}\n\nstyle global {\n  body{margin:0}\n\n  a { background: url(http://example.com/x.png) }\n}\n");
+        assert_eq!(format_source(&formatted).expect("formats"), formatted);
+        assert!(crate::parser::parse_webc(&formatted).is_ok());
+    }
+}
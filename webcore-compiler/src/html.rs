@@ -0,0 +1,142 @@
+//! HTML content rules the generated markup has to follow
+//!
+//! Void elements (`img`, `input`, `br`...) have no content and no end tag;
+//! giving them content is always an error. The nesting checks are opt-in
+//! with `[html] validate = true` in webc.toml: they report what the browser
+//! would silently repair, such as a `div` inside a `p` (the paragraph is
+//! closed before the `div`) or a `button` inside an `a`. They run on the
+//! lowered pages, so markup a layout slot or a component brings in counts.
+
+use crate::ast::*;
+use crate::checker::Diagnostic;
+use crate::data::DataSet;
+use crate::ir::{self, Attr, Node};
+use crate::project;
+
+// `link` is not listed: in a view it is the anchor shorthand
+const VOID_ELEMENTS: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "meta", "param", "source", "track", "wbr",
+];
+
+// Start tags that close an open `p`
+const BLOCK_ELEMENTS: &[&str] = &[
+    "address", "article", "aside", "blockquote", "details", "dialog", "div", "dl", "fieldset", "figcaption",
+    "figure", "footer", "form", "h1", "h2", "h3", "h4", "h5", "h6", "header", "hgroup", "hr", "main", "menu",
+    "nav", "ol", "p", "pre", "section", "table", "ul",
+];
+
+const INTERACTIVE_ELEMENTS: &[&str] = &[
+    "a", "button", "details", "embed", "iframe", "input", "label", "select", "textarea",
+];
+
+pub fn is_void(tag: &str) -> bool {
+    VOID_ELEMENTS.contains(&tag)
+}
+
+/// Nesting errors in every page as it is rendered: layouts composed around it,
+/// components expanded and slots filled. They are reported at the page
+pub fn validate(document: &WebCoreDocument, data: &DataSet) -> Vec<Diagnostic> {
+    // Components named `*Page` are rendered as pages of their own
+    let mut document = document.clone();
+    for component in document.components.values().filter(|c| c.name.ends_with("Page")) {
+        if !document.pages.contains_key(&component.name) {
+            let page = project::component_page(component);
+            document.pages.insert(component.name.clone(), page);
+        }
+    }
+    let mut names: Vec<&String> = document.pages.keys().collect();
+    names.sort();
+
+    let mut diagnostics = Vec::new();
+    for name in names {
+        // A page that cannot be lowered fails the build with its own error
+        let Ok(lowered) = ir::lower_page(&document, name, data, None) else { continue };
+        let page = &document.pages[name];
+        let mut walk = Walk { page: name, file: &page.file, span: page.span, diagnostics: &mut diagnostics };
+        walk.nodes(&lowered.nodes, Context::default());
+    }
+    diagnostics.dedup_by(|a, b| a.file == b.file && a.span == b.span && a.message == b.message);
+    diagnostics
+}
+
+// Open elements that restrict what may appear below them
+#[derive(Clone, Copy, Default)]
+struct Context<'a> {
+    paragraph: bool,
+    interactive: Option<&'a str>,
+}
+
+struct Walk<'a, 'd> {
+    page: &'a str,
+    file: &'a str,
+    span: Span,
+    diagnostics: &'d mut Vec<Diagnostic>,
+}
+
+impl<'a> Walk<'a, '_> {
+    fn nodes(&mut self, nodes: &'a [Node], context: Context<'a>) {
+        for node in nodes {
+            match node {
+                Node::Element { tag, attributes, children } => {
+                    let tag = tag.as_str();
+                    let hidden = attributes.iter().any(|a| matches!(a, Attr::Value { name, value } if name == "type" && value == "hidden"));
+                    let interactive = INTERACTIVE_ELEMENTS.contains(&tag) && !(tag == "input" && hidden);
+                    if context.paragraph && BLOCK_ELEMENTS.contains(&tag) {
+                        self.report(tag, "p", "the browser closes the paragraph before it");
+                    }
+                    if let Some(outer) = context.interactive.filter(|_| interactive) {
+                        self.report(tag, outer, "interactive elements do not nest");
+                    }
+                    let inner = Context {
+                        paragraph: context.paragraph || tag == "p",
+                        interactive: context.interactive.or(interactive.then_some(tag)),
+                    };
+                    self.nodes(children, inner);
+                }
+                // Runtime blocks render in place
+                Node::If { then, otherwise, .. } => {
+                    self.nodes(then, context);
+                    self.nodes(otherwise, context);
+                }
+                Node::Each { children, .. } | Node::Outlet(children) => self.nodes(children, context),
+                Node::Text(_) | Node::Binding { .. } | Node::Translation { .. } | Node::Raw(_) | Node::Slot(_) => {}
+            }
+        }
+    }
+
+    fn report(&mut self, tag: &str, parent: &str, reason: &str) {
+        self.diagnostics.push(Diagnostic::error(self.file, self.span,
+            format!("page '{}' renders <{}> inside <{}>, {}", self.page, tag, parent, reason)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_webc_file;
+
+    #[test]
+    fn invalid_nesting_is_reported_at_the_page() {
+        let src = r#"
+layout MainLayout { p { slot } }
+component Card {
+  view { div { "card" } }
+}
+component AboutPage {
+  view { link to="/a" { button { "Go" } } }
+}
+page "home" {
+  span { "Intro" }
+  Card
+}
+page "plain" layout Bare { p { input type="hidden" {} span { "ok" } } }
+layout Bare { slot }
+"#;
+        let doc = parse_webc_file(src, "src/pages/home.webc").expect("parse ok");
+        let found: Vec<(usize, String)> = validate(&doc, &DataSet::new()).into_iter().map(|d| (d.span.line, d.message)).collect();
+        assert_eq!(found, vec![
+            (6, "page 'AboutPage' renders <button> inside <a>, interactive elements do not nest".to_string()),
+            (9, "page 'home' renders <div> inside <p>, the browser closes the paragraph before it".to_string()),
+        ]);
+    }
+}
@@ -0,0 +1,255 @@
+//! Message catalogs (`locales/<lang>.toml`) and `t("key", count)` lookups
+//!
+//! Nested tables flatten to dotted keys (`[nav] home = "..."` → `nav.home`).
+//! A table made only of plural categories (`zero`, `one`, `few`, `many`,
+//! `other`, ...) is a plural message selected by the first argument.
+
+use crate::ast::*;
+use crate::checker::Diagnostic;
+use crate::data::{self, Scope};
+use serde_json::Value;
+use crate::project::ProjectInput;
+use std::collections::{BTreeMap, HashMap};
+
+const PLURAL_CATEGORIES: &[&str] = &["zero", "one", "two", "few", "many", "other"];
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    Text(String),
+    Plural(BTreeMap<String, String>),
+}
+
+#[derive(Debug, Clone)]
+pub struct Catalog {
+    pub lang: String,
+    pub messages: HashMap<String, Message>,
+}
+
+/// `t("cart.items", count)` split into its key and argument expressions
+#[derive(Debug, Clone, PartialEq)]
+pub struct Call {
+    pub key: String,
+    pub args: Vec<String>,
+}
+
+/// Every `locales/*.toml`, ordered by language code
+pub fn load_catalogs(input: &ProjectInput) -> Result<Vec<Catalog>, String> {
+    let mut catalogs = Vec::new();
+    for (path, _) in input.files_in("locales") {
+        let Some(lang) = path.strip_prefix("locales/").and_then(|name| name.strip_suffix(".toml")) else { continue };
+        if lang.contains('/') {
+            continue;
+        }
+        let source = input.read_to_string(path)?;
+        catalogs.push(parse_catalog(lang, &source).map_err(|e| format!("Failed to parse {}: {}", path, e))?);
+    }
+    catalogs.sort_by(|a, b| a.lang.cmp(&b.lang));
+    Ok(catalogs)
+}
+
+pub fn parse_catalog(lang: &str, source: &str) -> Result<Catalog, String> {
+    let table: toml::Table = toml::from_str(source).map_err(|e| e.to_string())?;
+    let mut messages = HashMap::new();
+    flatten("", &table, &mut messages)?;
+    Ok(Catalog { lang: lang.to_string(), messages })
+}
+
+fn flatten(prefix: &str, table: &toml::Table, out: &mut HashMap<String, Message>) -> Result<(), String> {
+    for (name, value) in table {
+        let key = if prefix.is_empty() { name.clone() } else { format!("{}.{}", prefix, name) };
+        match value {
+            toml::Value::String(text) => {
+                out.insert(key, Message::Text(text.clone()));
+            }
+            toml::Value::Table(inner) if is_plural_table(inner) => {
+                let forms = inner.iter()
+                    .map(|(category, text)| (category.clone(), text.as_str().unwrap_or_default().to_string()))
+                    .collect();
+                out.insert(key, Message::Plural(forms));
+            }
+            toml::Value::Table(inner) => flatten(&key, inner, out)?,
+            _ => return Err(format!("message '{}' must be a string or a table", key)),
+        }
+    }
+    Ok(())
+}
+
+fn is_plural_table(table: &toml::Table) -> bool {
+    table.contains_key("other")
+        && table.iter().all(|(k, v)| PLURAL_CATEGORIES.contains(&k.as_str()) && v.is_str())
+}
+
+impl Catalog {
+    pub fn translate(&self, key: &str, args: &[Value]) -> Option<String> {
+        let template = match self.messages.get(key)? {
+            Message::Text(text) => text.clone(),
+            Message::Plural(forms) => {
+                let category = match args.first().and_then(Value::as_f64) {
+                    Some(n) if n == 0.0 && forms.contains_key("zero") => "zero",
+                    Some(n) => plural_category(&self.lang, n),
+                    None => "other",
+                };
+                forms.get(category).or_else(|| forms.get("other")).cloned()?
+            }
+        };
+        Some(substitute(&template, args))
+    }
+
+    /// Plural forms of a message, for choosing one in the browser when the
+    /// count is only known at runtime
+    pub fn forms(&self, key: &str) -> Option<BTreeMap<String, String>> {
+        match self.messages.get(key)? {
+            Message::Text(text) => Some(BTreeMap::from([("other".to_string(), text.clone())])),
+            Message::Plural(forms) => Some(forms.clone()),
+        }
+    }
+}
+
+// `{count}` and `{0}` are the first argument, `{1}`... the following ones
+fn substitute(template: &str, args: &[Value]) -> String {
+    let mut text = template.to_string();
+    for (i, arg) in args.iter().enumerate() {
+        let value = data::value_to_text(arg);
+        if i == 0 {
+            text = text.replace("{count}", &value);
+        }
+        text = text.replace(&format!("{{{}}}", i), &value);
+    }
+    text
+}
+
+/// CLDR cardinal category for the common languages; others follow English
+pub fn plural_category(lang: &str, n: f64) -> &'static str {
+    let primary = lang.split(['-', '_']).next().unwrap_or(lang).to_ascii_lowercase();
+    let integer = n.fract() == 0.0 && n >= 0.0;
+    let i = n.abs() as u64;
+    let slavic_few = integer && (2..=4).contains(&(i % 10)) && !(12..=14).contains(&(i % 100));
+    match primary.as_str() {
+        "fr" | "pt" => if n.abs() < 2.0 { "one" } else { "other" },
+        "ja" | "zh" | "ko" | "vi" | "th" | "id" => "other",
+        "ru" | "uk" | "be" | "sr" | "hr" | "bs" => {
+            if !integer {
+                "other"
+            } else if i % 10 == 1 && i % 100 != 11 {
+                "one"
+            } else if slavic_few {
+                "few"
+            } else {
+                "many"
+            }
+        }
+        "pl" => if integer && i == 1 { "one" } else if slavic_few { "few" } else if integer { "many" } else { "other" },
+        "cs" | "sk" => if integer && i == 1 { "one" } else if integer && (2..=4).contains(&i) { "few" } else { "other" },
+        _ => if n == 1.0 { "one" } else { "other" },
+    }
+}
+
+/// Recognise `t("key", arg, ...)` (single or double quotes) in an expression
+pub fn parse_call(expr: &str) -> Option<Call> {
+    let inner = expr.trim().strip_prefix("t(")?.strip_suffix(')')?;
+    let mut parts = Vec::new();
+    let mut current = String::new();
+    let mut quote: Option<char> = None;
+    for c in inner.chars() {
+        match (quote, c) {
+            (Some(q), c) if c == q => { quote = None; current.push(c); }
+            (None, '"') | (None, '\'') => { quote = Some(c); current.push(c); }
+            (None, ',') => parts.push(std::mem::take(&mut current)),
+            _ => current.push(c),
+        }
+    }
+    parts.push(current);
+    let key = parts.first()?.trim();
+    let key = key.strip_prefix('"').and_then(|k| k.strip_suffix('"'))
+        .or_else(|| key.strip_prefix('\'').and_then(|k| k.strip_suffix('\'')))?;
+    let args = parts[1..].iter().map(|a| a.trim().to_string()).filter(|a| !a.is_empty()).collect();
+    Some(Call { key: key.to_string(), args })
+}
+
+/// Value of a call argument: a name in scope or a number literal
+pub fn arg_value(scope: &Scope, arg: &str) -> Option<Value> {
+    data::lookup(scope, arg).cloned()
+        .or_else(|| arg.parse::<i64>().ok().map(Value::from))
+        .or_else(|| arg.parse::<f64>().ok().map(Value::from))
+}
+
+/// Every key used by the project that a catalog does not define
+pub fn check_catalogs(document: &WebCoreDocument, catalogs: &[Catalog]) -> Vec<Diagnostic> {
+    let mut uses = Vec::new();
+    for layout in document.layouts.values() {
+        collect_calls(&layout.content, &layout.file, &mut uses);
+    }
+    for page in document.pages.values() {
+        collect_calls(&page.content, &page.file, &mut uses);
+    }
+    for component in document.components.values() {
+        collect_calls(&component.view, &component.file, &mut uses);
+    }
+    uses.sort_by(|a, b| (&a.1, a.2.start).cmp(&(&b.1, b.2.start)));
+
+    let mut diagnostics = Vec::new();
+    if catalogs.is_empty() {
+        if let Some((key, file, span)) = uses.first() {
+            diagnostics.push(Diagnostic::warning(file, *span,
+                format!("t(\"{}\") is used but there is no locales/ directory", key)));
+        }
+        return diagnostics;
+    }
+    for catalog in catalogs {
+        for (key, file, span) in &uses {
+            if !catalog.messages.contains_key(key) {
+                diagnostics.push(Diagnostic::warning(file, *span,
+                    format!("missing translation '{}' for locale '{}'", key, catalog.lang)));
+            }
+        }
+    }
+    diagnostics
+}
+
+fn collect_calls(elements: &[Element], file: &str, out: &mut Vec<(String, String, Span)>) {
+    for element in elements {
+        match element {
+            Element::Interpolation { expr, span } => {
+                if let Some(call) = parse_call(expr) {
+                    out.push((call.key, file.to_string(), *span));
+                }
+            }
+            Element::Tag { attributes, content, .. } | Element::Component { attributes, content, .. } => {
+                for attr in attributes {
+                    if let AttributeValue::Expression(expr) = &attr.value {
+                        if let Some(call) = parse_call(expr) {
+                            out.push((call.key, file.to_string(), attr.span));
+                        }
+                    }
+                }
+                collect_calls(content, file, out);
+            }
+            Element::For { content, .. } => collect_calls(content, file, out),
+            Element::If { content, else_content, .. } => {
+                collect_calls(content, file, out);
+                collect_calls(else_content, file, out);
+            }
+            Element::Text { .. } | Element::Slot { .. } | Element::Raw(_) => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plural_messages_follow_language_rules() {
+        let fr = parse_catalog("fr", "[nav]\nhome = \"Accueil\"\n[cart.items]\none = \"{count} article\"\nother = \"{count} articles\"\n").expect("fr");
+        let en = parse_catalog("en", "[cart.items]\nzero = \"Your cart is empty\"\none = \"{count} item\"\nother = \"{count} items\"\n").expect("en");
+        assert_eq!(fr.translate("nav.home", &[]).as_deref(), Some("Accueil"));
+        assert_eq!(fr.translate("cart.items", &[Value::from(0)]).as_deref(), Some("0 article"));
+        assert_eq!(fr.translate("cart.items", &[Value::from(3)]).as_deref(), Some("3 articles"));
+        assert_eq!(en.translate("cart.items", &[Value::from(0)]).as_deref(), Some("Your cart is empty"));
+        assert_eq!(en.translate("cart.items", &[Value::from(1)]).as_deref(), Some("1 item"));
+        assert_eq!(plural_category("ru", 22.0), "few");
+        assert_eq!(plural_category("ru", 11.0), "many");
+        assert_eq!(parse_call("t('cart.items', count)"), Some(Call { key: "cart.items".to_string(), args: vec!["count".to_string()] }));
+        assert_eq!(parse_call("total"), None);
+    }
+}
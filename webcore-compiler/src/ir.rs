@@ -0,0 +1,1023 @@
+//! Intermediate representation between the AST and the generators
+//!
+//! `lower_page` resolves everything a page depends on: layouts are composed,
+//! components expanded with their props bound and their `slot` filled,
+//! build-time data substituted, state qualified per instance (`c1$count`) and
+//! handlers given ids hashed from their place on the page, so they stay the
+//! same from one build to the next. `lower_styles` scopes component styles and
+//! replaces theme tokens. `lower_element` lowers one component on its own for
+//! the Custom Elements target. The generators only print the result.
+
+use crate::ast::*;
+use crate::data::{self, DataSet, Scope};
+use crate::i18n::{self, Catalog};
+use crate::theme::Theme;
+use crate::transition;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::cell::Cell;
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+/// Slot the layouts are composed around; no `slot` written in a view can have this name
+const OUTLET: &str = "webcore:outlet";
+
+/// Bounded recursion (a tree component over nested data) stops long before this;
+/// reaching it means a recursive component never hits its terminating condition
+const MAX_COMPONENT_DEPTH: usize = 64;
+
+/// One page, ready to print
+#[derive(Debug, Clone, Serialize)]
+pub struct PageIR {
+    pub name: String,
+    /// Layouts around the page, innermost first
+    pub layouts: Vec<String>,
+    pub nodes: Vec<Node>,
+    pub handlers: Vec<HandlerMapping>,
+    pub state: PageState,
+    /// Raw escape hatches reachable from the page, for CSP and the build report
+    pub raw_blocks: Vec<RawBlock>,
+    /// Components rendered on the page, by name
+    pub components: BTreeSet<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub enum Node {
+    /// Text content, escaped when printed
+    Text(String),
+    Element {
+        tag: String,
+        attributes: Vec<Attr>,
+        children: Vec<Node>,
+    },
+    /// `{count}` read from runtime state, printed with its initial value
+    Binding { path: String, initial: String },
+    /// `t("cart.items", count)` with a runtime count: every plural form is shipped
+    Translation { path: String, forms: serde_json::Value, initial: String },
+    /// `if` over runtime state: both branches ship and the runtime picks one
+    If { condition: String, then: Vec<Node>, otherwise: Vec<Node> },
+    /// `for` over runtime state
+    Each { iterable: String, item: String, children: Vec<Node> },
+    Raw(RawBlock),
+    /// A slot nothing was passed to
+    Slot(String),
+    /// The page's own content inside its layouts, swapped by the SPA router
+    Outlet(Vec<Node>),
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub enum Attr {
+    Value { name: String, value: String },
+    Flag(String),
+    /// `on:click={ ... }`, run by the handler with this id
+    Handler { id: String, event: String },
+    /// `transition:fade={ duration: 200 }` / `animate:flip`
+    Directive { kind: String, name: String, options: Option<serde_json::Value> },
+    /// An expression with no value at build time
+    Unbound(String),
+    /// Attribute following runtime state, on the elements target; absent
+    /// while the value is null or false
+    Binding { name: String, path: String, initial: Option<String> },
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HandlerMapping {
+    /// Id bound to the element, or `<instance>:<event>` for a listener on a
+    /// component's events
+    pub id: String,
+    pub kind: HandlerKind,
+    pub event_type: String,
+    /// `on:keydown.ctrl.s` → `["ctrl", "s"]`, applied by the runtime before the handler runs
+    pub modifiers: Vec<String>,
+    pub expression: String,
+    /// Component instance the handler runs in, which its `emit(...)` calls target
+    pub instance: Option<String>,
+}
+
+/// What runs a handler
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub enum HandlerKind {
+    /// A DOM event on the element its id is bound to
+    Dom,
+    /// A listener on a component's events, called by `emit`
+    Component,
+}
+
+/// Initial state of the component instances on one page, shipped to the runtime as JSON
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PageState {
+    pub resources: Vec<serde_json::Value>,
+    pub values: serde_json::Map<String, serde_json::Value>,
+}
+
+impl PageState {
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty() && self.resources.is_empty()
+    }
+}
+
+/// A component's style rules, scoped to the elements of its view
+#[derive(Debug, Clone, Serialize)]
+pub struct ComponentIR {
+    pub name: String,
+    /// Attribute carried by every element of the component's view
+    pub scope: String,
+    pub rules: Vec<StyleRule>,
+}
+
+/// A component built on its own as a custom element
+#[derive(Debug, Clone, Serialize)]
+pub struct ElementIR {
+    pub name: String,
+    /// `wc-item-list`
+    pub tag: String,
+    /// The shadow root's content; `slot` is a native `<slot>`
+    pub nodes: Vec<Node>,
+    pub handlers: Vec<HandlerMapping>,
+    /// Props (null until set) and state defaults, by name
+    pub state: serde_json::Map<String, serde_json::Value>,
+    /// Style rules with theme tokens replaced, left unscoped for the shadow root
+    pub rules: Vec<StyleRule>,
+    pub keyframes: Vec<Keyframes>,
+    /// Tags of the other custom elements in the view
+    pub elements: BTreeSet<String>,
+}
+
+/// Lowers one page of the document
+pub fn lower_page(document: &WebCoreDocument, page_name: &str, data: &DataSet, catalog: Option<&Catalog>) -> Result<PageIR, String> {
+    let page = document.pages.get(page_name)
+        .ok_or_else(|| format!("Page '{}' not found", page_name))?;
+
+    // Resolve the layout chain (innermost first) and compose it around the page,
+    // which is lowered where the outlet ends up
+    let layouts = resolve_layout_chain(document, page)?;
+    let composed = compose_layouts(&layouts, &[Element::Slot { name: OUTLET.to_string(), span: Span::default() }]);
+
+    let mut lowering = Lowering {
+        page: page_name,
+        page_content: &page.content,
+        document,
+        data,
+        catalog,
+        counter: 0,
+        layout_counter: 0,
+        in_outlet: false,
+        handlers: Vec::new(),
+        state: PageState::default(),
+        initial: initial_stores(document),
+        components: BTreeSet::new(),
+        elements: false,
+    };
+    let names = HashMap::new();
+    let positions = Cell::new(0);
+    let frame = Frame { depth: 0, instance: None, names: &names, slot: None, scope: None, path: "", positions: &positions };
+    let nodes = lowering.lower_elements(&composed, &data::scope_for(&page.data, data), &frame)?;
+
+    let mut raw_blocks = Vec::new();
+    collect_raw_blocks(&compose_layouts(&layouts, &page.content), document, &mut HashSet::new(), &mut raw_blocks);
+
+    Ok(PageIR {
+        name: page.name.clone(),
+        layouts: layouts.iter().map(|l| l.name.clone()).collect(),
+        nodes,
+        handlers: lowering.handlers,
+        state: lowering.state,
+        raw_blocks,
+        components: lowering.components,
+    })
+}
+
+/// Lowers a component for the elements target: props and state are the
+/// element's own, components in its view stay custom elements and build-time
+/// data is rendered as on pages
+pub fn lower_element(document: &WebCoreDocument, component: &Component, data: &DataSet, theme: Option<&Theme>) -> Result<ElementIR, String> {
+    if !component.resources.is_empty() || !component.imports.is_empty() {
+        return Err(format!("component '{}' uses resources or stores, which the elements target does not support", component.name));
+    }
+    if let Some(prop) = component.props.iter().find(|p| HOST_PROPERTIES.contains(&p.name.as_str())) {
+        return Err(format!("prop '{}' would hide the element's own '{}' property; rename it", prop.name, prop.name));
+    }
+    let mut state = serde_json::Map::new();
+    for prop in &component.props {
+        state.insert(prop.name.clone(), serde_json::Value::Null);
+    }
+    for var in &component.state {
+        let value = var.default_value.as_deref().and_then(|v| serde_json::from_str(v).ok()).unwrap_or(serde_json::Value::Null);
+        state.insert(var.name.clone(), value);
+    }
+
+    let mut lowering = Lowering {
+        page: &component.name,
+        page_content: &[],
+        document,
+        data,
+        catalog: None,
+        counter: 0,
+        layout_counter: 0,
+        in_outlet: true,
+        handlers: Vec::new(),
+        state: PageState::default(),
+        initial: state.clone().into_iter().collect(),
+        components: BTreeSet::new(),
+        elements: true,
+    };
+    let names: HashMap<String, String> = state.keys().map(|name| (name.clone(), name.clone())).collect();
+    let positions = Cell::new(0);
+    let frame = Frame { depth: 0, instance: None, names: &names, slot: None, scope: None, path: "", positions: &positions };
+    let nodes = lowering.lower_elements(&component.view, &data::scope_for(&component.data, data), &frame)?;
+
+    let tag = tag_name(&component.name);
+    Ok(ElementIR {
+        name: component.name.clone(),
+        nodes,
+        handlers: lowering.handlers,
+        state,
+        rules: style_rules(component, theme, None),
+        keyframes: component.keyframes.clone(),
+        elements: lowering.components.iter().map(|name| tag_name(name)).filter(|t| *t != tag).collect(),
+        tag,
+    })
+}
+
+// Properties every element already has; a prop of the same name would replace them
+const HOST_PROPERTIES: &[&str] = &[
+    "accessKey", "attributes", "autofocus", "children", "classList", "className", "contentEditable", "dataset",
+    "dir", "draggable", "hidden", "id", "inert", "innerHTML", "innerText", "isConnected", "lang", "localName",
+    "nodeName", "nodeType", "nonce", "outerHTML", "parentElement", "parentNode", "part", "popover", "role",
+    "shadowRoot", "slot", "spellcheck", "style", "tabIndex", "tagName", "textContent", "title", "translate",
+];
+
+/// `ItemList` → `wc-item-list`
+pub fn tag_name(component: &str) -> String {
+    format!("wc-{}", kebab_case(component))
+}
+
+/// `itemCount` → `item-count`
+pub fn kebab_case(name: &str) -> String {
+    let mut result = String::new();
+    let mut previous_lower = false;
+    for c in name.chars() {
+        if c.is_uppercase() && previous_lower {
+            result.push('-');
+        }
+        previous_lower = c.is_lowercase() || c.is_ascii_digit();
+        result.extend(c.to_lowercase());
+    }
+    result
+}
+
+// Store fields hold their defaults on every page until a handler writes them
+fn initial_stores(document: &WebCoreDocument) -> Scope {
+    document.stores.values().map(|store| {
+        let fields = store.fields.iter().map(|field| {
+            let value = field.default_value.as_deref()
+                .and_then(|v| serde_json::from_str(v).ok())
+                .unwrap_or(serde_json::Value::Null);
+            (field.name.clone(), value)
+        }).collect();
+        (store.name.clone(), serde_json::Value::Object(fields))
+    }).collect()
+}
+
+/// Layouts wrapping a page, innermost first: the page's own choice, then
+/// `App.layout`, then MainLayout/default, followed by its `extends` parents
+pub fn resolve_layout_chain<'a>(document: &'a WebCoreDocument, page: &Page) -> Result<Vec<&'a Layout>, String> {
+    let requested = page.layout.as_ref()
+        .or_else(|| document.app.as_ref().and_then(|app| app.layout.as_ref()));
+    let mut layout = match requested {
+        Some(name) => document.layouts.get(name)
+            .ok_or_else(|| format!("Layout '{}' used by page '{}' not found", name, page.name))?,
+        None => document.layouts.get("MainLayout")
+            .or_else(|| document.layouts.get("default"))
+            .ok_or_else(|| "No layout found (tried MainLayout and default)".to_string())?,
+    };
+
+    let mut chain = vec![layout];
+    while let Some(parent) = &layout.extends {
+        layout = document.layouts.get(parent)
+            .ok_or_else(|| format!("Layout '{}' extends unknown layout '{}'", layout.name, parent))?;
+        if chain.iter().any(|l| l.name == layout.name) {
+            let names: Vec<&str> = chain.iter().map(|l| l.name.as_str()).chain([layout.name.as_str()]).collect();
+            return Err(format!("Layout inheritance cycle: {}", names.join(" -> ")));
+        }
+        chain.push(layout);
+    }
+    Ok(chain)
+}
+
+// Each layout's content slot receives what has been composed so far
+fn compose_layouts(layouts: &[&Layout], page_content: &[Element]) -> Vec<Element> {
+    let mut content = page_content.to_vec();
+    for layout in layouts {
+        content = fill_content_slot(&layout.content, &content);
+    }
+    content
+}
+
+fn fill_content_slot(elements: &[Element], slot_content: &[Element]) -> Vec<Element> {
+    let mut result = Vec::new();
+    for element in elements {
+        match element {
+            Element::Slot { name, .. } if name == "content" => result.extend(slot_content.iter().cloned()),
+            Element::Tag { name, attributes, content, span } => result.push(Element::Tag {
+                name: name.clone(),
+                attributes: attributes.clone(),
+                content: fill_content_slot(content, slot_content),
+                span: *span,
+            }),
+            // `Card { slot }` in a layout passes the page on as the card's content
+            Element::Component { name, attributes, content, span } => result.push(Element::Component {
+                name: name.clone(),
+                attributes: attributes.clone(),
+                content: fill_content_slot(content, slot_content),
+                span: *span,
+            }),
+            Element::For { item, iterable, content, span } => result.push(Element::For {
+                item: item.clone(),
+                iterable: iterable.clone(),
+                content: fill_content_slot(content, slot_content),
+                span: *span,
+            }),
+            Element::If { condition, content, else_content, span } => result.push(Element::If {
+                condition: condition.clone(),
+                content: fill_content_slot(content, slot_content),
+                else_content: fill_content_slot(else_content, slot_content),
+                span: *span,
+            }),
+            other => result.push(other.clone()),
+        }
+    }
+    result
+}
+
+// Raw blocks reachable from the page, following component definitions once
+fn collect_raw_blocks(elements: &[Element], document: &WebCoreDocument, visited: &mut HashSet<String>, out: &mut Vec<RawBlock>) {
+    for element in elements {
+        match element {
+            Element::Raw(block) => out.push(block.clone()),
+            Element::Tag { content, .. } | Element::For { content, .. } => collect_raw_blocks(content, document, visited, out),
+            Element::If { content, else_content, .. } => {
+                collect_raw_blocks(content, document, visited, out);
+                collect_raw_blocks(else_content, document, visited, out);
+            }
+            Element::Component { name, content, .. } => {
+                collect_raw_blocks(content, document, visited, out);
+                if let Some(component) = document.components.get(name) {
+                    if visited.insert(name.clone()) {
+                        collect_raw_blocks(&component.view, document, visited, out);
+                    }
+                }
+            }
+            Element::Text { .. } | Element::Slot { .. } | Element::Interpolation { .. } => {}
+        }
+    }
+}
+
+// What the page accumulates while it is lowered
+struct Lowering<'a> {
+    page: &'a str,
+    page_content: &'a [Element],
+    document: &'a WebCoreDocument,
+    data: &'a DataSet,
+    catalog: Option<&'a Catalog>,
+    // Instance numbering, in document order: `l1`, `l2`... around the outlet,
+    // `c1`, `c2`... inside it, so a layout's instances keep their keys on every page
+    counter: usize,
+    layout_counter: usize,
+    in_outlet: bool,
+    handlers: Vec<HandlerMapping>,
+    state: PageState,
+    // Value of every state key and store before the runtime changes it
+    initial: Scope,
+    components: BTreeSet<String>,
+    // Lowering a custom element rather than a page (see `lower_element`)
+    elements: bool,
+}
+
+// Where an element sits: inside which component instance and what its names mean
+struct Frame<'f> {
+    // Number of component expansions above the current element
+    depth: usize,
+    // Instance id of the enclosing component when it has events or state
+    instance: Option<&'f str>,
+    // Runtime names visible in the current view and the state key each one reads
+    names: &'f HashMap<String, String>,
+    // What the caller passed between the component's braces
+    slot: Option<&'f [Node]>,
+    // Style scope attribute of the enclosing component
+    scope: Option<&'f str>,
+    // Component calls leading here from the page (`/Card1/Button0`)
+    path: &'f str,
+    // Handlers and component calls met so far in this frame
+    positions: &'f Cell<usize>,
+}
+
+impl Frame<'_> {
+    // Component state lives under a per-instance key (`c1$count`), so two instances
+    // never share it; stores keep their own name
+    fn runtime_expr(&self, expr: &str) -> String {
+        map_names(expr, |name| self.names.get(name).cloned())
+    }
+
+    fn next_position(&self) -> usize {
+        let position = self.positions.get();
+        self.positions.set(position + 1);
+        position
+    }
+}
+
+// `h` and 10 hex digits of the page (none for the layouts), component path and
+// position in the frame: unique on the page, and unchanged by edits elsewhere in the project
+fn handler_id(page: &str, path: &str, position: usize) -> String {
+    let digest = Sha256::digest(format!("{}{}#{}", page, path, position).as_bytes());
+    let hex: String = digest.iter().take(5).map(|b| format!("{:02x}", b)).collect();
+    format!("h{}", hex)
+}
+
+/// `data-wc-item-list` on the elements of `ItemList`
+pub fn scope_attribute(component: &str) -> String {
+    format!("data-{}", tag_name(component))
+}
+
+// `count`, `user.name`: a value that can be followed at runtime
+fn is_path(expr: &str) -> bool {
+    expr.trim().chars().all(|c| c.is_alphanumeric() || c == '_' || c == '.' || c == '$')
+}
+
+// Build-time names are bound in the scope; anything else (state, resources) is read at runtime
+fn is_build_time(scope: &Scope, path: &str) -> bool {
+    scope.contains_key(path.split('.').next().unwrap_or(path).trim())
+}
+
+// A payload known at build time (`emit("select", item)` inside a `for` over data)
+// is inlined as JSON, since loop variables do not exist in the browser
+fn bind_emit_payload(expr: &str, scope: &Scope) -> String {
+    match parse_emit(expr) {
+        Some((event, Some(payload))) => match data::lookup(scope, &payload) {
+            Some(value) => format!("emit(\"{}\", {})", event, serde_json::to_string(value).unwrap_or_default()),
+            None => expr.to_string(),
+        },
+        _ => expr.to_string(),
+    }
+}
+
+// Props passed by the caller (`TreeNode node={child}`), evaluated in the caller's scope
+fn bind_props(component: &Component, attributes: &[Attribute], caller_scope: &Scope, scope: &mut Scope) {
+    for attr in attributes {
+        if !component.props.iter().any(|p| p.name == attr.name) {
+            continue;
+        }
+        let value = match &attr.value {
+            AttributeValue::String(value) => Some(serde_json::Value::String(data::interpolate(caller_scope, value))),
+            AttributeValue::Boolean(value) => Some(serde_json::Value::Bool(*value)),
+            AttributeValue::Expression(expr) => data::lookup(caller_scope, expr.trim()).cloned(),
+        };
+        if let Some(value) = value {
+            scope.insert(attr.name.clone(), value);
+        }
+    }
+}
+
+fn lower_directive(attr: &Attribute) -> Attr {
+    let (kind, name) = attr.name.split_once(':').unwrap_or_default();
+    let options = match &attr.value {
+        AttributeValue::Expression(expr) => Some(serde_json::Value::Object(transition::parse_options(expr).unwrap_or_default())),
+        _ => None,
+    };
+    Attr::Directive { kind: kind.to_string(), name: name.to_string(), options }
+}
+
+impl Lowering<'_> {
+    fn lower_elements(&mut self, elements: &[Element], scope: &Scope, frame: &Frame) -> Result<Vec<Node>, String> {
+        let mut nodes = Vec::new();
+        for element in elements {
+            nodes.extend(self.lower_element(element, scope, frame)?);
+        }
+        Ok(nodes)
+    }
+
+    fn lower_element(&mut self, element: &Element, scope: &Scope, frame: &Frame) -> Result<Vec<Node>, String> {
+        match element {
+            Element::Text { text, .. } => Ok(vec![Node::Text(text.clone())]),
+            Element::Tag { name, content, .. } if name == "text" => self.lower_elements(content, scope, frame),
+            Element::Tag { name, attributes, content, .. } => {
+                let tag = if name == "link" { "a" } else { name.as_str() };
+                let is_link = tag == "a";
+                let mut resolved_href: Option<String> = None;
+                let mut attrs = Vec::new();
+                for attr in attributes {
+                    if transition::is_directive(attr) {
+                        attrs.push(lower_directive(attr));
+                        continue;
+                    }
+                    match &attr.value {
+                        AttributeValue::String(value) if is_link && attr.name == "to" => resolved_href = Some(data::interpolate(scope, value)),
+                        AttributeValue::String(value) => attrs.push(Attr::Value { name: attr.name.clone(), value: data::interpolate(scope, value) }),
+                        AttributeValue::Boolean(true) => attrs.push(Attr::Flag(attr.name.clone())),
+                        AttributeValue::Boolean(false) => {}
+                        AttributeValue::Expression(expr) => {
+                            if let Some((event_type, modifiers)) = attr.event() {
+                                // Event handler: on:click={ count += 1 }, on:submit.prevent={ ... }
+                                let id = handler_id(self.id_scope(), frame.path, frame.next_position());
+                                if self.handlers.iter().any(|h| h.id == id) {
+                                    return Err(format!("Handler id {} is used twice on page '{}'", id, self.page));
+                                }
+                                let handler = HandlerMapping {
+                                    id,
+                                    kind: HandlerKind::Dom,
+                                    event_type: event_type.to_string(),
+                                    modifiers: modifiers.iter().map(|m| m.to_string()).collect(),
+                                    expression: frame.runtime_expr(&bind_emit_payload(expr, scope)),
+                                    instance: frame.instance.map(str::to_string),
+                                };
+                                attrs.push(Attr::Handler { id: handler.id.clone(), event: handler.event_type.clone() });
+                                self.handlers.push(handler);
+                            } else if let Some(call) = i18n::parse_call(expr) {
+                                attrs.push(Attr::Value { name: attr.name.clone(), value: self.translate_static(&call, scope) });
+                            } else if let Some(value) = data::lookup(scope, expr) {
+                                let value = data::value_to_text(value);
+                                if is_link && attr.name == "to" {
+                                    resolved_href = Some(value);
+                                } else {
+                                    attrs.push(Attr::Value { name: attr.name.clone(), value });
+                                }
+                            } else if self.elements && is_path(expr) {
+                                attrs.push(self.attr_binding(&attr.name, &frame.runtime_expr(expr.trim())));
+                            } else {
+                                attrs.push(Attr::Unbound(attr.name.clone()));
+                            }
+                        }
+                    }
+                }
+                if is_link {
+                    if let Some(href) = resolved_href {
+                        attrs.push(Attr::Value { name: "href".to_string(), value: href });
+                    } else if !attributes.iter().any(|a| a.name == "href") {
+                        attrs.push(Attr::Value { name: "href".to_string(), value: "#".to_string() });
+                    }
+                }
+                if let Some(scope_attr) = frame.scope {
+                    attrs.push(Attr::Flag(scope_attr.to_string()));
+                }
+                let children = self.lower_elements(content, scope, frame)?;
+                Ok(vec![Node::Element { tag: tag.to_string(), attributes: attrs, children }])
+            }
+            // Positions of its own, so the layout's handlers after the outlet get
+            // the same ids on every page
+            Element::Slot { name, .. } if name == OUTLET && frame.depth == 0 => {
+                let positions = Cell::new(0);
+                let outlet_frame = Frame { positions: &positions, ..*frame };
+                self.in_outlet = true;
+                let page = self.lower_elements(self.page_content, scope, &outlet_frame);
+                self.in_outlet = false;
+                Ok(vec![Node::Outlet(page?)])
+            }
+            // The shadow root's own slots, filled by the element's children
+            Element::Slot { name, .. } if self.elements => {
+                let attributes = if name == "content" { Vec::new() } else { vec![Attr::Value { name: "name".to_string(), value: name.clone() }] };
+                Ok(vec![Node::Element { tag: "slot".to_string(), attributes, children: Vec::new() }])
+            }
+            Element::Slot { name, .. } => match frame.slot {
+                Some(nodes) if name == "content" => Ok(nodes.to_vec()),
+                _ => Ok(vec![Node::Slot(name.clone())]),
+            },
+            Element::Component { name, attributes, content, .. } => match self.document.components.get(name) {
+                Some(component) if self.elements => self.lower_custom_element(component, attributes, content, scope, frame),
+                Some(component) => self.lower_component(component, attributes, content, scope, frame),
+                None => {
+                    // Not a known component: kept as an element of that name
+                    let attrs = attributes.iter().filter_map(|attr| match &attr.value {
+                        AttributeValue::String(value) => Some(Attr::Value { name: attr.name.clone(), value: data::interpolate(scope, value) }),
+                        AttributeValue::Boolean(true) => Some(Attr::Flag(attr.name.clone())),
+                        AttributeValue::Boolean(false) => None,
+                        AttributeValue::Expression(_) => Some(Attr::Unbound(attr.name.clone())),
+                    }).collect();
+                    let children = self.lower_elements(content, scope, frame)?;
+                    Ok(vec![Node::Element { tag: name.clone(), attributes: attrs, children }])
+                }
+            },
+            Element::Interpolation { expr, .. } => {
+                if let Some(value) = data::lookup(scope, expr) {
+                    // Data known at build time is rendered directly
+                    return Ok(vec![Node::Text(data::value_to_text(value))]);
+                }
+                if let Some(call) = i18n::parse_call(expr) {
+                    if self.elements {
+                        return Err(format!("'{}' is not supported by the elements target", expr.trim()));
+                    }
+                    return Ok(vec![self.lower_translation(&call, scope, frame)]);
+                }
+                Ok(vec![self.binding(&frame.runtime_expr(expr))])
+            }
+            Element::Raw(block) => Ok(vec![Node::Raw(block.clone())]),
+            Element::If { condition, content, else_content, .. } if !is_build_time(scope, condition) => {
+                // State and resources only exist in the browser
+                let then = self.lower_elements(content, scope, frame)?;
+                let otherwise = self.lower_elements(else_content, scope, frame)?;
+                Ok(vec![Node::If { condition: frame.runtime_expr(condition), then, otherwise }])
+            }
+            Element::If { condition, content, else_content, .. } => {
+                let branch = if data::lookup(scope, condition).is_some_and(data::is_truthy) { content } else { else_content };
+                self.lower_elements(branch, scope, frame)
+            }
+            Element::For { item, iterable, content, .. } if !is_build_time(scope, iterable) => {
+                // The loop variable shadows any state of the same name inside the loop
+                let mut names = frame.names.clone();
+                names.remove(item);
+                let item_frame = Frame { names: &names, ..*frame };
+                let children = self.lower_elements(content, scope, &item_frame)?;
+                Ok(vec![Node::Each { iterable: frame.runtime_expr(iterable), item: item.clone(), children }])
+            }
+            Element::For { item, iterable, content, .. } => {
+                let items = match data::lookup(scope, iterable) {
+                    Some(serde_json::Value::Array(items)) => items.clone(),
+                    Some(serde_json::Value::Object(map)) => map.values().cloned().collect(),
+                    Some(_) => return Err(format!("'{}' is not a list and cannot be used in a for loop", iterable)),
+                    None => return Err(format!("Unknown data source '{}' in for loop", iterable)),
+                };
+                let mut nodes = Vec::new();
+                for value in items {
+                    let mut item_scope = scope.clone();
+                    item_scope.insert(item.clone(), value);
+                    nodes.extend(self.lower_elements(content, &item_scope, frame)?);
+                }
+                Ok(nodes)
+            }
+        }
+    }
+
+    // Replaces the component with its view, in a frame of its own
+    fn lower_component(&mut self, component: &Component, attributes: &[Attribute], content: &[Element], scope: &Scope, frame: &Frame) -> Result<Vec<Node>, String> {
+        if frame.depth >= MAX_COMPONENT_DEPTH {
+            return Err(format!("Component '{}' is nested more than {} levels deep; does its recursion have a terminating `if`?", component.name, MAX_COMPONENT_DEPTH));
+        }
+        self.components.insert(component.name.clone());
+        // What the caller put between the braces renders in the caller's frame
+        let slot = if content.is_empty() { None } else { Some(self.lower_elements(content, scope, frame)?) };
+        let path = format!("{}/{}{}", frame.path, component.name, frame.next_position());
+        let positions = Cell::new(0);
+
+        let mut component_scope = data::scope_for(&component.data, self.data);
+        bind_props(component, attributes, scope, &mut component_scope);
+        let mut instance = None;
+        let mut names = HashMap::new();
+        // A prop given a runtime value (`Badge count={count}`) reads the caller's state
+        for attr in attributes {
+            if let AttributeValue::Expression(expr) = &attr.value {
+                if is_path(expr) && component.props.iter().any(|p| p.name == attr.name) && !component_scope.contains_key(&attr.name) {
+                    names.insert(attr.name.clone(), frame.runtime_expr(expr.trim()));
+                }
+            }
+        }
+        if !component.events.is_empty() || !component.state.is_empty() || !component.resources.is_empty() {
+            let id = self.next_instance();
+            // `on:select` on the component runs in the caller, when the instance emits
+            for attr in attributes {
+                if let (Some(event), AttributeValue::Expression(expr)) = (attr.name.strip_prefix("on:"), &attr.value) {
+                    self.handlers.push(HandlerMapping {
+                        id: format!("{}:{}", id, event),
+                        kind: HandlerKind::Component,
+                        event_type: event.to_string(),
+                        modifiers: Vec::new(),
+                        expression: frame.runtime_expr(&bind_emit_payload(expr, scope)),
+                        instance: frame.instance.map(str::to_string),
+                    });
+                }
+            }
+            self.declare_instance_state(component, &id, &mut names);
+            instance = Some(id);
+        }
+        let scope_attr = (!component.style.is_empty()).then(|| scope_attribute(&component.name));
+        let component_frame = Frame {
+            depth: frame.depth + 1,
+            instance: instance.as_deref(),
+            names: &names,
+            slot: slot.as_deref(),
+            scope: scope_attr.as_deref(),
+            path: &path,
+            positions: &positions,
+        };
+        self.lower_elements(&component.view, &component_scope, &component_frame)
+    }
+
+    // Elements target: another component stays a custom element, its props
+    // given as attributes and its `on:` listeners bound to the events it dispatches
+    fn lower_custom_element(&mut self, component: &Component, attributes: &[Attribute], content: &[Element], scope: &Scope, frame: &Frame) -> Result<Vec<Node>, String> {
+        self.components.insert(component.name.clone());
+        let mut attrs = Vec::new();
+        for attr in attributes {
+            let name = kebab_case(&attr.name);
+            match &attr.value {
+                AttributeValue::String(value) => attrs.push(Attr::Value { name, value: data::interpolate(scope, value) }),
+                AttributeValue::Boolean(true) => attrs.push(Attr::Flag(name)),
+                AttributeValue::Boolean(false) => {}
+                AttributeValue::Expression(expr) => match attr.event() {
+                    Some((event, modifiers)) => {
+                        let handler = HandlerMapping {
+                            id: handler_id(self.id_scope(), frame.path, frame.next_position()),
+                            kind: HandlerKind::Dom,
+                            event_type: event.to_string(),
+                            modifiers: modifiers.iter().map(|m| m.to_string()).collect(),
+                            expression: frame.runtime_expr(&bind_emit_payload(expr, scope)),
+                            instance: None,
+                        };
+                        attrs.push(Attr::Handler { id: handler.id.clone(), event: handler.event_type.clone() });
+                        self.handlers.push(handler);
+                    }
+                    // Lists and objects are passed as JSON, which the element parses back
+                    None => match data::lookup(scope, expr.trim()) {
+                        Some(value @ (serde_json::Value::Array(_) | serde_json::Value::Object(_))) => attrs.push(Attr::Value { name, value: value.to_string() }),
+                        Some(value) => attrs.push(Attr::Value { name, value: data::value_to_text(value) }),
+                        None if is_path(expr) => attrs.push(self.attr_binding(&name, &frame.runtime_expr(expr.trim()))),
+                        None => attrs.push(Attr::Unbound(name)),
+                    },
+                },
+            }
+        }
+        let children = self.lower_elements(content, scope, frame)?;
+        Ok(vec![Node::Element { tag: tag_name(&component.name), attributes: attrs, children }])
+    }
+
+    fn attr_binding(&self, name: &str, path: &str) -> Attr {
+        let initial = match data::lookup(&self.initial, path) {
+            None | Some(serde_json::Value::Null) | Some(serde_json::Value::Bool(false)) => None,
+            Some(serde_json::Value::Bool(true)) => Some(String::new()),
+            Some(value @ (serde_json::Value::Array(_) | serde_json::Value::Object(_))) => Some(value.to_string()),
+            Some(value) => Some(data::value_to_text(value)),
+        };
+        Attr::Binding { name: name.to_string(), path: path.to_string(), initial }
+    }
+
+    // Registers the instance's state and resources with the page and names them in its view
+    // Handlers of the layouts do not depend on the page they are rendered around
+    fn id_scope(&self) -> &str {
+        if self.in_outlet { self.page } else { "" }
+    }
+
+    fn next_instance(&mut self) -> String {
+        if self.in_outlet {
+            self.counter += 1;
+            format!("c{}", self.counter)
+        } else {
+            self.layout_counter += 1;
+            format!("l{}", self.layout_counter)
+        }
+    }
+
+    fn declare_instance_state(&mut self, component: &Component, instance: &str, names: &mut HashMap<String, String>) {
+        for var in &component.state {
+            let key = format!("{}${}", instance, var.name);
+            let value = var.default_value.as_deref()
+                .and_then(|v| serde_json::from_str(v).ok())
+                .unwrap_or(serde_json::Value::Null);
+            self.initial.insert(key.clone(), value.clone());
+            self.state.values.insert(key.clone(), value);
+            names.insert(var.name.clone(), key);
+        }
+        for resource in &component.resources {
+            let key = format!("{}${}", instance, resource.name);
+            // A bound resource starts fetching as soon as the runtime takes over
+            self.initial.insert(key.clone(), serde_json::json!({ "loading": true, "error": null, "data": null }));
+            names.insert(resource.name.clone(), key);
+        }
+        let qualify = |expr: &str| map_names(expr, |name| names.get(name).cloned());
+        for resource in &component.resources {
+            let mut url = resource.url.clone();
+            let mut dependencies = Vec::new();
+            for dependency in resource.dependencies() {
+                let qualified = qualify(&dependency);
+                url = url.replace(&format!("{{{}}}", dependency), &format!("{{{}}}", qualified));
+                let root = qualified.split('.').next().unwrap_or_default().to_string();
+                if !dependencies.contains(&root) {
+                    dependencies.push(root);
+                }
+            }
+            self.state.resources.push(serde_json::json!({
+                "name": names[&resource.name],
+                "url": url,
+                "dependencies": dependencies,
+            }));
+        }
+    }
+
+    // `{count}` shows the state's initial value until the runtime binds it; names
+    // with no value yet (loop items, unknown paths) start empty
+    fn binding(&self, path: &str) -> Node {
+        let initial = data::lookup(&self.initial, path).map(data::value_to_text).unwrap_or_default();
+        Node::Binding { path: path.to_string(), initial }
+    }
+
+    // `t("cart.items", count)` with every argument known at build time; a missing
+    // message falls back to its key (the checker reports it per locale)
+    fn translate_static(&self, call: &i18n::Call, scope: &Scope) -> String {
+        let args: Vec<serde_json::Value> = call.args.iter()
+            .map(|arg| i18n::arg_value(scope, arg).unwrap_or(serde_json::Value::Null))
+            .collect();
+        self.catalog.and_then(|c| c.translate(&call.key, &args)).unwrap_or_else(|| call.key.clone())
+    }
+
+    // A count held in runtime state is only known in the browser, so the plural
+    // forms are shipped with the element and chosen there
+    fn lower_translation(&self, call: &i18n::Call, scope: &Scope, frame: &Frame) -> Node {
+        if let (Some(var), Some(catalog)) = (call.args.first(), self.catalog) {
+            if i18n::arg_value(scope, var).is_none() {
+                if let Some(forms) = catalog.forms(&call.key) {
+                    let path = frame.runtime_expr(var);
+                    let mut args: Vec<serde_json::Value> = call.args.iter()
+                        .map(|arg| i18n::arg_value(scope, arg).unwrap_or(serde_json::Value::Null))
+                        .collect();
+                    args[0] = data::lookup(&self.initial, &path).cloned().unwrap_or(serde_json::Value::Null);
+                    let initial = catalog.translate(&call.key, &args).unwrap_or_default();
+                    return Node::Translation { path, forms: serde_json::json!(forms), initial };
+                }
+            }
+        }
+        Node::Text(self.translate_static(call, scope))
+    }
+}
+
+/// Style rules of every component that has some, scoped to the component and
+/// with theme tokens (`color-primary`, `radius-card`) replaced by their variables
+pub fn lower_styles(document: &WebCoreDocument, theme: Option<&Theme>) -> Vec<ComponentIR> {
+    let mut components: Vec<&Component> = document.components.values().filter(|c| !c.style.is_empty()).collect();
+    components.sort_by(|a, b| a.name.cmp(&b.name));
+    components.into_iter().map(|component| {
+        let scope = scope_attribute(&component.name);
+        let rules = style_rules(component, theme, Some(&scope));
+        ComponentIR { name: component.name.clone(), scope, rules }
+    }).collect()
+}
+
+fn style_rules(component: &Component, theme: Option<&Theme>, scope: Option<&str>) -> Vec<StyleRule> {
+    component.style.iter().map(|rule| StyleRule {
+        selector: scope.map_or_else(|| rule.selector.clone(), |scope| scope_selector(&rule.selector, scope)),
+        properties: rule.properties.iter().map(|p| StyleProperty {
+            name: p.name.clone(),
+            value: replace_theme_tokens(&p.value, theme),
+        }).collect(),
+        span: rule.span,
+    }).collect()
+}
+
+// `li:hover` → `li[data-wc-list]:hover`: the rule only matches the component's own elements
+fn scope_selector(selector: &str, scope: &str) -> String {
+    selector.split(',').map(|part| {
+        let part = part.trim();
+        let (ancestors, last) = part.rsplit_once(' ').map(|(a, l)| (format!("{} ", a), l)).unwrap_or_default();
+        let last = if ancestors.is_empty() { part } else { last };
+        let at = last.find(':').filter(|i| *i > 0).unwrap_or(last.len());
+        format!("{}{}[{}]{}", ancestors, &last[..at], scope, &last[at..])
+    }).collect::<Vec<_>>().join(", ")
+}
+
+fn replace_theme_tokens(value: &str, theme: Option<&Theme>) -> String {
+    let Some(theme) = theme else { return value.to_string() };
+    let groups = [("color", &theme.colors), ("font", &theme.fonts), ("radius", &theme.radius), ("breakpoint", &theme.breakpoints)];
+    value.split(' ').map(|word| {
+        let is_token = groups.iter().any(|(group, values)| {
+            word.strip_prefix(group).and_then(|rest| rest.strip_prefix('-')).is_some_and(|key| values.contains_key(key))
+        });
+        if is_token { format!("var(--{})", word) } else { word.to_string() }
+    }).collect::<Vec<_>>().join(" ")
+}
+
+/// `emit("select", item)` in a handler: the event name and its payload expression
+pub fn parse_emit(expr: &str) -> Option<(String, Option<String>)> {
+    let inner = expr.trim().strip_prefix("emit(")?.strip_suffix(')')?.trim();
+    let quote = inner.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+    let end = inner[1..].find(quote)? + 1;
+    let name = inner[1..end].to_string();
+    let payload = inner[end + 1..].trim().strip_prefix(',').map(|p| p.trim().to_string()).filter(|p| !p.is_empty());
+    Some((name, payload))
+}
+
+const BUILTINS: &[&str] = &["true", "false", "null", "undefined"];
+
+/// Root names an expression reads or writes: `count = max(0, count - 1)` → `count`.
+/// Property accesses, function calls, literals and `$`-prefixed runtime values are skipped.
+pub fn expression_names(expr: &str) -> Vec<String> {
+    let mut names = Vec::new();
+    map_names(expr, |name| {
+        if !names.iter().any(|n| n == name) {
+            names.push(name.to_string());
+        }
+        None
+    });
+    names
+}
+
+/// Rewrites the root names of an expression (the ones `expression_names` reports),
+/// keeping those for which `rename` returns `None`
+pub fn map_names(expr: &str, mut rename: impl FnMut(&str) -> Option<String>) -> String {
+    let chars: Vec<char> = expr.chars().collect();
+    let mut result = String::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let start = i;
+        if c == '"' || c == '\'' {
+            i += 1;
+            while i < chars.len() && chars[i] != c {
+                i += 1;
+            }
+            i = (i + 1).min(chars.len());
+        } else if c.is_ascii_digit() {
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '.') {
+                i += 1;
+            }
+        } else if c.is_alphabetic() || c == '_' || c == '$' {
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_' || chars[i] == '$') {
+                i += 1;
+            }
+            let name: String = chars[start..i].iter().collect();
+            let after_dot = chars[..start].iter().rev().find(|c| !c.is_whitespace()) == Some(&'.');
+            let is_call = chars[i..].iter().find(|c| !c.is_whitespace()) == Some(&'(');
+            if !after_dot && !is_call && !name.starts_with('$') && !BUILTINS.contains(&name.as_str()) {
+                if let Some(renamed) = rename(&name) {
+                    result.push_str(&renamed);
+                    continue;
+                }
+            }
+        } else {
+            i += 1;
+        }
+        result.extend(&chars[start..i]);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn layout_instances_keep_their_keys_on_every_page() {
+        let src = r#"
+layout MainLayout { header { Counter } main { slot } footer { Counter } }
+component Counter {
+  state { count: Number = 0 }
+  view { button on:click={ count += 1 } "{count}" }
+}
+page "a" { Counter }
+page "b" { p { Counter } p { Counter } }
+"#;
+        let doc = crate::parser::parse_webc(src).expect("parse ok");
+        let lowered = |name: &str| lower_page(&doc, name, &DataSet::new(), None).expect("lowers");
+        let (a, b) = (lowered("a"), lowered("b"));
+        let keys = |page: &PageIR| page.state.values.keys().cloned().collect::<Vec<_>>();
+        assert_eq!(keys(&a), vec!["c1$count", "l1$count", "l2$count"]);
+        assert_eq!(keys(&b), vec!["c1$count", "c2$count", "l1$count", "l2$count"]);
+        let layout_handlers = |page: &PageIR| page.handlers.iter()
+            .filter(|h| h.expression.starts_with("l")).map(|h| (h.id.clone(), h.expression.clone())).collect::<Vec<_>>();
+        assert_eq!(layout_handlers(&a), layout_handlers(&b));
+    }
+
+    #[test]
+    fn components_are_expanded_with_props_and_slot_content() {
+        let src = r#"
+layout MainLayout { main { slot } }
+component Card {
+  props { title: String }
+  state { open: Boolean = false }
+  view { section { h2 "{title}" button on:click={ open = !open } "Toggle" slot } }
+  style { h2 { color: color-primary } }
+}
+page "home" { Card title="Hello" { p "Inside" } }
+"#;
+        let doc = crate::parser::parse_webc(src).expect("parse ok");
+        let page = lower_page(&doc, "home", &DataSet::new(), None).expect("lowers");
+        assert_eq!(page.layouts, vec!["MainLayout"]);
+        assert_eq!(page.components.iter().collect::<Vec<_>>(), vec!["Card"]);
+        let Node::Element { tag, children, .. } = &page.nodes[0] else { panic!("main element") };
+        assert_eq!(tag, "main");
+        let [Node::Outlet(children)] = children.as_slice() else { panic!("page content in the outlet") };
+        let Node::Element { attributes, children: section, .. } = &children[0] else { panic!("section element") };
+        assert!(matches!(attributes.as_slice(), [Attr::Flag(scope)] if scope == "data-wc-card"));
+        assert!(matches!(&section[0], Node::Element { children, .. } if matches!(children.as_slice(), [Node::Text(t)] if t == "Hello")));
+        assert!(matches!(&section[1], Node::Element { attributes, .. } if matches!(attributes.first(), Some(Attr::Handler { id, .. }) if *id == handler_id("home", "/Card0", 0))));
+        // Slot content belongs to the page, so it does not carry the card's scope
+        assert!(matches!(&section[2], Node::Element { tag, attributes, .. } if tag == "p" && attributes.is_empty()));
+        assert_eq!(page.handlers[0].expression, "c1$open=!c1$open");
+        assert_eq!(page.state.values.get("c1$open"), Some(&serde_json::json!(false)));
+    }
+
+    #[test]
+    fn styles_are_scoped_and_theme_tokens_become_variables() {
+        let src = "component List {\n  view { ul { li \"x\" } }\n  style {\n    li:hover { border: \"1px solid color-primary\" }\n    ul { border-radius: radius-card margin: \"0\" }\n  }\n}\n";
+        let doc = crate::parser::parse_webc(src).expect("parse ok");
+        let theme = Theme {
+            name: "t".to_string(),
+            colors: HashMap::from([("primary".to_string(), "#1E88E5".to_string())]),
+            fonts: HashMap::new(),
+            radius: HashMap::from([("card".to_string(), "12px".to_string())]),
+            breakpoints: HashMap::new(),
+        };
+        let styles = lower_styles(&doc, Some(&theme));
+        let rules: Vec<(String, Vec<String>)> = styles[0].rules.iter()
+            .map(|r| (r.selector.clone(), r.properties.iter().map(|p| format!("{}: {}", p.name, p.value)).collect()))
+            .collect();
+        assert_eq!(rules, vec![
+            ("li[data-wc-list]:hover".to_string(), vec!["border: 1px solid var(--color-primary)".to_string()]),
+            ("ul[data-wc-list]".to_string(), vec!["border-radius: var(--radius-card)".to_string(), "margin: 0".to_string()]),
+        ]);
+    }
+}
@@ -0,0 +1,28 @@
+//! WebCore compiler: `.webc` sources to static pages
+//!
+//! [`compile`] builds a project held in memory; the `webc` binary reads the
+//! project directory into a [`ProjectInput`] and writes the [`BuildOutput`]
+//! files to `dist/`.
+
+pub mod parser;
+pub mod ast;
+pub mod codegen { pub mod codegen_html; pub mod codegen_css; pub mod codegen_js; pub mod codegen_elements; }
+pub mod theme;
+pub mod css_processor;
+pub mod data;
+pub mod content;
+pub mod security;
+pub mod checker;
+pub mod i18n;
+pub mod transition;
+pub mod modifiers;
+pub mod a11y;
+pub mod html;
+pub mod assets;
+pub mod seo;
+pub mod fmt;
+pub mod lsp;
+pub mod ir;
+pub mod project;
+
+pub use project::{compile, BuildOptions, BuildOutput, ProjectInput, Target};
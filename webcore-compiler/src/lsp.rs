@@ -0,0 +1,828 @@
+//! `webc lsp`: language server over stdio
+//!
+//! The workspace is every .webc file under the project root, with the
+//! editor's unsaved buffers in place of the files on disk. Each change
+//! re-parses and re-checks the whole project, so diagnostics, definitions
+//! and renames always see the same document the build would.
+
+use crate::ast::*;
+use crate::checker::{self, Diagnostic, Severity};
+use crate::parser::{self, Token};
+use crate::project::{self, ProjectInput};
+use crate::{a11y, html};
+use crate::theme::{self, Theme};
+use crate::transition;
+use lsp_server::{Connection, Message, Notification, Request, Response};
+use lsp_types::{
+    CompletionItem, CompletionItemKind, CompletionOptions, CompletionParams, CompletionResponse,
+    DidChangeTextDocumentParams, DidCloseTextDocumentParams, DidOpenTextDocumentParams, GotoDefinitionParams,
+    GotoDefinitionResponse, Hover, HoverContents, HoverParams, HoverProviderCapability, InitializeParams, Location,
+    MarkupContent, MarkupKind, OneOf, Position, PublishDiagnosticsParams, Range, RenameParams, ServerCapabilities,
+    TextDocumentSyncCapability, TextDocumentSyncKind, TextEdit, Uri, WorkspaceEdit,
+};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+const HTML_TAGS: &[&str] = &[
+    "a", "article", "aside", "button", "div", "footer", "form", "h1", "h2", "h3", "h4", "h5", "h6", "header",
+    "img", "input", "label", "li", "link", "main", "nav", "ol", "option", "p", "section", "select", "span",
+    "table", "tbody", "td", "textarea", "th", "thead", "tr", "ul",
+];
+
+pub fn run() -> Result<(), String> {
+    let (connection, io_threads) = Connection::stdio();
+    let capabilities = ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
+        definition_provider: Some(OneOf::Left(true)),
+        completion_provider: Some(CompletionOptions {
+            trigger_characters: Some(vec!["{".to_string(), " ".to_string()]),
+            ..Default::default()
+        }),
+        hover_provider: Some(HoverProviderCapability::Simple(true)),
+        rename_provider: Some(OneOf::Left(true)),
+        ..Default::default()
+    };
+    let capabilities = serde_json::to_value(capabilities).map_err(|e| e.to_string())?;
+    let params = connection.initialize(capabilities).map_err(|e| format!("LSP initialize failed: {}", e))?;
+    let params: InitializeParams = serde_json::from_value(params).map_err(|e| format!("Invalid initialize params: {}", e))?;
+    let root = params.workspace_folders.as_ref()
+        .and_then(|folders| folders.first())
+        .and_then(|folder| uri_to_path(&folder.uri))
+        .or_else(|| std::env::current_dir().ok())
+        .ok_or("no workspace folder")?;
+
+    let mut server = Server { connection: &connection, workspace: Workspace::load(&root), published: HashSet::new() };
+    server.publish_diagnostics()?;
+    for message in &connection.receiver {
+        match message {
+            Message::Request(request) => {
+                if connection.handle_shutdown(&request).map_err(|e| e.to_string())? {
+                    break;
+                }
+                server.handle_request(request)?;
+            }
+            Message::Notification(notification) => server.handle_notification(notification)?,
+            Message::Response(_) => {}
+        }
+    }
+    // The writer thread exits once the connection is gone
+    drop(server);
+    drop(connection);
+    io_threads.join().map_err(|e| e.to_string())
+}
+
+struct Server<'a> {
+    connection: &'a Connection,
+    workspace: Workspace,
+    // Files that were sent diagnostics, so fixed ones get an empty list
+    published: HashSet<String>,
+}
+
+impl Server<'_> {
+    fn send(&self, message: Message) -> Result<(), String> {
+        self.connection.sender.send(message).map_err(|e| e.to_string())
+    }
+
+    fn handle_request(&mut self, request: Request) -> Result<(), String> {
+        let result = match request.method.as_str() {
+            "textDocument/definition" => self.params(&request).map(|p: GotoDefinitionParams| {
+                let position = p.text_document_position_params;
+                serde_json::to_value(self.workspace.definition(&position.text_document.uri, position.position)
+                    .map(GotoDefinitionResponse::Scalar))
+            }),
+            "textDocument/completion" => self.params(&request).map(|p: CompletionParams| {
+                let position = p.text_document_position;
+                serde_json::to_value(CompletionResponse::Array(self.workspace.completions(&position.text_document.uri, position.position)))
+            }),
+            "textDocument/hover" => self.params(&request).map(|p: HoverParams| {
+                let position = p.text_document_position_params;
+                serde_json::to_value(self.workspace.hover(&position.text_document.uri, position.position))
+            }),
+            // A name that is not valid or already taken is refused like bad params
+            "textDocument/rename" => self.params(&request).and_then(|p: RenameParams| {
+                let position = p.text_document_position;
+                self.workspace.rename(&position.text_document.uri, position.position, &p.new_name)
+            }).map(serde_json::to_value),
+            _ => {
+                let response = Response::new_err(request.id, lsp_server::ErrorCode::MethodNotFound as i32, format!("unsupported request {}", request.method));
+                return self.send(Message::Response(response));
+            }
+        };
+        let response = match result {
+            Ok(Ok(value)) => Response::new_ok(request.id, value),
+            Ok(Err(e)) => Response::new_err(request.id, lsp_server::ErrorCode::InternalError as i32, e.to_string()),
+            Err(e) => Response::new_err(request.id, lsp_server::ErrorCode::InvalidParams as i32, e),
+        };
+        self.send(Message::Response(response))
+    }
+
+    fn params<T: serde::de::DeserializeOwned>(&self, request: &Request) -> Result<T, String> {
+        serde_json::from_value(request.params.clone()).map_err(|e| format!("invalid params for {}: {}", request.method, e))
+    }
+
+    fn handle_notification(&mut self, notification: Notification) -> Result<(), String> {
+        let params = notification.params;
+        match notification.method.as_str() {
+            "textDocument/didOpen" => {
+                let Ok(p) = serde_json::from_value::<DidOpenTextDocumentParams>(params) else { return Ok(()) };
+                self.workspace.set_buffer(&p.text_document.uri, p.text_document.text);
+            }
+            "textDocument/didChange" => {
+                let Ok(p) = serde_json::from_value::<DidChangeTextDocumentParams>(params) else { return Ok(()) };
+                if let Some(change) = p.content_changes.into_iter().last() {
+                    self.workspace.set_buffer(&p.text_document.uri, change.text);
+                }
+            }
+            "textDocument/didClose" => {
+                let Ok(p) = serde_json::from_value::<DidCloseTextDocumentParams>(params) else { return Ok(()) };
+                self.workspace.reload(&p.text_document.uri);
+            }
+            _ => return Ok(()),
+        }
+        self.publish_diagnostics()
+    }
+
+    fn publish_diagnostics(&mut self) -> Result<(), String> {
+        let mut by_file: BTreeMap<String, Vec<lsp_types::Diagnostic>> = BTreeMap::new();
+        for file in self.published.iter().chain(self.workspace.files.keys()) {
+            by_file.insert(file.clone(), Vec::new());
+        }
+        let analysis = self.workspace.analysis();
+        let project_checks = self.workspace.project_checks(&analysis.document);
+        for diagnostic in analysis.diagnostics.iter().chain(&project_checks) {
+            let file = if diagnostic.file.is_empty() { continue } else { &diagnostic.file };
+            let range = self.workspace.range(file, diagnostic.span);
+            by_file.entry(file.clone()).or_default().push(lsp_types::Diagnostic {
+                range,
+                severity: Some(match diagnostic.severity {
+                    Severity::Error => lsp_types::DiagnosticSeverity::ERROR,
+                    Severity::Warning => lsp_types::DiagnosticSeverity::WARNING,
+                }),
+                code: diagnostic.rule.map(|r| lsp_types::NumberOrString::String(r.to_string())),
+                source: Some("webc".to_string()),
+                message: diagnostic.message.clone(),
+                ..Default::default()
+            });
+        }
+        self.published = by_file.iter().filter(|(_, d)| !d.is_empty()).map(|(f, _)| f.clone()).collect();
+        for (file, diagnostics) in by_file {
+            let params = PublishDiagnosticsParams { uri: self.workspace.uri(&file), diagnostics, version: None };
+            self.send(Message::Notification(Notification::new("textDocument/publishDiagnostics".to_string(), params)))?;
+        }
+        Ok(())
+    }
+}
+
+/// Project sources by path relative to the root, as the build names them
+pub struct Workspace {
+    root: PathBuf,
+    files: BTreeMap<String, String>,
+    theme: Option<Theme>,
+}
+
+struct Analysis {
+    document: WebCoreDocument,
+    diagnostics: Vec<Diagnostic>,
+}
+
+// What the name under the cursor refers to
+#[derive(Debug, PartialEq)]
+enum Symbol {
+    Component(String),
+    Layout(String),
+    Store(String),
+    /// Prop, state, data or resource of a component
+    Member { component: String, name: String },
+}
+
+impl Workspace {
+    pub fn load(root: &Path) -> Self {
+        let mut files = BTreeMap::new();
+        collect_sources(root, root, &mut files);
+        let theme = theme::load_theme(&root.join("theme.toml").to_string_lossy()).ok();
+        Self { root: root.to_path_buf(), files, theme }
+    }
+
+    fn relative(&self, uri: &Uri) -> Option<String> {
+        let path = uri_to_path(uri)?;
+        Some(path.strip_prefix(&self.root).ok()?.to_string_lossy().replace('\\', "/"))
+    }
+
+    fn uri(&self, file: &str) -> Uri {
+        path_to_uri(&self.root.join(file))
+    }
+
+    fn set_buffer(&mut self, uri: &Uri, text: String) {
+        if let Some(file) = self.relative(uri).filter(|f| f.ends_with(".webc")) {
+            self.files.insert(file, text);
+        }
+    }
+
+    // A closed buffer falls back to the file on disk, if it is still there
+    fn reload(&mut self, uri: &Uri) {
+        if let Some(file) = self.relative(uri) {
+            match fs::read_to_string(self.root.join(&file)) {
+                Ok(text) => { self.files.insert(file, text); }
+                Err(_) => { self.files.remove(&file); }
+            }
+        }
+    }
+
+    fn analysis(&self) -> Analysis {
+        let mut document = WebCoreDocument {
+            app: None,
+            layouts: HashMap::new(),
+            pages: HashMap::new(),
+            components: HashMap::new(),
+            stores: HashMap::new(),
+            global_styles: Vec::new(),
+        };
+        let mut diagnostics = Vec::new();
+        for (file, text) in &self.files {
+            match parser::parse_webc_file(text, file) {
+                Ok(parsed) => checker::merge_definitions(&mut document, parsed, &mut diagnostics),
+                Err(e) => diagnostics.push(checker::parse_error(file, &e)),
+            }
+        }
+        diagnostics.extend(checker::check_document(&document));
+        diagnostics.extend(transition::check_directives(&document));
+        Analysis { document, diagnostics }
+    }
+
+    // What the build reports beyond the sources themselves: the a11y lint and
+    // the opt-in nesting checks, under webc.toml and with the data on disk
+    fn project_checks(&self, document: &WebCoreDocument) -> Vec<Diagnostic> {
+        let mut input = ProjectInput::new();
+        let Ok(toml) = fs::read(self.root.join("webc.toml")) else { return Vec::new() };
+        input.insert("webc.toml", toml);
+        let Ok(config) = project::read_config(&input) else { return Vec::new() };
+        let mut diagnostics = config.warnings.clone();
+        diagnostics.extend(a11y::lint(document, config.declared_lang.as_deref(), config.lang_span, &config.a11y));
+        if config.validate_html {
+            let input = ProjectInput::from_dir(&self.root).unwrap_or(input);
+            if let Ok((data, _)) = project::load_data(document, &input) {
+                diagnostics.extend(html::validate(document, &data));
+            }
+        }
+        diagnostics
+    }
+
+    fn range(&self, file: &str, span: Span) -> Range {
+        if span.line == 0 {
+            return Range::default();
+        }
+        // webc.toml is not an open buffer; its diagnostics are placed on the file on disk
+        let disk;
+        let text = match self.files.get(file) {
+            Some(text) => text.as_str(),
+            None => {
+                disk = fs::read_to_string(self.root.join(file)).unwrap_or_default();
+                disk.as_str()
+            }
+        };
+        Range { start: position_of(text, span.start), end: position_of(text, span.end.max(span.start)) }
+    }
+
+    fn location(&self, file: &str, span: Span) -> Location {
+        Location { uri: self.uri(file), range: self.range(file, span) }
+    }
+
+    // Resolves the cursor to a file, offset and the symbol there
+    fn resolve(&self, uri: &Uri, position: Position) -> Option<(Analysis, String, Symbol)> {
+        let file = self.relative(uri)?;
+        let text = self.files.get(&file)?;
+        let offset = offset_of(text, position);
+        let analysis = self.analysis();
+        let symbol = symbol_at(&analysis.document, &file, text, offset)?;
+        Some((analysis, file, symbol))
+    }
+
+    fn definition(&self, uri: &Uri, position: Position) -> Option<Location> {
+        let (analysis, _, symbol) = self.resolve(uri, position)?;
+        let document = &analysis.document;
+        match &symbol {
+            Symbol::Component(name) => document.components.get(name).map(|c| self.location(&c.file, c.span)),
+            Symbol::Layout(name) => document.layouts.get(name).map(|l| self.location(&l.file, l.span)),
+            Symbol::Store(name) => document.stores.get(name).map(|s| self.location(&s.file, s.span)),
+            Symbol::Member { component, name } => {
+                let component = document.components.get(component)?;
+                member_span(component, name).map(|span| self.location(&component.file, span))
+            }
+        }
+    }
+
+    fn hover(&self, uri: &Uri, position: Position) -> Option<Hover> {
+        let (analysis, _, symbol) = self.resolve(uri, position)?;
+        let document = &analysis.document;
+        let text = match &symbol {
+            Symbol::Component(name) => {
+                let component = document.components.get(name)?;
+                let mut text = format!("component {}", name);
+                for prop in &component.props {
+                    text.push_str(&format!("\n  prop {}: {}", prop.name, prop.type_.as_deref().unwrap_or("Any")));
+                }
+                for event in &component.events {
+                    text.push_str(&format!("\n  event {}{}", event.name, event.type_.as_ref().map(|t| format!(": {}", t)).unwrap_or_default()));
+                }
+                text
+            }
+            Symbol::Layout(name) => format!("layout {}", name),
+            Symbol::Store(name) => {
+                let store = document.stores.get(name)?;
+                let fields: Vec<String> = store.fields.iter().map(|f| format!("\n  {}: {}", f.name, f.type_)).collect();
+                format!("store {}{}", name, fields.concat())
+            }
+            Symbol::Member { component, name } => member_summary(document.components.get(component)?, name)?,
+        };
+        Some(Hover {
+            contents: HoverContents::Markup(MarkupContent { kind: MarkupKind::Markdown, value: format!("```webc\n{}\n```", text) }),
+            range: None,
+        })
+    }
+
+    fn completions(&self, uri: &Uri, position: Position) -> Vec<CompletionItem> {
+        let Some(file) = self.relative(uri) else { return Vec::new() };
+        let Some(text) = self.files.get(&file) else { return Vec::new() };
+        let offset = offset_of(text, position);
+        let analysis = self.analysis();
+        let document = &analysis.document;
+        let item = |label: &str, kind, detail: Option<String>| CompletionItem { label: label.to_string(), kind: Some(kind), detail, ..Default::default() };
+        let mut items = Vec::new();
+
+        // `color: |` in a style block: theme tokens
+        let line = &text[text[..offset].rfind('\n').map(|i| i + 1).unwrap_or(0)..offset];
+        if line.trim_start().split_once(':').is_some_and(|(name, _)| !name.contains(' ') && !name.is_empty()) {
+            if let Some(theme) = &self.theme {
+                for (group, values) in [("color", &theme.colors), ("font", &theme.fonts), ("radius", &theme.radius), ("breakpoint", &theme.breakpoints)] {
+                    let mut names: Vec<&String> = values.keys().collect();
+                    names.sort();
+                    for name in names {
+                        items.push(item(&format!("var(--{}-{})", group, name), CompletionItemKind::CONSTANT, Some(values[name].clone())));
+                    }
+                }
+            }
+            return items;
+        }
+
+        if let Some(Definition::Component(component)) = enclosing(document, &file, offset) {
+            for prop in &component.props {
+                items.push(item(&prop.name, CompletionItemKind::PROPERTY, prop.type_.clone()));
+            }
+            for var in &component.state {
+                items.push(item(&var.name, CompletionItemKind::VARIABLE, Some(var.type_.clone())));
+            }
+            for data in &component.data {
+                items.push(item(&data.name, CompletionItemKind::VARIABLE, Some(format!("data {}", data.path))));
+            }
+            for resource in &component.resources {
+                items.push(item(&resource.name, CompletionItemKind::VARIABLE, Some(format!("resource {}", resource.url))));
+            }
+            for import in &component.imports {
+                items.push(item(&import.name, CompletionItemKind::MODULE, Some("store".to_string())));
+            }
+        }
+        // Inside `{ ... }`, only names make sense
+        if in_expression(text, offset) {
+            return items;
+        }
+        // `Card |`: the props of the component the line starts with
+        if let Some(component) = line.split_whitespace().next().and_then(|tag| document.components.get(tag)) {
+            for prop in &component.props {
+                items.push(item(&format!("{}=", prop.name), CompletionItemKind::PROPERTY, prop.type_.clone()));
+            }
+        }
+        let mut components: Vec<&Component> = document.components.values().collect();
+        components.sort_by(|a, b| a.name.cmp(&b.name));
+        for component in components {
+            items.push(item(&component.name, CompletionItemKind::CLASS, Some("component".to_string())));
+        }
+        for tag in HTML_TAGS {
+            items.push(item(tag, CompletionItemKind::KEYWORD, None));
+        }
+        items
+    }
+
+    // `WorkspaceEdit` keys its changes by `Uri`
+    #[allow(clippy::mutable_key_type)]
+    fn rename(&self, uri: &Uri, position: Position, new_name: &str) -> Result<Option<WorkspaceEdit>, String> {
+        let Some((analysis, _, symbol)) = self.resolve(uri, position) else { return Ok(None) };
+        check_new_name(&analysis.document, &symbol, new_name)?;
+        let mut edits: HashMap<Uri, Vec<TextEdit>> = HashMap::new();
+        for (file, ranges) in self.occurrences(&analysis.document, &symbol) {
+            let text = &self.files[&file];
+            let file_edits = ranges.into_iter()
+                .map(|(start, end)| TextEdit { range: Range { start: position_of(text, start), end: position_of(text, end) }, new_text: new_name.to_string() })
+                .collect();
+            edits.insert(self.uri(&file), file_edits);
+        }
+        Ok(Some(WorkspaceEdit { changes: Some(edits), ..Default::default() }))
+    }
+
+    // Byte ranges of every reference to the symbol, by file
+    fn occurrences(&self, document: &WebCoreDocument, symbol: &Symbol) -> BTreeMap<String, Vec<(usize, usize)>> {
+        let mut found: BTreeMap<String, Vec<(usize, usize)>> = BTreeMap::new();
+        match symbol {
+            Symbol::Component(name) | Symbol::Layout(name) | Symbol::Store(name) => {
+                for (file, text) in &self.files {
+                    let ranges = names_in(text, 0, text.len(), name, &HashSet::new());
+                    if !ranges.is_empty() {
+                        found.insert(file.clone(), ranges);
+                    }
+                }
+            }
+            Symbol::Member { component, name } => {
+                let Some(definition) = document.components.get(component) else { return found };
+                let Some(text) = self.files.get(&definition.file) else { return found };
+                let (start, end) = definition_range(document, &definition.file, definition.span.start, text.len());
+                // Attribute names in the view belong to the element, not to this component
+                let mut attributes = HashSet::new();
+                for_each_attribute(&definition.view, &mut |_, attr| { attributes.insert(attr.span.start); });
+                found.entry(definition.file.clone()).or_default().extend(names_in(text, start, end, name, &attributes));
+                // `Card title="..."`: a renamed prop is renamed where it is passed
+                if definition.props.iter().any(|p| &p.name == name) {
+                    for (file, views) in views_by_file(document) {
+                        for view in views {
+                            for_each_attribute(view, &mut |element, attr| {
+                                if element == component && &attr.name == name {
+                                    found.entry(file.to_string()).or_default().push((attr.span.start, attr.span.start + name.len()));
+                                }
+                            });
+                        }
+                    }
+                }
+            }
+        }
+        for ranges in found.values_mut() {
+            ranges.sort();
+            ranges.dedup();
+        }
+        found
+    }
+}
+
+fn collect_sources(root: &Path, dir: &Path, files: &mut BTreeMap<String, String>) {
+    let Ok(entries) = fs::read_dir(dir) else { return };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            if !matches!(path.file_name().and_then(|n| n.to_str()), Some("dist" | "node_modules" | "target") | None) {
+                collect_sources(root, &path, files);
+            }
+        } else if path.extension().and_then(|e| e.to_str()) == Some("webc") {
+            if let (Ok(relative), Ok(text)) = (path.strip_prefix(root), fs::read_to_string(&path)) {
+                files.insert(relative.to_string_lossy().replace('\\', "/"), text);
+            }
+        }
+    }
+}
+
+enum Definition<'a> {
+    Component(&'a Component),
+    Other,
+}
+
+// Top-level definitions of a file by the offset of their name
+fn definitions_in<'a>(document: &'a WebCoreDocument, file: &str) -> Vec<(usize, Definition<'a>)> {
+    let mut starts: Vec<(usize, Definition)> = Vec::new();
+    starts.extend(document.components.values().filter(|c| c.file == file).map(|c| (c.span.start, Definition::Component(c))));
+    starts.extend(document.pages.values().filter(|p| p.file == file).map(|p| (p.span.start, Definition::Other)));
+    starts.extend(document.layouts.values().filter(|l| l.file == file).map(|l| (l.span.start, Definition::Other)));
+    starts.extend(document.stores.values().filter(|s| s.file == file).map(|s| (s.span.start, Definition::Other)));
+    starts.sort_by_key(|(start, _)| *start);
+    starts
+}
+
+fn enclosing<'a>(document: &'a WebCoreDocument, file: &str, offset: usize) -> Option<Definition<'a>> {
+    definitions_in(document, file).into_iter().rev().find(|(start, _)| *start <= offset).map(|(_, d)| d)
+}
+
+// From a definition's name to the next definition in the same file
+fn definition_range(document: &WebCoreDocument, file: &str, start: usize, len: usize) -> (usize, usize) {
+    let end = definitions_in(document, file).iter().map(|(s, _)| *s).find(|s| *s > start).unwrap_or(len);
+    (start, end)
+}
+
+// The new name has to parse as the same kind of name and not be taken already
+fn check_new_name(document: &WebCoreDocument, symbol: &Symbol, new_name: &str) -> Result<(), String> {
+    let tokens = parser::tokenize(new_name);
+    if !matches!(tokens.as_slice(), [Token::Identifier(name), Token::Eof] if name == new_name) {
+        return Err(format!("'{}' is not a valid name", new_name));
+    }
+    let taken = match symbol {
+        Symbol::Component(_) if !new_name.starts_with(|c: char| c.is_uppercase()) => {
+            return Err(format!("component '{}' would be read as an element, component names start with an uppercase letter", new_name));
+        }
+        Symbol::Component(name) => name != new_name && document.components.contains_key(new_name),
+        Symbol::Layout(name) => name != new_name && document.layouts.contains_key(new_name),
+        Symbol::Store(name) => name != new_name && document.stores.contains_key(new_name),
+        Symbol::Member { component, name } => name != new_name
+            && document.components.get(component).is_some_and(|c| member_span(c, new_name).is_some()),
+    };
+    if taken {
+        return Err(format!("'{}' is already defined", new_name));
+    }
+    Ok(())
+}
+
+fn member_span(component: &Component, name: &str) -> Option<Span> {
+    component.props.iter().find(|p| p.name == name).map(|p| p.span)
+        .or_else(|| component.state.iter().find(|s| s.name == name).map(|s| s.span))
+        .or_else(|| component.data.iter().find(|d| d.name == name).map(|d| d.span))
+        .or_else(|| component.resources.iter().find(|r| r.name == name).map(|r| r.span))
+}
+
+fn member_summary(component: &Component, name: &str) -> Option<String> {
+    if let Some(prop) = component.props.iter().find(|p| p.name == name) {
+        return Some(format!("prop {}: {}", prop.name, prop.type_.as_deref().unwrap_or("Any")));
+    }
+    if let Some(var) = component.state.iter().find(|s| s.name == name) {
+        let default = var.default_value.as_ref().map(|d| format!(" = {}", d)).unwrap_or_default();
+        return Some(format!("state {}: {}{}", var.name, var.type_, default));
+    }
+    if let Some(data) = component.data.iter().find(|d| d.name == name) {
+        return Some(format!("data {} from \"{}\"", data.name, data.path));
+    }
+    component.resources.iter().find(|r| r.name == name).map(|r| format!("resource {} = fetch(\"{}\")", r.name, r.url))
+}
+
+fn views_by_file(document: &WebCoreDocument) -> Vec<(&str, Vec<&[Element]>)> {
+    let mut views: BTreeMap<&str, Vec<&[Element]>> = BTreeMap::new();
+    for layout in document.layouts.values() {
+        views.entry(&layout.file).or_default().push(&layout.content);
+    }
+    for page in document.pages.values() {
+        views.entry(&page.file).or_default().push(&page.content);
+    }
+    for component in document.components.values() {
+        views.entry(&component.file).or_default().push(&component.view);
+    }
+    views.into_iter().collect()
+}
+
+// Calls `visit` with the element name and each of its attributes
+fn for_each_attribute(elements: &[Element], visit: &mut dyn FnMut(&str, &Attribute)) {
+    for element in elements {
+        match element {
+            Element::Tag { name, attributes, content, .. } | Element::Component { name, attributes, content, .. } => {
+                for attr in attributes {
+                    visit(name, attr);
+                }
+                for_each_attribute(content, visit);
+            }
+            Element::For { content, .. } => for_each_attribute(content, visit),
+            Element::If { content, else_content, .. } => {
+                for_each_attribute(content, visit);
+                for_each_attribute(else_content, visit);
+            }
+            Element::Text { .. } | Element::Slot { .. } | Element::Interpolation { .. } | Element::Raw(_) => {}
+        }
+    }
+}
+
+fn symbol_at(document: &WebCoreDocument, file: &str, text: &str, offset: usize) -> Option<Symbol> {
+    let (start, end) = word_at(text, offset)?;
+    let word = &text[start..end];
+    let tokens = parser::tokenize_with_offsets(text);
+    let index = tokens.iter().position(|(_, s, e)| *s <= start && end <= *e)?;
+    match &tokens[index].0 {
+        Token::Identifier(name) if name != word => return None,
+        Token::Identifier(_) => {}
+        // Inside a string only `{ interpolations }` hold names
+        Token::String(_) if in_braces(&text[tokens[index].1..start]) => {}
+        _ => return None,
+    }
+    if text[..start].ends_with('.') {
+        return None;
+    }
+    // `Card title="..."`: the attribute is a prop of the element's component
+    let mut attribute = None;
+    for (_, views) in views_by_file(document).into_iter().filter(|(f, _)| *f == file) {
+        for view in views {
+            for_each_attribute(view, &mut |element, attr| {
+                if attr.span.start == start && document.components.contains_key(element) {
+                    attribute = Some(Symbol::Member { component: element.to_string(), name: attr.name.clone() });
+                }
+            });
+        }
+    }
+    if attribute.is_some() {
+        return attribute;
+    }
+    if document.components.contains_key(word) {
+        return Some(Symbol::Component(word.to_string()));
+    }
+    if document.layouts.contains_key(word) {
+        return Some(Symbol::Layout(word.to_string()));
+    }
+    if document.stores.contains_key(word) {
+        return Some(Symbol::Store(word.to_string()));
+    }
+    match enclosing(document, file, offset)? {
+        Definition::Component(component) if member_span(component, word).is_some() => {
+            Some(Symbol::Member { component: component.name.clone(), name: word.to_string() })
+        }
+        _ => None,
+    }
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '$'
+}
+
+fn word_at(text: &str, offset: usize) -> Option<(usize, usize)> {
+    let offset = offset.min(text.len());
+    let start = text[..offset].char_indices().rev().take_while(|(_, c)| is_name_char(*c)).last().map(|(i, _)| i).unwrap_or(offset);
+    let end = text[offset..].char_indices().find(|(_, c)| !is_name_char(*c)).map(|(i, _)| offset + i).unwrap_or(text.len());
+    (start < end).then_some((start, end))
+}
+
+// Whether the end of `before` (text since the string opened) is inside `{ ... }`
+fn in_braces(before: &str) -> bool {
+    before.rfind('{').is_some_and(|open| !before[open..].contains('}'))
+}
+
+// Cursor inside an attribute expression or a string interpolation
+fn in_expression(text: &str, offset: usize) -> bool {
+    let tokens = parser::tokenize_with_offsets(text);
+    let mut depth: Vec<bool> = Vec::new();
+    for (i, (token, start, end)) in tokens.iter().enumerate() {
+        if *start >= offset {
+            break;
+        }
+        match token {
+            Token::LeftBrace => depth.push(i > 0 && matches!(tokens[i - 1].0, Token::Equals)),
+            Token::RightBrace => { depth.pop(); }
+            Token::String(_) if offset < *end => return in_braces(&text[*start..offset]),
+            _ => {}
+        }
+    }
+    depth.last() == Some(&true)
+}
+
+// Every `name` in text[start..end] read as a name: identifiers other than
+// property accesses and attribute names, and names inside string interpolations
+fn names_in(text: &str, start: usize, end: usize, name: &str, attributes: &HashSet<usize>) -> Vec<(usize, usize)> {
+    let mut ranges = Vec::new();
+    for (token, token_start, token_end) in parser::tokenize_with_offsets(text) {
+        if token_start < start || token_end > end {
+            continue;
+        }
+        match token {
+            Token::Identifier(ref ident)
+                if ident == name && !text[..token_start].ends_with('.') && !attributes.contains(&token_start) => {
+                ranges.push((token_start, token_end));
+            }
+            Token::String(_) => {
+                let body = &text[token_start..token_end];
+                let mut search = 0;
+                while let Some(found) = body[search..].find(name) {
+                    let at = search + found;
+                    search = at + name.len();
+                    let before = body[..at].chars().next_back();
+                    let after = body[at + name.len()..].chars().next();
+                    if before.is_some_and(|c| is_name_char(c) || c == '.') || after.is_some_and(is_name_char) {
+                        continue;
+                    }
+                    if in_braces(&body[..at]) {
+                        ranges.push((token_start + at, token_start + at + name.len()));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    ranges
+}
+
+// LSP positions count UTF-16 code units
+fn position_of(text: &str, offset: usize) -> Position {
+    let offset = offset.min(text.len());
+    let line_start = text[..offset].rfind('\n').map(|i| i + 1).unwrap_or(0);
+    let line = text[..offset].matches('\n').count() as u32;
+    Position { line, character: text[line_start..offset].encode_utf16().count() as u32 }
+}
+
+fn offset_of(text: &str, position: Position) -> usize {
+    let line_start = if position.line == 0 {
+        0
+    } else {
+        match text.match_indices('\n').nth(position.line as usize - 1) {
+            Some((i, _)) => i + 1,
+            None => return text.len(),
+        }
+    };
+    let mut units = 0;
+    for (i, c) in text[line_start..].char_indices() {
+        if units >= position.character as usize || c == '\n' {
+            return line_start + i;
+        }
+        units += c.len_utf16();
+    }
+    text.len()
+}
+
+fn uri_to_path(uri: &Uri) -> Option<PathBuf> {
+    let encoded = uri.as_str().strip_prefix("file://")?;
+    let bytes = encoded.as_bytes();
+    let mut decoded = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() + 1 {
+            if let Ok(byte) = u8::from_str_radix(encoded.get(i + 1..i + 3)?, 16) {
+                decoded.push(byte);
+                i += 3;
+                continue;
+            }
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8(decoded).ok().map(PathBuf::from)
+}
+
+fn path_to_uri(path: &Path) -> Uri {
+    let mut encoded = String::from("file://");
+    for byte in path.to_string_lossy().replace('\\', "/").bytes() {
+        if byte.is_ascii_alphanumeric() || b"/-_.~".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    Uri::from_str(&encoded).unwrap_or_else(|_| Uri::from_str("file:///").expect("valid uri"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn workspace() -> Workspace {
+        let mut files = BTreeMap::new();
+        files.insert("src/components/Badge.webc".to_string(),
+            "component Badge {\n  props { label: String }\n  view { span \"{label}!\" }\n}\n".to_string());
+        files.insert("src/pages/Home.webc".to_string(),
+            "component HomePage {\n  state { count: Number = 0 }\n  view {\n    Badge label=\"hi\"\n    button on:click={ count += 1 } \"{count} clicks\"\n  }\n}\n".to_string());
+        Workspace { root: PathBuf::from("/project"), files, theme: None }
+    }
+
+    #[test]
+    fn definitions_and_hover_resolve_names_across_files() {
+        let workspace = workspace();
+        let home = workspace.uri("src/pages/Home.webc");
+        assert_eq!(home.as_str(), "file:///project/src/pages/Home.webc");
+        let badge = workspace.definition(&home, Position { line: 3, character: 6 }).expect("component definition");
+        assert_eq!((badge.uri.as_str(), badge.range.start), ("file:///project/src/components/Badge.webc", Position { line: 0, character: 10 }));
+        let count = workspace.definition(&home, Position { line: 4, character: 24 }).expect("state definition");
+        assert_eq!(count.range.start, Position { line: 1, character: 10 });
+        let hover = workspace.hover(&home, Position { line: 3, character: 11 }).expect("prop hover");
+        assert!(matches!(hover.contents, HoverContents::Markup(ref m) if m.value.contains("prop label: String")));
+    }
+
+    #[test]
+    #[allow(clippy::mutable_key_type)]
+    fn rename_updates_declarations_uses_and_call_sites() {
+        let workspace = workspace();
+        let badge = workspace.uri("src/components/Badge.webc");
+        let edit = workspace.rename(&badge, Position { line: 1, character: 11 }, "text").expect("valid name").expect("rename");
+        let changes = edit.changes.expect("changes");
+        let lines = |file: &str| -> Vec<(u32, u32)> {
+            let mut lines: Vec<(u32, u32)> = changes[&workspace.uri(file)].iter().map(|e| (e.range.start.line, e.range.start.character)).collect();
+            lines.sort();
+            lines
+        };
+        assert_eq!(lines("src/components/Badge.webc"), vec![(1, 10), (2, 16)]);
+        assert_eq!(lines("src/pages/Home.webc"), vec![(3, 10)]);
+
+        let home = workspace.uri("src/pages/Home.webc");
+        let edit = workspace.rename(&home, Position { line: 1, character: 10 }, "total").expect("valid name").expect("rename state");
+        let changes = edit.changes.expect("changes");
+        let mut positions: Vec<(u32, u32)> = changes[&home].iter().map(|e| (e.range.start.line, e.range.start.character)).collect();
+        positions.sort();
+        assert_eq!(positions, vec![(1, 10), (4, 22), (4, 37)]);
+
+        let refused = |uri: &Uri, position: Position, name: &str| workspace.rename(uri, position, name).expect_err("refused");
+        assert_eq!(refused(&home, Position { line: 1, character: 10 }, "two words"), "'two words' is not a valid name");
+        assert_eq!(refused(&badge, Position { line: 0, character: 10 }, "HomePage"), "'HomePage' is already defined");
+        assert!(refused(&badge, Position { line: 0, character: 10 }, "badge").starts_with("component 'badge' would be read as an element"));
+    }
+
+    #[test]
+    fn project_checks_follow_webc_toml() {
+        let root = std::env::temp_dir().join(format!("webc-lsp-{}", std::process::id()));
+        fs::create_dir_all(&root).expect("temp dir");
+        fs::write(root.join("webc.toml"), "[html]\nvalidate = true\n\n[app]\nlang = \"\"\n").expect("webc.toml");
+        let mut workspace = workspace();
+        workspace.root = root.clone();
+        workspace.files.insert("src/pages/intro.webc".to_string(), "layout MainLayout { slot }\npage \"intro\" { p { div { \"x\" } } }\n".to_string());
+        let analysis = workspace.analysis();
+        let checks = workspace.project_checks(&analysis.document);
+        let lang = checks.iter().find(|d| d.message.starts_with("pages have no language")).expect("html-lang");
+        let range = workspace.range(&lang.file, lang.span);
+        fs::remove_dir_all(&root).ok();
+        assert_eq!((range.start, range.end), (Position { line: 4, character: 0 }, Position { line: 4, character: 9 }));
+        let messages: Vec<String> = checks.iter().map(|d| d.message.clone()).collect();
+        assert!(messages.contains(&"page 'intro' renders <div> inside <p>, the browser closes the paragraph before it".to_string()), "{:?}", messages);
+    }
+}
@@ -1,14 +1,10 @@
-mod parser;
-mod ast;
-pub mod codegen { pub mod codegen_html; pub mod codegen_css; pub mod codegen_js; }
-mod theme;
-mod css_processor;
+use webcore::{ast, checker, fmt, ir, lsp, parser, project};
+use webcore::{BuildOptions, BuildOutput, ProjectInput, Target};
 
 use std::env;
 use std::fs;
-use std::path::Path;
-use std::collections::HashMap;
-use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use std::collections::HashSet;
 use std::sync::{Arc, Mutex};
 use std::thread;
 use std::time::Duration;
@@ -24,18 +20,65 @@ fn main() {
     if args.len() < 2 {
         println!("Usage: webc <command> [options]");
         println!("Commands:");
-        println!("  build    Build the project");
+        println!("  build    Build the project (--target elements: components as Custom Elements)");
+        println!("  fmt      Format .webc files (--check: only report files that would change)");
+        println!("  ast      Print the syntax tree of a .webc file as JSON");
+        println!("  ir       Print what the build resolves for each page as JSON (ir [page])");
+        println!("  lsp      Run the language server over stdio");
         println!("  dev      Start development server (not implemented yet)");
         return;
     }
     
     match args[1].as_str() {
         "build" => {
-            if let Err(e) = build_project() {
+            // Args: build [--target elements]
+            let result = match args.iter().position(|a| a == "--target").and_then(|i| args.get(i + 1)).map(String::as_str) {
+                None | Some("pages") => build_project().map(|_| ()),
+                Some("elements") => build_elements(),
+                Some(other) => Err(format!("unknown build target '{}' (expected pages or elements)", other)),
+            };
+            if let Err(e) = result {
                 eprintln!("Build failed: {}", e);
                 std::process::exit(1);
             }
         }
+        "fmt" => {
+            // Args: fmt [--check] [paths...], defaults to src/
+            let check = args[2..].iter().any(|a| a == "--check");
+            let paths: Vec<PathBuf> = args[2..].iter().filter(|a| *a != "--check").map(PathBuf::from).collect();
+            match format_files(&paths, check) {
+                Ok(true) => {}
+                Ok(false) => std::process::exit(1),
+                Err(e) => {
+                    eprintln!("Format failed: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        "ast" => {
+            // Args: ast <file>
+            let result = match args.get(2) {
+                Some(file) => dump_ast(Path::new(file)),
+                None => Err("usage: webc ast <file.webc>".to_string()),
+            };
+            if let Err(e) = result {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        }
+        "ir" => {
+            // Args: ir [page]
+            if let Err(e) = dump_ir(args.get(2).map(String::as_str)) {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        }
+        "lsp" => {
+            if let Err(e) = lsp::run() {
+                eprintln!("Language server error: {}", e);
+                std::process::exit(1);
+            }
+        }
         "dev" => {
             let mut port: u16 = 3000;
             let mut host: Option<String> = None;
@@ -68,296 +111,174 @@ fn main() {
     }
 }
 
-fn build_project() -> Result<(), String> {
-    println!("🔨 Building WebCore project...");
-    
-    // Read project config
-    let config = read_config()?;
-    println!("📁 Project: {}", config.app_title);
-    
-    // Create dist directory
-    let dist_dir = Path::new("dist");
-    if dist_dir.exists() {
-        fs::remove_dir_all(dist_dir).map_err(|e| format!("Failed to clean dist: {}", e))?;
+// Rewrites (or with `check`, only lists) files not in canonical layout; false when
+// `check` found some
+fn format_files(paths: &[PathBuf], check: bool) -> Result<bool, String> {
+    let roots = if paths.is_empty() { vec![PathBuf::from("src")] } else { paths.to_vec() };
+    let mut files = Vec::new();
+    for root in &roots {
+        collect_webc_files(root, &mut files)?;
     }
-    fs::create_dir_all(dist_dir).map_err(|e| format!("Failed to create dist: {}", e))?;
-    
-    // Load theme
-    let theme = if Path::new("theme.toml").exists() {
-        println!("🎨 Loading theme...");
-        Some(theme::load_theme("theme.toml")?)
-    } else {
-        println!("⚠️  No theme.toml found, using default theme");
-        None
-    };
-    
-    // Load and parse all WebCore files
-    let mut document = ast::WebCoreDocument {
-        app: None,
-        layouts: HashMap::new(),
-        pages: HashMap::new(),
-        components: HashMap::new(),
-    };
-    
-    // Load app.webc first
-    let app_path = Path::new("src/app.webc");
-    if app_path.exists() {
-        let content = fs::read_to_string(app_path).map_err(|e| format!("Failed to read app.webc: {}", e))?;
-        let parsed = parser::parse_webc(&content).map_err(|e| format!("Parse error in app.webc: {:?}", e))?;
-        document.app = parsed.app;
-    }
-
-    // Load layouts
-    let layouts_dir = Path::new("src/layouts");
-    if layouts_dir.exists() {
-        for entry in fs::read_dir(layouts_dir).map_err(|e| format!("Failed to read layouts: {}", e))? {
-            let entry = entry.map_err(|e| format!("Failed to read entry: {}", e))?;
-            let path = entry.path();
-            if path.extension().and_then(|s| s.to_str()) == Some("webc") {
-                let content = fs::read_to_string(&path).map_err(|e| format!("Failed to read {:?}: {}", path, e))?;
-                let parsed = parser::parse_webc(&content).map_err(|e| format!("Parse error in {:?}: {:?}", path, e))?;
-                
-                // Merge layouts
-                for (name, layout) in parsed.layouts {
-                    document.layouts.insert(name, layout);
-                }
-            }
+    files.sort();
+    let mut changed = 0;
+    for file in &files {
+        let source = fs::read_to_string(file).map_err(|e| format!("Failed to read {:?}: {}", file, e))?;
+        let formatted = fmt::format_source(&source).map_err(|e| format!("{}: {}", file.display(), e))?;
+        if formatted == source {
+            continue;
         }
-    }
-    
-    // Load components
-    let components_dir = Path::new("src/components");
-    if components_dir.exists() {
-        for entry in fs::read_dir(components_dir).map_err(|e| format!("Failed to read components: {}", e))? {
-            let entry = entry.map_err(|e| format!("Failed to read entry: {}", e))?;
-            let path = entry.path();
-            if path.extension().and_then(|s| s.to_str()) == Some("webc") {
-                let content = fs::read_to_string(&path).map_err(|e| format!("Failed to read {:?}: {}", path, e))?;
-                let parsed = parser::parse_webc(&content).map_err(|e| format!("Parse error in {:?}: {:?}", path, e))?;
-                
-                // Merge components
-                for (name, component) in parsed.components {
-                    document.components.insert(name, component);
-                }
-            }
+        changed += 1;
+        if check {
+            println!("would reformat {}", file.display());
+        } else {
+            fs::write(file, formatted).map_err(|e| format!("Failed to write {:?}: {}", file, e))?;
+            println!("formatted {}", file.display());
         }
     }
+    if check && changed > 0 {
+        println!("{} of {} file(s) would be reformatted", changed, files.len());
+        return Ok(false);
+    }
+    if !check {
+        println!("✅ {} file(s) formatted, {} unchanged", changed, files.len() - changed);
+    }
+    Ok(true)
+}
 
-    // Load pages
-    let pages_dir = Path::new("src/pages");
-    if pages_dir.exists() {
-        for entry in fs::read_dir(pages_dir).map_err(|e| format!("Failed to read pages: {}", e))? {
+fn collect_webc_files(path: &Path, out: &mut Vec<PathBuf>) -> Result<(), String> {
+    if path.is_dir() {
+        for entry in fs::read_dir(path).map_err(|e| format!("Failed to read {:?}: {}", path, e))? {
             let entry = entry.map_err(|e| format!("Failed to read entry: {}", e))?;
-            let path = entry.path();
-            if path.extension().and_then(|s| s.to_str()) == Some("webc") {
-                let content = fs::read_to_string(&path).map_err(|e| format!("Failed to read {:?}: {}", path, e))?;
-                let parsed = parser::parse_webc(&content).map_err(|e| format!("Parse error in {:?}: {:?}", path, e))?;
-                
-                // Merge pages and components
-                for (name, page) in parsed.pages {
-                    document.pages.insert(name, page);
-                }
-                for (name, component) in parsed.components {
-                    document.components.insert(name, component);
-                }
-            }
+            collect_webc_files(&entry.path(), out)?;
         }
+    } else if path.extension().and_then(|s| s.to_str()) == Some("webc") {
+        out.push(path.to_path_buf());
+    } else if !path.exists() {
+        return Err(format!("{:?} not found", path));
     }
-    
-    // Collect all handlers from all pages
-    let mut all_handlers = Vec::new();
-    
-    // Generate HTML for each page
-    for (page_name, _) in &document.pages {
-        println!("📄 Generating: {}.html", page_name);
-        let options = codegen::codegen_html::HtmlPageOptions {
-            lang: config.app_lang.clone(),
-            title: config.app_title.clone(),
-        };
-        let html_result = codegen::codegen_html::generate_html(&document, page_name, &options)?;
-        all_handlers.extend(html_result.handlers);
-        let output_path = dist_dir.join(format!("{}.html", page_name));
-        fs::write(&output_path, html_result.html).map_err(|e| format!("Failed to write {:?}: {}", output_path, e))?;
+    Ok(())
+}
+
+// Inputs outside `src/` that the build read; `webc dev` watches them too
+struct BuildOutcome {
+    data_files: Vec<PathBuf>,
+}
+
+// Compiles the project in the current directory; diagnostics are printed and
+// errors fail the build before anything is written
+fn compile_project(options: &BuildOptions) -> Result<BuildOutput, String> {
+    let input = ProjectInput::from_dir(Path::new("."))?;
+    let output = webcore::compile(&input, options);
+    for diagnostic in &output.diagnostics {
+        println!("{}", diagnostic);
     }
-    
-    // Generate HTML for each component that looks like a page
-    println!("🔍 Found {} components", document.components.len());
-    for (component_name, component) in &document.components {
-        println!("🔍 Component: {} (ends with Page: {})", component_name, component_name.ends_with("Page"));
-        if component_name.ends_with("Page") {
-            println!("📄 Generating: {}.html", component_name);
-            // Create a temporary page from the component
-            let temp_page = ast::Page {
-                name: component_name.clone(),
-                content: component.view.clone(),
-            };
-            let mut temp_doc = document.clone();
-            temp_doc.pages.insert(component_name.clone(), temp_page);
-            
-            let options = codegen::codegen_html::HtmlPageOptions {
-                lang: config.app_lang.clone(),
-                title: config.app_title.clone(),
-            };
-            let html_result = codegen::codegen_html::generate_html(&temp_doc, component_name, &options)?;
-            all_handlers.extend(html_result.handlers);
-            let output_path = dist_dir.join(format!("{}.html", component_name));
-            fs::write(&output_path, html_result.html).map_err(|e| format!("Failed to write {:?}: {}", output_path, e))?;
+    if output.has_errors() {
+        let count = output.diagnostics.iter().filter(|d| d.severity == checker::Severity::Error).count();
+        return Err(format!("{} error(s) found", count));
+    }
+    Ok(output)
+}
+
+// Replaces `clean` (dist/ or the part of it the target owns) with the output,
+// whose paths are relative to dist/
+fn write_output(clean: &Path, output: &BuildOutput) -> Result<(), String> {
+    if clean.exists() {
+        fs::remove_dir_all(clean).map_err(|e| format!("Failed to clean {:?}: {}", clean, e))?;
+    }
+    fs::create_dir_all(clean).map_err(|e| format!("Failed to create {:?}: {}", clean, e))?;
+    for (file, contents) in &output.files {
+        let path = Path::new("dist").join(file);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| format!("Failed to create {:?}: {}", parent, e))?;
         }
+        fs::write(&path, contents).map_err(|e| format!("Failed to write {:?}: {}", path, e))?;
     }
-    
-    // If no pages were generated, create a default index.html
-    if document.pages.is_empty() && !document.components.iter().any(|(name, _)| name.ends_with("Page")) {
-        println!("📄 Generating: index.html (default)");
-        let default_page = ast::Page {
-            name: "index".to_string(),
-            content: vec![
-                ast::Element::Tag {
-                    name: "h1".to_string(),
-                    attributes: vec![],
-                    content: vec![ast::Element::Text("Welcome to WebCore".to_string())],
-                },
-                ast::Element::Tag {
-                    name: "p".to_string(),
-                    attributes: vec![],
-                    content: vec![ast::Element::Text("This is a default page.".to_string())],
-                },
-            ],
-        };
-        let mut temp_doc = document.clone();
-        temp_doc.pages.insert("index".to_string(), default_page);
-        
-        let options = codegen::codegen_html::HtmlPageOptions {
-            lang: config.app_lang.clone(),
-            title: config.app_title.clone(),
-        };
-        let html_result = codegen::codegen_html::generate_html(&temp_doc, "index", &options)?;
-        all_handlers.extend(html_result.handlers);
-        let output_path = dist_dir.join("index.html");
-        fs::write(&output_path, html_result.html).map_err(|e| format!("Failed to write {:?}: {}", output_path, e))?;
+    Ok(())
+}
+
+fn build_project() -> Result<BuildOutcome, String> {
+    println!("🔨 Building WebCore project...");
+    let output = compile_project(&BuildOptions::default())?;
+    write_output(Path::new("dist"), &output)?;
+    for file in output.files.keys().filter(|f| f.ends_with(".html")) {
+        println!("📄 {}", file);
     }
-    
-    // Generate theme files
-    if let Some(theme) = &theme {
-        println!("🎨 Generating theme files...");
-        
-        // Generate CSS variables using codegen_css
-        let css_variables = codegen::codegen_css::generate_theme_css(theme);
-        
-        // Post-process CSS with LightningCSS
-        let processed_css = if config.mode == "prod" {
-            println!("🔧 Minifying CSS with LightningCSS...");
-            css_processor::minify_css(&css_variables)?
-        } else {
-            css_processor::format_css(&css_variables)?
-        };
-        
-        let css_path = dist_dir.join("theme.css");
-        fs::write(&css_path, processed_css).map_err(|e| format!("Failed to write theme.css: {}", e))?;
-        
-        // Generate WebCore runtime JS with compiled handlers
-        let mut runtime_js = codegen::codegen_js::generate_runtime_js(&all_handlers);
-        
-        // Add component-specific JavaScript
-        for component in document.components.values() {
-            if !component.state.is_empty() {
-                runtime_js.push_str(&format!("\n// Component: {}\n", component.name));
-                for state_var in &component.state {
-                    runtime_js.push_str(&format!(
-                        "window.__webcore_state__.set('{}', {});\n",
-                        state_var.name,
-                        state_var.default_value.as_ref().unwrap_or(&"null".to_string())
-                    ));
-                }
+
+    // Raw escape hatches bypass escaping; list every use so they stay visible
+    if !output.raw_blocks.is_empty() {
+        println!("⚠️  Raw content report ({} block(s) passed through unescaped):", output.raw_blocks.len());
+        for (location, block) in &output.raw_blocks {
+            match &block.src {
+                Some(src) => println!("   - {}: {} src=\"{}\"", location, block.kind.label(), src),
+                None => println!("   - {}: {} ({} bytes)", location, block.kind.label(), block.content.len()),
             }
         }
-        
-        let js_path = dist_dir.join("webcore.js");
-        fs::write(&js_path, runtime_js).map_err(|e| format!("Failed to write webcore.js: {}", e))?;
-    }
-    
-    // Copy public assets
-    let public_dir = Path::new("public");
-    if public_dir.exists() {
-        println!("📁 Copying public assets...");
-        copy_dir_recursive(public_dir, dist_dir)?;
     }
 
-    // Generate index.html with links to pages/components
-    generate_index_html(&document)?;
-    
     println!("✅ Build completed successfully!");
-    Ok(())
+    Ok(BuildOutcome { data_files: output.data_files.iter().map(PathBuf::from).collect() })
 }
 
-fn read_config() -> Result<Config, String> {
-    let config_path = Path::new("webc.toml");
-    if !config_path.exists() {
-        return Err("webc.toml not found".to_string());
+// `webc build --target elements`: one ES module per component in dist/elements/
+fn build_elements() -> Result<(), String> {
+    println!("🔨 Building WebCore components as Custom Elements...");
+    let output = compile_project(&BuildOptions { target: Target::Elements, ..Default::default() })?;
+    write_output(Path::new("dist/elements"), &output)?;
+    let modules: Vec<&String> = output.files.keys().filter(|f| f.as_str() != "elements/index.js").collect();
+    for file in &modules {
+        println!("   - {}", file);
     }
-    
-    let content = fs::read_to_string(config_path).map_err(|e| format!("Failed to read webc.toml: {}", e))?;
-    
-    let parsed: WebcToml = toml::from_str(&content).map_err(|e| format!("Failed to parse webc.toml: {}", e))?;
-    let app_title = parsed.app.as_ref().and_then(|a| a.title.clone()).unwrap_or_else(|| "WebCore App".to_string());
-    let app_lang = parsed.app.as_ref().and_then(|a| a.lang.clone()).unwrap_or_else(|| "fr".to_string());
-    let mode = parsed.app.as_ref().and_then(|a| a.mode.clone()).unwrap_or_else(|| "dev".to_string());
-    
-    Ok(Config { 
-        app_title,
-        app_lang,
-        mode,
-    })
-}
-
-#[derive(Debug)]
-struct Config {
-    app_title: String,
-    app_lang: String,
-    mode: String,
+    println!("✅ Built {} custom element(s)", modules.len());
+    Ok(())
 }
 
-#[derive(Debug, Deserialize)]
-struct WebcToml {
-    app: Option<AppSection>,
+// `webc ast <file>`: the parsed file, before it is merged into the project
+fn dump_ast(path: &Path) -> Result<(), String> {
+    let content = fs::read_to_string(path).map_err(|e| format!("Failed to read {:?}: {}", path, e))?;
+    let document = parser::parse_webc_file(&content, &path.to_string_lossy())
+        .map_err(|e| format!("Parse error in {:?}: {}", path, e))?;
+    let json = serde_json::to_string_pretty(&document).map_err(|e| e.to_string())?;
+    println!("{}", json);
+    Ok(())
 }
 
-#[derive(Debug, Deserialize)]
-struct AppSection {
-    title: Option<String>,
-    lang: Option<String>,
-    mode: Option<String>,
-}
+// `webc ir [page]`: the lowered pages and the scoped component styles the
+// generators print. Diagnostics go to stderr so stdout stays valid JSON
+fn dump_ir(only: Option<&str>) -> Result<(), String> {
+    let input = ProjectInput::from_dir(Path::new("."))?;
+    let mut diagnostics = Vec::new();
+    let loaded = project::load(&input, &BuildOptions::default(), &mut diagnostics);
+    for diagnostic in &diagnostics {
+        eprintln!("{}", diagnostic);
+    }
+    let project::Project { mut document, data, theme, .. } = loaded?;
 
-fn generate_index_html(document: &ast::WebCoreDocument) -> Result<(), String> {
-    let mut links: Vec<(String, String)> = Vec::new();
-    for page_name in document.pages.keys() {
-        links.push((format!("{}.html", page_name), page_name.clone()));
+    let component_pages: Vec<ast::Page> = document.components.values()
+        .filter(|c| c.name.ends_with("Page"))
+        .map(project::component_page)
+        .collect();
+    for page in component_pages {
+        document.pages.insert(page.name.clone(), page);
     }
-    for (component_name, _) in &document.components {
-        if component_name.ends_with("Page") {
-            links.push((format!("{}.html", component_name), component_name.clone()));
-        }
+    let mut names: Vec<&String> = document.pages.keys().filter(|name| only.is_none_or(|o| o == name.as_str())).collect();
+    names.sort();
+    if names.is_empty() {
+        return Err(format!("Page '{}' not found", only.unwrap_or_default()));
     }
-    links.sort_by(|a, b| a.1.cmp(&b.1));
 
-    let mut html = String::new();
-    html.push_str("<!DOCTYPE html>\n<html lang=\"fr\">\n<head>\n");
-    html.push_str("  <meta charset=\"UTF-8\">\n");
-    html.push_str("  <meta name=\"viewport\" content=\"width=device-width, initial-scale=1.0\">\n");
-    html.push_str("  <title>Index</title>\n");
-    html.push_str("  <link rel=\"stylesheet\" href=\"theme.css\">\n");
-    html.push_str("</head>\n<body>\n<h1>Pages</h1>\n<ul>\n");
-    for (href, label) in links {
-        html.push_str(&format!("  <li><a href=\"{}\">{}</a></li>\n", href, label));
+    let mut pages = Vec::new();
+    for name in names {
+        pages.push(ir::lower_page(&document, name, &data, None)?);
     }
-    html.push_str("</ul>\n<script src=\"webcore.js\"></script>\n</body>\n</html>\n");
-
-    fs::write("dist/index.html", html).map_err(|e| format!("Failed to write index.html: {}", e))
+    let pages = serde_json::json!({ "pages": pages, "components": ir::lower_styles(&document, theme.as_ref()) });
+    let json = serde_json::to_string_pretty(&pages).map_err(|e| e.to_string())?;
+    println!("{}", json);
+    Ok(())
 }
 
 fn dev_server_with_options(port: u16, host: Option<String>, auto_open: bool) -> Result<(), String> {
     // initial build
-    build_project()?;
+    let outcome = build_project()?;
 
     // start file watcher
     let rebuild_flag = Arc::new(Mutex::new(false));
@@ -378,6 +299,11 @@ fn dev_server_with_options(port: u16, host: Option<String>, auto_open: bool) ->
     if Path::new("theme.toml").exists() { watcher.watch(Path::new("theme.toml"), RecursiveMode::NonRecursive).map_err(|e| format!("watch error: {}", e))?; }
     watcher.watch(Path::new("webc.toml"), RecursiveMode::NonRecursive).map_err(|e| format!("watch error: {}", e))?;
 
+    // data files can live anywhere in the project; the set may grow on rebuild
+    let watcher = Arc::new(Mutex::new(watcher));
+    let mut watched_data: HashSet<PathBuf> = HashSet::new();
+    watch_data_files(&watcher, &mut watched_data, &outcome.data_files);
+
     // start server with port auto-increment if in use
     let (server, bound_port) = bind_server_with_fallback(port, 50)?;
     let local_host = match host.as_deref() {
@@ -428,7 +354,10 @@ fn dev_server_with_options(port: u16, host: Option<String>, auto_open: bool) ->
             }
             if do_rebuild {
                 println!("♻️  Rebuilding...");
-                if let Err(e) = build_project() { eprintln!("Rebuild failed: {}", e); }
+                match build_project() {
+                    Ok(outcome) => watch_data_files(&watcher, &mut watched_data, &outcome.data_files),
+                    Err(e) => eprintln!("Rebuild failed: {}", e),
+                }
             }
         }
     });
@@ -443,17 +372,51 @@ fn dev_server_with_options(port: u16, host: Option<String>, auto_open: bool) ->
     Ok(())
 }
 
+fn watch_data_files(watcher: &Arc<Mutex<RecommendedWatcher>>, watched: &mut HashSet<PathBuf>, files: &[PathBuf]) {
+    let Ok(mut watcher) = watcher.lock() else { return };
+    for file in files {
+        // src/ is already watched recursively
+        if file.starts_with("src") || watched.contains(file) || !file.exists() {
+            continue;
+        }
+        match watcher.watch(file, RecursiveMode::NonRecursive) {
+            Ok(()) => { watched.insert(file.clone()); }
+            Err(e) => eprintln!("watch error for {:?}: {}", file, e),
+        }
+    }
+}
+
+// Mock API for resources during development: `/api/users` is answered with
+// `mocks/api/users.json` (or `mocks/api/users/index.json`)
+fn mock_file(url_path: &str) -> Option<PathBuf> {
+    let trimmed = url_path.trim_end_matches('/');
+    if trimmed.is_empty() || trimmed.contains("..") {
+        return None;
+    }
+    [format!("mocks{}.json", trimmed), format!("mocks{}/index.json", trimmed)]
+        .into_iter()
+        .map(PathBuf::from)
+        .find(|p| p.is_file())
+}
+
 fn handle_request(request: Request) -> Result<(), String> {
-    let url = request.url();
+    let url = request.url().split(['?', '#']).next().unwrap_or("/").to_string();
     let path = if url == "/" { "dist/index.html".to_string() } else { format!("dist{}", url) };
     let path = Path::new(&path);
-    let file_path = if path.is_dir() { path.join("index.html") } else { path.to_path_buf() };
+    let file_path = if path.is_dir() {
+        path.join("index.html")
+    } else if !path.exists() {
+        mock_file(&url).unwrap_or_else(|| path.to_path_buf())
+    } else {
+        path.to_path_buf()
+    };
     match fs::read(&file_path) {
         Ok(bytes) => {
             let content_type = match file_path.extension().and_then(|e| e.to_str()).unwrap_or("") {
                 "html" => "text/html; charset=utf-8",
                 "css" => "text/css; charset=utf-8",
                 "js" => "application/javascript; charset=utf-8",
+                "json" => "application/json; charset=utf-8",
                 "png" => "image/png",
                 "jpg" | "jpeg" => "image/jpeg",
                 _ => "application/octet-stream",
@@ -461,10 +424,19 @@ fn handle_request(request: Request) -> Result<(), String> {
             let response = Response::from_data(bytes).with_header(tiny_http::Header::from_bytes(&b"Content-Type"[..], content_type.as_bytes()).unwrap());
             request.respond(response).map_err(|e| format!("respond error: {}", e))
         }
-        Err(_) => {
-            let response = Response::from_string("Not Found").with_status_code(404);
-            request.respond(response).map_err(|e| format!("respond error: {}", e))
-        }
+        // The site's own 404 page when it has one, like static hosts serve it
+        Err(_) => match fs::read("dist/404.html") {
+            Ok(bytes) => {
+                let response = Response::from_data(bytes)
+                    .with_status_code(404)
+                    .with_header(tiny_http::Header::from_bytes(&b"Content-Type"[..], &b"text/html; charset=utf-8"[..]).unwrap());
+                request.respond(response).map_err(|e| format!("respond error: {}", e))
+            }
+            Err(_) => {
+                let response = Response::from_string("Not Found").with_status_code(404);
+                request.respond(response).map_err(|e| format!("respond error: {}", e))
+            }
+        },
     }
 }
 
@@ -506,18 +478,3 @@ fn get_primary_ipv4() -> Option<String> {
     }
     None
 }
-
-fn copy_dir_recursive(src: &Path, dst: &Path) -> Result<(), String> {
-    if src.is_dir() {
-        fs::create_dir_all(dst).map_err(|e| format!("Failed to create dir {:?}: {}", dst, e))?;
-        for entry in fs::read_dir(src).map_err(|e| format!("Failed to read dir {:?}: {}", src, e))? {
-            let entry = entry.map_err(|e| format!("Failed to read entry: {}", e))?;
-            let src_path = entry.path();
-            let dst_path = dst.join(entry.file_name());
-            copy_dir_recursive(&src_path, &dst_path)?;
-        }
-    } else {
-        fs::copy(src, dst).map_err(|e| format!("Failed to copy {:?} to {:?}: {}", src, dst, e))?;
-    }
-    Ok(())
-}
@@ -0,0 +1,47 @@
+//! Event modifiers: `on:submit.prevent`, `on:click.once`, `on:keydown.ctrl.s`
+//!
+//! Modifiers stay part of the attribute name; the handler is registered with
+//! them and the runtime applies them before calling it. Key modifiers name
+//! `event.key`, system modifiers require Ctrl/Shift/Alt/Meta to be held.
+
+pub const EVENT_MODIFIERS: &[&str] = &["prevent", "stop", "once", "passive"];
+pub const SYSTEM_MODIFIERS: &[&str] = &["ctrl", "shift", "alt", "meta"];
+pub const KEY_MODIFIERS: &[&str] = &["enter", "esc", "escape", "space", "tab", "delete", "up", "down", "left", "right"];
+const KEYBOARD_EVENTS: &[&str] = &["keydown", "keyup", "keypress"];
+
+/// Checks the modifiers of an `on:<event>` attribute
+pub fn validate(event: &str, modifiers: &[&str]) -> Result<(), String> {
+    for modifier in modifiers {
+        let is_key = KEY_MODIFIERS.contains(modifier) || is_single_key(modifier);
+        if !is_key && !EVENT_MODIFIERS.contains(modifier) && !SYSTEM_MODIFIERS.contains(modifier) {
+            return Err(format!("unknown event modifier '.{}' (expected {}, {} or a key name)",
+                modifier, EVENT_MODIFIERS.join(", "), SYSTEM_MODIFIERS.join(", ")));
+        }
+        if is_key && !KEYBOARD_EVENTS.contains(&event) {
+            return Err(format!("key modifier '.{}' only applies to keyboard events, not '{}'", modifier, event));
+        }
+    }
+    if modifiers.contains(&"passive") && modifiers.contains(&"prevent") {
+        return Err(format!("on:{} cannot be both .passive and .prevent, a passive listener cannot cancel the event", event));
+    }
+    Ok(())
+}
+
+// `.s`, `.k`, `.1`: a single character compared with `event.key`
+fn is_single_key(modifier: &str) -> bool {
+    modifier.len() == 1 && modifier.chars().all(|c| c.is_ascii_alphanumeric())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn modifiers_are_validated_against_the_event() {
+        assert!(validate("keydown", &["ctrl", "s", "prevent"]).is_ok());
+        assert!(validate("scroll", &["passive"]).is_ok());
+        assert!(validate("click", &["prevnt"]).expect_err("typo").starts_with("unknown event modifier '.prevnt'"));
+        assert!(validate("click", &["enter"]).expect_err("not a key event").contains("only applies to keyboard events"));
+        assert!(validate("wheel", &["passive", "prevent"]).is_err());
+    }
+}
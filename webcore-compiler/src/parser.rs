@@ -5,7 +5,80 @@ use std::collections::HashMap;
 
 pub fn parse_webc(source: &str) -> Result<WebCoreDocument, ParseError> {
     let mut parser = Parser::new(source);
-    parser.parse_document()
+    let document = parser.parse_document();
+    document.map_err(|e| parser.located(e))
+}
+
+/// Parse a file from the project, recording its path on every definition
+pub fn parse_webc_file(source: &str, file: &str) -> Result<WebCoreDocument, ParseError> {
+    let mut document = parse_webc(source)?;
+    if let Some(app) = document.app.as_mut() {
+        app.file = file.to_string();
+    }
+    for layout in document.layouts.values_mut() {
+        layout.file = file.to_string();
+    }
+    for page in document.pages.values_mut() {
+        page.file = file.to_string();
+    }
+    for component in document.components.values_mut() {
+        component.file = file.to_string();
+    }
+    for store in document.stores.values_mut() {
+        store.file = file.to_string();
+    }
+    Ok(document)
+}
+
+/// Token stream of a file, comments and whitespace dropped
+pub fn tokenize(source: &str) -> Vec<Token> {
+    Parser::tokenize(source).0
+}
+
+/// Tokens with the byte range each one covers, for editor features
+pub fn tokenize_with_offsets(source: &str) -> Vec<(Token, usize, usize)> {
+    let (tokens, offsets, ends, _) = Parser::tokenize(source);
+    tokens.into_iter().zip(offsets).zip(ends).map(|((token, start), end)| (token, start, end)).collect()
+}
+
+/// What a statement of the file declares, for the formatter
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Statement {
+    /// Top-level `app`, `layout`, `page`, `component`, `store` or `style global`
+    Definition,
+    /// A view element: tag, component, text, `for`, `if`, `slot`...
+    Element,
+    /// Anything else: sections, fields, props, routes, style rules
+    Declaration,
+}
+
+/// A parsed file as the formatter needs it: every token with its byte range,
+/// the `//` comments between them and where each statement starts
+#[derive(Debug)]
+pub struct Syntax {
+    pub tokens: Vec<(Token, usize, usize)>,
+    /// Byte ranges of the comments
+    pub comments: Vec<(usize, usize)>,
+    /// Index of the first token of each statement
+    pub statements: HashMap<usize, Statement>,
+    /// Blocks whose content is kept verbatim (CSS): the `{` token, the last
+    /// token inside and the byte offset of the closing `}`
+    pub verbatim: Vec<(usize, usize, usize)>,
+}
+
+/// Parse `source`, keeping what `parse_webc` drops
+pub fn parse_syntax(source: &str) -> Result<Syntax, ParseError> {
+    let mut parser = Parser::new(source);
+    if let Err(e) = parser.parse_document() {
+        return Err(parser.located(e));
+    }
+    let (_, _, _, comments) = Parser::tokenize(source);
+    Ok(Syntax {
+        tokens: tokenize_with_offsets(source),
+        comments,
+        statements: parser.statements,
+        verbatim: parser.verbatim,
+    })
 }
 
 #[derive(Debug)]
@@ -13,15 +86,45 @@ pub enum ParseError {
     UnexpectedToken(String),
     ExpectedToken(String),
     InvalidSyntax(String),
+    /// A name defined twice in the same file
+    Duplicate { kind: &'static str, name: String, span: Span, first: Span },
+    /// An error at the token the parser stopped on
+    At { span: Span, error: Box<ParseError> },
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseError::UnexpectedToken(t) => write!(f, "unexpected token {}", t),
+            ParseError::ExpectedToken(t) => write!(f, "expected {}", t),
+            ParseError::InvalidSyntax(msg) => write!(f, "invalid syntax: {}", msg),
+            ParseError::Duplicate { kind, name, first, .. } => write!(f, "{} '{}' is already defined at line {}", kind, name, first.line),
+            ParseError::At { error, .. } => error.fmt(f),
+        }
+    }
+}
+
+// A second definition would silently replace the first one
+fn unique(kind: &'static str, name: &str, span: Span, first: Option<Span>) -> Result<(), ParseError> {
+    match first {
+        Some(first) => Err(ParseError::Duplicate { kind, name: name.to_string(), span, first }),
+        None => Ok(()),
+    }
 }
 
 pub struct Parser {
     source: String,
     pos: usize,
     tokens: Vec<Token>,
+    // Byte range in `source` covered by each token
+    offsets: Vec<usize>,
+    ends: Vec<usize>,
+    line_starts: Vec<usize>,
+    statements: HashMap<usize, Statement>,
+    verbatim: Vec<(usize, usize, usize)>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Token {
     Identifier(String),
     String(String),
@@ -30,6 +133,8 @@ pub enum Token {
     RightBrace,
     LeftParen,
     RightParen,
+    LeftBracket,
+    RightBracket,
     Colon,
     Equals,
     Comma,
@@ -37,82 +142,163 @@ pub enum Token {
     Arrow, // =>
     Plus,
     Minus,
+    Bang,
     Eof,
 }
 
 impl Parser {
     pub fn new(source: &str) -> Self {
-        let tokens = Self::tokenize(source);
+        let (tokens, offsets, ends, _) = Self::tokenize(source);
+        let line_starts = std::iter::once(0)
+            .chain(source.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
         Self {
             source: source.to_string(),
             pos: 0,
             tokens,
+            offsets,
+            ends,
+            line_starts,
+            statements: HashMap::new(),
+            verbatim: Vec::new(),
         }
     }
 
-    fn tokenize(source: &str) -> Vec<Token> {
+    #[allow(clippy::type_complexity)]
+    fn tokenize(source: &str) -> (Vec<Token>, Vec<usize>, Vec<usize>, Vec<(usize, usize)>) {
         let mut tokens = Vec::new();
-        let mut chars = source.chars().peekable();
+        let mut offsets = Vec::new();
+        let mut ends = Vec::new();
+        let mut comments = Vec::new();
+        let mut chars = source.char_indices().peekable();
         
-        while let Some(ch) = chars.next() {
-            match ch {
-                '{' => tokens.push(Token::LeftBrace),
-                '}' => tokens.push(Token::RightBrace),
-                '(' => tokens.push(Token::LeftParen),
-                ')' => tokens.push(Token::RightParen),
-                ':' => tokens.push(Token::Colon),
+        while let Some((start, ch)) = chars.next() {
+            let token = match ch {
+                '{' => Token::LeftBrace,
+                '}' => Token::RightBrace,
+                '(' => Token::LeftParen,
+                ')' => Token::RightParen,
+                '[' => Token::LeftBracket,
+                ']' => Token::RightBracket,
+                ':' => Token::Colon,
                 '=' => {
-                    if chars.peek() == Some(&'>') {
+                    if chars.peek().map(|&(_, c)| c) == Some('>') {
                         chars.next();
-                        tokens.push(Token::Arrow);
+                        Token::Arrow
                     } else {
-                        tokens.push(Token::Equals);
+                        Token::Equals
+                    }
+                }
+                ',' => Token::Comma,
+                '.' => Token::Dot,
+                '+' => Token::Plus,
+                '-' => Token::Minus,
+                '!' => Token::Bang,
+                // `// comment` runs to the end of the line; it starts a line or
+                // follows a space, so `url(http://...)` stays a value
+                '/' if chars.peek().map(|&(_, c)| c) == Some('/')
+                    && source[..start].chars().next_back().is_none_or(char::is_whitespace) =>
+                {
+                    while chars.peek().is_some_and(|&(_, c)| c != '\n') {
+                        chars.next();
                     }
+                    let end = chars.peek().map(|&(i, _)| i).unwrap_or(source.len());
+                    comments.push((start, end));
+                    continue;
+                }
+                '"' if source[start..].starts_with("\"\"\"") => {
+                    // """triple-quoted""" strings are kept verbatim, quotes and newlines included
+                    let body_start = start + 3;
+                    let body_end = source[body_start..].find("\"\"\"").map(|i| body_start + i).unwrap_or(source.len());
+                    while chars.peek().is_some_and(|&(i, _)| i < (body_end + 3).min(source.len())) {
+                        chars.next();
+                    }
+                    Token::String(source[body_start..body_end].to_string())
                 }
-                ',' => tokens.push(Token::Comma),
-                '.' => tokens.push(Token::Dot),
-                '+' => tokens.push(Token::Plus),
-                '-' => tokens.push(Token::Minus),
                 '"' => {
                     let mut string = String::new();
-                    while let Some(c) = chars.next() {
+                    for (_, c) in chars.by_ref() {
                         if c == '"' {
                             break;
                         }
                         string.push(c);
                     }
-                    tokens.push(Token::String(string));
+                    Token::String(string)
                 }
-                c if c.is_alphabetic() || c == '_' => {
+                // `$detail` / `$event`: runtime values available in handlers
+                c if c.is_alphabetic() || c == '_' || c == '$' => {
                     let mut ident = String::new();
                     ident.push(c);
-                    while let Some(&c) = chars.peek() {
-                        if c.is_alphanumeric() || c == '_' || c == '-' || c == ':' {
-                            ident.push(chars.next().unwrap());
+                    while let Some(&(i, c)) = chars.peek() {
+                        // `on:click` is one name, but `count: Number` ends at the colon
+                        let joins_name = c == ':' && source[i + 1..].starts_with(|n: char| n.is_alphanumeric() || n == '_');
+                        if c.is_alphanumeric() || c == '_' || c == '-' || joins_name {
+                            ident.push(c);
+                            chars.next();
                         } else {
                             break;
                         }
                     }
-                    tokens.push(Token::Identifier(ident));
+                    Token::Identifier(ident)
                 }
                 c if c.is_numeric() => {
                     let mut number = String::new();
                     number.push(c);
-                    while let Some(&c) = chars.peek() {
+                    while let Some(&(_, c)) = chars.peek() {
                         if c.is_numeric() || c == '.' {
-                            number.push(chars.next().unwrap());
+                            number.push(c);
+                            chars.next();
                         } else {
                             break;
                         }
                     }
-                    tokens.push(Token::Number(number));
+                    Token::Number(number)
                 }
                 c if c.is_whitespace() => continue,
-                _ => {} // Ignore other characters for now
-            }
+                _ => continue, // Ignore other characters for now
+            };
+            tokens.push(token);
+            offsets.push(start);
+            ends.push(chars.peek().map(|&(i, _)| i).unwrap_or(source.len()));
         }
         tokens.push(Token::Eof);
-        tokens
+        offsets.push(source.len());
+        ends.push(source.len());
+        (tokens, offsets, ends, comments)
+    }
+
+    // Record that a statement of `kind` starts at the current token; the
+    // outermost one wins when several start together
+    fn mark(&mut self, kind: Statement) {
+        self.statements.entry(self.pos).or_insert(kind);
+    }
+
+    fn located(&self, error: ParseError) -> ParseError {
+        match error {
+            ParseError::Duplicate { .. } | ParseError::At { .. } => error,
+            error => {
+                let start = self.offsets.get(self.pos).copied().unwrap_or(self.source.len());
+                let end = self.ends.get(self.pos).copied().unwrap_or(start);
+                ParseError::At { span: self.span_at(start, end), error: Box::new(error) }
+            }
+        }
+    }
+
+    fn span_at(&self, start: usize, end: usize) -> Span {
+        let line = self.line_starts.partition_point(|&s| s <= start);
+        let line_start = self.line_starts[line - 1];
+        let column = self.source[line_start..start].chars().count() + 1;
+        Span { start, end, line, column }
+    }
+
+    // Offset of the first character inside the current string token's quotes
+    fn string_body_start(&self) -> usize {
+        let start = self.offsets[self.pos];
+        if self.source[start..].starts_with("\"\"\"") { start + 3 } else { start + 1 }
+    }
+
+    fn current_span(&self) -> Span {
+        self.span_at(self.offsets[self.pos], self.ends[self.pos])
     }
 
     fn current_token(&self) -> &Token {
@@ -134,36 +320,266 @@ impl Parser {
         }
     }
 
+    // `expect` only compares token kinds; keywords need their text checked too
+    fn expect_keyword(&mut self, keyword: &str) -> Result<(), ParseError> {
+        match self.current_token() {
+            Token::Identifier(ref name) if name == keyword => {
+                self.advance();
+                Ok(())
+            }
+            _ => Err(ParseError::ExpectedToken(format!("'{}'", keyword))),
+        }
+    }
+
+    fn peek_token(&self, offset: usize) -> &Token {
+        self.tokens.get(self.pos + offset).unwrap_or(&Token::Eof)
+    }
+
+    // Dotted path such as `posts` or `site.posts`
+    fn parse_path(&mut self) -> Result<String, ParseError> {
+        let mut path = match self.current_token() {
+            Token::Identifier(ref name) => name.clone(),
+            _ => return Err(ParseError::ExpectedToken("identifier".to_string())),
+        };
+        self.advance();
+        while matches!(self.current_token(), Token::Dot) {
+            self.advance();
+            match self.current_token() {
+                Token::Identifier(ref name) => {
+                    path.push('.');
+                    path.push_str(name);
+                    self.advance();
+                }
+                _ => return Err(ParseError::ExpectedToken("identifier after '.'".to_string())),
+            }
+        }
+        Ok(path)
+    }
+
+    // `{ element* }`
+    fn parse_block(&mut self) -> Result<Vec<Element>, ParseError> {
+        self.expect(Token::LeftBrace)?;
+        let mut content = Vec::new();
+        while !matches!(self.current_token(), Token::RightBrace | Token::Eof) {
+            content.push(self.parse_element()?);
+        }
+        self.expect(Token::RightBrace)?;
+        Ok(content)
+    }
+
+    // Source text between the current `{` and its matching `}`, taken verbatim
+    // for content the tokenizer cannot represent (CSS)
+    fn capture_braced_source(&mut self) -> Result<String, ParseError> {
+        if !matches!(self.current_token(), Token::LeftBrace) {
+            return Err(ParseError::ExpectedToken("LeftBrace".to_string()));
+        }
+        let open_token = self.pos;
+        let open = self.offsets[self.pos];
+        let mut depth = 0usize;
+        let mut close = None;
+        let mut in_string: Option<char> = None;
+        let mut in_comment = false;
+        let mut chars = self.source[open..].char_indices().peekable();
+        while let Some((i, c)) = chars.next() {
+            match (in_string, c) {
+                // `/* don't */`: quotes and braces in a comment count for nothing
+                _ if in_comment && c == '*' && chars.next_if(|&(_, c)| c == '/').is_some() => in_comment = false,
+                _ if in_comment => {}
+                (Some(_), '\\') => {
+                    chars.next();
+                }
+                (Some(q), c) if c == q => in_string = None,
+                (Some(_), _) => {}
+                (None, '/') if chars.next_if(|&(_, c)| c == '*').is_some() => in_comment = true,
+                (None, '"') | (None, '\'') => in_string = Some(c),
+                (None, '{') => depth += 1,
+                (None, '}') => {
+                    depth -= 1;
+                    if depth == 0 {
+                        close = Some(open + i);
+                        break;
+                    }
+                }
+                _ => {}
+            }
+        }
+        let close = close.ok_or_else(|| ParseError::InvalidSyntax("unclosed '{' in style block".to_string()))?;
+        let text = dedent(&self.source[open + 1..close]);
+        while self.offsets[self.pos] <= close && !matches!(self.current_token(), Token::Eof) {
+            self.advance();
+        }
+        self.verbatim.push((open_token, self.pos - 1, close));
+        Ok(text)
+    }
+
+    // Optional `<keyword> Name` after a declaration name (`layout Docs`, `extends MainLayout`)
+    fn parse_named_clause(&mut self, keyword: &str) -> Result<Option<String>, ParseError> {
+        if !matches!(self.current_token(), Token::Identifier(ref kw) if kw == keyword) {
+            return Ok(None);
+        }
+        self.advance();
+        match self.current_token() {
+            Token::Identifier(ref name) => {
+                let name = name.clone();
+                self.advance();
+                Ok(Some(name))
+            }
+            _ => Err(ParseError::ExpectedToken(format!("name after '{}'", keyword))),
+        }
+    }
+
+    fn is_translation_call(&self) -> bool {
+        matches!(self.current_token(), Token::Identifier(ref name) if name == "t")
+            && matches!(self.peek_token(1), Token::LeftParen)
+    }
+
+    // `t("cart.items", count)` kept as source text, like an interpolation expression
+    fn parse_translation_call(&mut self) -> Result<Element, ParseError> {
+        let start = self.offsets[self.pos];
+        self.advance();
+        let mut depth = 0usize;
+        loop {
+            match self.current_token() {
+                Token::LeftParen => depth += 1,
+                Token::RightParen => {
+                    depth -= 1;
+                    if depth == 0 {
+                        break;
+                    }
+                }
+                Token::Eof => return Err(ParseError::ExpectedToken("')' after t(...)".to_string())),
+                _ => {}
+            }
+            self.advance();
+        }
+        let end = self.ends[self.pos];
+        self.advance();
+        Ok(Element::Interpolation { expr: self.source[start..end].to_string(), span: self.span_at(start, end) })
+    }
+
+    // `data x = ...` / `resource x = ...`
+    fn is_declaration(&self, keyword: &str) -> bool {
+        matches!(self.current_token(), Token::Identifier(ref kw) if kw == keyword)
+            && matches!(self.peek_token(1), Token::Identifier(_))
+            && matches!(self.peek_token(2), Token::Equals)
+    }
+
+    // data posts = load("content/posts.json") | data posts = collection("blog")
+    fn parse_data_source(&mut self) -> Result<DataSource, ParseError> {
+        self.mark(Statement::Declaration);
+        self.expect_keyword("data")?;
+        let span = self.current_span();
+        let name = match self.current_token() {
+            Token::Identifier(ref name) => {
+                let name = name.clone();
+                self.advance();
+                name
+            }
+            _ => return Err(ParseError::ExpectedToken("data source name".to_string())),
+        };
+        self.expect(Token::Equals)?;
+        let kind = match self.current_token() {
+            Token::Identifier(ref f) if f == "load" => DataSourceKind::File,
+            Token::Identifier(ref f) if f == "collection" => DataSourceKind::Collection,
+            _ => return Err(ParseError::ExpectedToken("load(...) or collection(...)".to_string())),
+        };
+        self.advance();
+        self.expect(Token::LeftParen)?;
+        let path = match self.current_token() {
+            Token::String(ref path) => {
+                let path = path.clone();
+                self.advance();
+                path
+            }
+            _ => return Err(ParseError::ExpectedToken("data file path".to_string())),
+        };
+        self.expect(Token::RightParen)?;
+        if kind == DataSourceKind::File && crate::data::DataFormat::from_path(&path).is_none() {
+            return Err(ParseError::InvalidSyntax(format!("unsupported data format for '{}' (expected .json, .toml or .csv)", path)));
+        }
+        Ok(DataSource { name, path, kind, span })
+    }
+
+    // resource users = fetch("/api/users")
+    fn parse_resource(&mut self) -> Result<Resource, ParseError> {
+        self.mark(Statement::Declaration);
+        self.expect_keyword("resource")?;
+        let span = self.current_span();
+        let name = match self.current_token() {
+            Token::Identifier(ref name) => {
+                let name = name.clone();
+                self.advance();
+                name
+            }
+            _ => return Err(ParseError::ExpectedToken("resource name".to_string())),
+        };
+        self.expect(Token::Equals)?;
+        self.expect_keyword("fetch")?;
+        self.expect(Token::LeftParen)?;
+        let url = match self.current_token() {
+            Token::String(ref url) => {
+                let url = url.clone();
+                self.advance();
+                url
+            }
+            _ => return Err(ParseError::ExpectedToken("resource URL".to_string())),
+        };
+        self.expect(Token::RightParen)?;
+        Ok(Resource { name, url, span })
+    }
+
     pub fn parse_document(&mut self) -> Result<WebCoreDocument, ParseError> {
         let mut app = None;
-        let mut layouts = HashMap::new();
-        let mut pages = HashMap::new();
-        let mut components = HashMap::new();
+        let mut layouts: HashMap<String, Layout> = HashMap::new();
+        let mut pages: HashMap<String, Page> = HashMap::new();
+        let mut components: HashMap<String, Component> = HashMap::new();
+        let mut stores: HashMap<String, Store> = HashMap::new();
+        let mut global_styles = Vec::new();
 
         while !matches!(self.current_token(), Token::Eof) {
+            self.mark(Statement::Definition);
             match self.current_token() {
+                Token::Identifier(ref name) if name == "style" && matches!(self.peek_token(1), Token::Identifier(ref g) if g == "global") => {
+                    let span = self.current_span();
+                    self.advance();
+                    self.advance();
+                    let content = self.capture_braced_source()?;
+                    global_styles.push(RawBlock { kind: RawKind::Style, content, src: None, attributes: Vec::new(), span });
+                }
                 Token::Identifier(ref name) if name == "app" => {
                     app = Some(self.parse_app()?);
                 }
                 Token::Identifier(ref name) if name == "layout" => {
                     let layout = self.parse_layout()?;
+                    unique("layout", &layout.name, layout.span, layouts.get(&layout.name).map(|l| l.span))?;
                     layouts.insert(layout.name.clone(), layout);
                 }
                 Token::Identifier(ref name) if name == "page" => {
                     let page = self.parse_page()?;
+                    unique("page", &page.name, page.span, pages.get(&page.name).map(|p| p.span))?;
                     pages.insert(page.name.clone(), page);
                 }
                 Token::Identifier(ref name) if name == "component" => {
                     let component = self.parse_component()?;
+                    unique("component", &component.name, component.span, components.get(&component.name).map(|c| c.span))?;
                     components.insert(component.name.clone(), component);
                 }
+                Token::Identifier(ref name) if name == "store" => {
+                    let store = self.parse_store()?;
+                    unique("store", &store.name, store.span, stores.get(&store.name).map(|s| s.span))?;
+                    stores.insert(store.name.clone(), store);
+                }
                 _ => {
                     // Try to parse as a simple element
                     let element = self.parse_element()?;
                     // For now, create a default page with this element
                     let page = Page {
                         name: "default".to_string(),
+                        layout: None,
+                        data: Vec::new(),
                         content: vec![element],
+                        file: String::new(),
+                        span: Span::default(),
                     };
                     pages.insert("default".to_string(), page);
                 }
@@ -175,10 +591,13 @@ impl Parser {
             layouts,
             pages,
             components,
+            stores,
+            global_styles,
         })
     }
 
     fn parse_app(&mut self) -> Result<App, ParseError> {
+        let span = self.current_span();
         self.expect(Token::Identifier("app".to_string()))?;
         
         let name = match self.current_token() {
@@ -194,12 +613,13 @@ impl Parser {
         
         let mut theme = None;
         let mut layout = None;
-        let mut routes = HashMap::new();
+        let mut routes = Vec::new();
         
         while !matches!(self.current_token(), Token::RightBrace) {
             match self.current_token() {
                 Token::Identifier(ref key) => {
                     let key = key.clone();
+                    self.mark(Statement::Declaration);
                     self.advance();
                     
                     if key == "theme" {
@@ -225,6 +645,8 @@ impl Parser {
                     } else if key == "routes" {
                         self.expect(Token::LeftBrace)?;
                         while !matches!(self.current_token(), Token::RightBrace) {
+                            self.mark(Statement::Declaration);
+                            let span = self.current_span();
                             let route_path = match self.current_token() {
                                 Token::String(ref path) => {
                                     let path = path.clone();
@@ -242,7 +664,7 @@ impl Parser {
                                 }
                                 _ => return Err(ParseError::ExpectedToken("component name".to_string())),
                             };
-                            routes.insert(route_path, component_name);
+                            routes.push(Route { path: route_path, target: component_name, span });
                         }
                         self.expect(Token::RightBrace)?;
                     }
@@ -258,12 +680,14 @@ impl Parser {
             theme,
             layout,
             routes,
+            file: String::new(),
+            span,
         })
     }
 
     fn parse_layout(&mut self) -> Result<Layout, ParseError> {
         self.expect(Token::Identifier("layout".to_string()))?;
-        
+        let span = self.current_span();
         let name = match self.current_token() {
             Token::Identifier(ref name) => {
                 let name = name.clone();
@@ -272,6 +696,7 @@ impl Parser {
             }
             _ => return Err(ParseError::ExpectedToken("layout name".to_string())),
         };
+        let extends = self.parse_named_clause("extends")?;
 
         self.expect(Token::LeftBrace)?;
         
@@ -282,12 +707,12 @@ impl Parser {
         
         self.expect(Token::RightBrace)?;
         
-        Ok(Layout { name, content })
+        Ok(Layout { name, extends, content, file: String::new(), span })
     }
 
     fn parse_page(&mut self) -> Result<Page, ParseError> {
         self.expect(Token::Identifier("page".to_string()))?;
-        
+        let span = self.current_span();
         let name = match self.current_token() {
             Token::String(ref name) => {
                 let name = name.clone();
@@ -296,22 +721,28 @@ impl Parser {
             }
             _ => return Err(ParseError::ExpectedToken("page name".to_string())),
         };
+        let layout = self.parse_named_clause("layout")?;
 
         self.expect(Token::LeftBrace)?;
         
+        let mut data = Vec::new();
         let mut content = Vec::new();
         while !matches!(self.current_token(), Token::RightBrace) {
-            content.push(self.parse_element()?);
+            if self.is_declaration("data") {
+                data.push(self.parse_data_source()?);
+            } else {
+                content.push(self.parse_element()?);
+            }
         }
         
         self.expect(Token::RightBrace)?;
         
-        Ok(Page { name, content })
+        Ok(Page { name, layout, data, content, file: String::new(), span })
     }
 
     fn parse_component(&mut self) -> Result<Component, ParseError> {
         self.expect(Token::Identifier("component".to_string()))?;
-        
+        let span = self.current_span();
         let name = match self.current_token() {
             Token::Identifier(ref name) => {
                 let name = name.clone();
@@ -320,88 +751,55 @@ impl Parser {
             }
             _ => return Err(ParseError::ExpectedToken("component name".to_string())),
         };
+        let layout = self.parse_named_clause("layout")?;
 
         self.expect(Token::LeftBrace)?;
         
         let mut props = Vec::new();
+        let mut imports = Vec::new();
+        let mut events = Vec::new();
         let mut state = Vec::new();
+        let mut data = Vec::new();
+        let mut resources = Vec::new();
         let mut view = Vec::new();
         let mut style = Vec::new();
+        let mut keyframes = Vec::new();
         
         while !matches!(self.current_token(), Token::RightBrace) {
+            if self.is_declaration("data") {
+                data.push(self.parse_data_source()?);
+                continue;
+            }
+            if self.is_declaration("resource") {
+                resources.push(self.parse_resource()?);
+                continue;
+            }
+            if matches!(self.current_token(), Token::Identifier(ref kw) if kw == "use") && matches!(self.peek_token(1), Token::Identifier(_)) {
+                self.mark(Statement::Declaration);
+                self.advance();
+                let span = self.current_span();
+                if let Token::Identifier(ref name) = self.current_token() {
+                    imports.push(StoreImport { name: name.clone(), span });
+                }
+                self.advance();
+                continue;
+            }
             match self.current_token() {
                 Token::Identifier(ref section) => {
                     let section = section.clone();
+                    self.mark(Statement::Declaration);
                     self.advance();
                     
                     if section == "props" {
-                        self.expect(Token::LeftBrace)?;
-                        while !matches!(self.current_token(), Token::RightBrace) {
-                            let prop_name = match self.current_token() {
-                                Token::Identifier(ref name) => {
-                                    let name = name.clone();
-                                    self.advance();
-                                    name
-                                }
-                                _ => return Err(ParseError::ExpectedToken("prop name".to_string())),
-                            };
-                            let prop_type = if matches!(self.current_token(), Token::Colon) {
-                                self.advance();
-                                Some(match self.current_token() {
-                                    Token::Identifier(ref type_) => {
-                                        let type_ = type_.clone();
-                                        self.advance();
-                                        type_
-                                    }
-                                    _ => return Err(ParseError::ExpectedToken("prop type".to_string())),
-                                })
-                            } else {
-                                None
-                            };
-                            props.push(Prop { name: prop_name, type_: prop_type });
+                        for (name, type_, span) in self.parse_typed_names("prop")? {
+                            props.push(Prop { name, type_, span });
                         }
-                        self.expect(Token::RightBrace)?;
-                    } else if section == "state" {
-                        self.expect(Token::LeftBrace)?;
-                        while !matches!(self.current_token(), Token::RightBrace) {
-                            let state_name = match self.current_token() {
-                                Token::Identifier(ref name) => {
-                                    let name = name.clone();
-                                    self.advance();
-                                    name
-                                }
-                                _ => return Err(ParseError::ExpectedToken("state name".to_string())),
-                            };
-                            self.expect(Token::Colon)?;
-                            let state_type = match self.current_token() {
-                                Token::Identifier(ref type_) => {
-                                    let type_ = type_.clone();
-                                    self.advance();
-                                    type_
-                                }
-                                _ => return Err(ParseError::ExpectedToken("state type".to_string())),
-                            };
-                            let default_value = if matches!(self.current_token(), Token::Equals) {
-                                self.advance();
-                                Some(match self.current_token() {
-                                    Token::Number(ref value) => {
-                                        let value = value.clone();
-                                        self.advance();
-                                        value
-                                    }
-                                    Token::String(ref value) => {
-                                        let value = value.clone();
-                                        self.advance();
-                                        value
-                                    }
-                                    _ => return Err(ParseError::ExpectedToken("default value".to_string())),
-                                })
-                            } else {
-                                None
-                            };
-                            state.push(StateVar { name: state_name, type_: state_type, default_value });
+                    } else if section == "events" {
+                        for (name, type_, span) in self.parse_typed_names("event")? {
+                            events.push(EventDecl { name, type_, span });
                         }
-                        self.expect(Token::RightBrace)?;
+                    } else if section == "state" {
+                        state.extend(self.parse_state_fields()?);
                     } else if section == "view" {
                         self.expect(Token::LeftBrace)?;
                         while !matches!(self.current_token(), Token::RightBrace) {
@@ -411,6 +809,22 @@ impl Parser {
                     } else if section == "style" {
                         self.expect(Token::LeftBrace)?;
                         while !matches!(self.current_token(), Token::RightBrace) {
+                            if matches!(self.current_token(), Token::Identifier(ref kw) if kw == "keyframes") {
+                                self.mark(Statement::Declaration);
+                                self.advance();
+                                let span = self.current_span();
+                                let name = match self.current_token() {
+                                    Token::Identifier(ref name) => {
+                                        let name = name.clone();
+                                        self.advance();
+                                        name
+                                    }
+                                    _ => return Err(ParseError::ExpectedToken("keyframes name".to_string())),
+                                };
+                                let body = self.capture_braced_source()?;
+                                keyframes.push(Keyframes { name, body, span });
+                                continue;
+                            }
                             style.push(self.parse_style_rule()?);
                         }
                         self.expect(Token::RightBrace)?;
@@ -427,14 +841,159 @@ impl Parser {
         
         Ok(Component { 
             name, 
+            layout,
             props, 
+            imports,
+            events,
             state, 
+            data,
+            resources,
             view, 
-            style 
+            style,
+            keyframes,
+            file: String::new(),
+            span,
         })
     }
 
+    // `{ count: Number = 0 items: List = [] }` for component state and stores
+    fn parse_state_fields(&mut self) -> Result<Vec<StateVar>, ParseError> {
+        self.expect(Token::LeftBrace)?;
+        let mut fields = Vec::new();
+        while !matches!(self.current_token(), Token::RightBrace) {
+            self.mark(Statement::Declaration);
+            let span = self.current_span();
+            let name = match self.current_token() {
+                Token::Identifier(ref name) => {
+                    let name = name.clone();
+                    self.advance();
+                    name
+                }
+                _ => return Err(ParseError::ExpectedToken("state name".to_string())),
+            };
+            self.expect(Token::Colon)?;
+            let type_ = match self.current_token() {
+                Token::Identifier(ref type_) => {
+                    let type_ = type_.clone();
+                    self.advance();
+                    type_
+                }
+                _ => return Err(ParseError::ExpectedToken("state type".to_string())),
+            };
+            let default_value = if matches!(self.current_token(), Token::Equals) {
+                self.advance();
+                Some(self.parse_literal()?.to_string())
+            } else {
+                None
+            };
+            fields.push(StateVar { name, type_, default_value, span });
+        }
+        self.expect(Token::RightBrace)?;
+        Ok(fields)
+    }
+
+    // Default values: numbers, strings, booleans, null, `[...]` and `{ key: value }`
+    fn parse_literal(&mut self) -> Result<serde_json::Value, ParseError> {
+        let value = match self.current_token().clone() {
+            Token::Number(number) => serde_json::from_str(&number)
+                .map_err(|_| ParseError::InvalidSyntax(format!("invalid number '{}'", number)))?,
+            Token::Minus => {
+                self.advance();
+                let Token::Number(number) = self.current_token().clone() else {
+                    return Err(ParseError::ExpectedToken("number after '-'".to_string()));
+                };
+                serde_json::from_str(&format!("-{}", number))
+                    .map_err(|_| ParseError::InvalidSyntax(format!("invalid number '-{}'", number)))?
+            }
+            Token::String(text) => serde_json::Value::String(text),
+            Token::Identifier(ref word) if word == "true" || word == "false" => serde_json::Value::Bool(word == "true"),
+            Token::Identifier(ref word) if word == "null" => serde_json::Value::Null,
+            Token::LeftBracket => {
+                self.advance();
+                let mut items = Vec::new();
+                while !matches!(self.current_token(), Token::RightBracket) {
+                    items.push(self.parse_literal()?);
+                    if matches!(self.current_token(), Token::Comma) {
+                        self.advance();
+                    }
+                }
+                serde_json::Value::Array(items)
+            }
+            Token::LeftBrace => {
+                self.advance();
+                let mut map = serde_json::Map::new();
+                while !matches!(self.current_token(), Token::RightBrace) {
+                    let key = match self.current_token() {
+                        Token::Identifier(ref key) | Token::String(ref key) => key.clone(),
+                        _ => return Err(ParseError::ExpectedToken("object key".to_string())),
+                    };
+                    self.advance();
+                    self.expect(Token::Colon)?;
+                    map.insert(key, self.parse_literal()?);
+                    if matches!(self.current_token(), Token::Comma) {
+                        self.advance();
+                    }
+                }
+                serde_json::Value::Object(map)
+            }
+            _ => return Err(ParseError::ExpectedToken("default value".to_string())),
+        };
+        self.advance();
+        Ok(value)
+    }
+
+    fn parse_store(&mut self) -> Result<Store, ParseError> {
+        self.expect_keyword("store")?;
+        let span = self.current_span();
+        let name = match self.current_token() {
+            Token::Identifier(ref name) => {
+                let name = name.clone();
+                self.advance();
+                name
+            }
+            _ => return Err(ParseError::ExpectedToken("store name".to_string())),
+        };
+        let fields = self.parse_state_fields()?;
+        Ok(Store { name, fields, file: String::new(), span })
+    }
+
+    // `{ name: Type other }` as used by `props` and `events`; the type is optional
+    fn parse_typed_names(&mut self, what: &str) -> Result<Vec<(String, Option<String>, Span)>, ParseError> {
+        self.expect(Token::LeftBrace)?;
+        let mut names = Vec::new();
+        while !matches!(self.current_token(), Token::RightBrace) {
+            self.mark(Statement::Declaration);
+            let span = self.current_span();
+            let name = match self.current_token() {
+                Token::Identifier(ref name) => {
+                    let name = name.clone();
+                    self.advance();
+                    name
+                }
+                _ => return Err(ParseError::ExpectedToken(format!("{} name", what))),
+            };
+            let type_ = if matches!(self.current_token(), Token::Colon) {
+                self.advance();
+                Some(match self.current_token() {
+                    Token::Identifier(ref type_) => {
+                        let type_ = type_.clone();
+                        self.advance();
+                        type_
+                    }
+                    _ => return Err(ParseError::ExpectedToken(format!("{} type", what))),
+                })
+            } else {
+                None
+            };
+            names.push((name, type_, span));
+        }
+        self.expect(Token::RightBrace)?;
+        Ok(names)
+    }
+
     fn parse_style_rule(&mut self) -> Result<StyleRule, ParseError> {
+        self.mark(Statement::Declaration);
+        let span = self.current_span();
         let selector = match self.current_token() {
             Token::Identifier(ref selector) => {
                 let selector = selector.clone();
@@ -448,6 +1007,7 @@ impl Parser {
         
         let mut properties = Vec::new();
         while !matches!(self.current_token(), Token::RightBrace) {
+            self.mark(Statement::Declaration);
             let prop_name = match self.current_token() {
                 Token::Identifier(ref name) => {
                     let name = name.clone();
@@ -475,10 +1035,15 @@ impl Parser {
         
         self.expect(Token::RightBrace)?;
         
-        Ok(StyleRule { selector, properties })
+        Ok(StyleRule { selector, properties, span })
     }
 
     fn parse_element(&mut self) -> Result<Element, ParseError> {
+        self.mark(Statement::Element);
+        let span = self.current_span();
+        if self.is_translation_call() {
+            return self.parse_translation_call();
+        }
         match self.current_token() {
             Token::Identifier(ref tag_name) => {
                 let tag_name = tag_name.clone();
@@ -494,7 +1059,50 @@ impl Parser {
                         }
                         _ => "content".to_string(),
                     };
-                    return Ok(Element::Slot(slot_name));
+                    return Ok(Element::Slot { name: slot_name, span });
+                }
+
+                // raw html """<svg>...</svg>"""
+                if tag_name == "raw" {
+                    self.expect_keyword("html")?;
+                    let content = match self.current_token() {
+                        Token::String(ref text) => {
+                            let text = text.clone();
+                            self.advance();
+                            text
+                        }
+                        _ => return Err(ParseError::ExpectedToken("raw html content".to_string())),
+                    };
+                    return Ok(Element::Raw(RawBlock { kind: RawKind::Html, content, src: None, attributes: Vec::new(), span }));
+                }
+
+                // for post in posts { ... }
+                if tag_name == "for" {
+                    let item = match self.current_token() {
+                        Token::Identifier(ref name) => {
+                            let name = name.clone();
+                            self.advance();
+                            name
+                        }
+                        _ => return Err(ParseError::ExpectedToken("loop variable".to_string())),
+                    };
+                    self.expect_keyword("in")?;
+                    let iterable = self.parse_path()?;
+                    let content = self.parse_block()?;
+                    return Ok(Element::For { item, iterable, content, span });
+                }
+
+                // if node.children { ... } else { ... }
+                if tag_name == "if" {
+                    let condition = self.parse_path()?;
+                    let content = self.parse_block()?;
+                    let else_content = if matches!(self.current_token(), Token::Identifier(ref kw) if kw == "else") {
+                        self.advance();
+                        self.parse_block()?
+                    } else {
+                        Vec::new()
+                    };
+                    return Ok(Element::If { condition, content, else_content, span });
                 }
                 
                 // Parse attributes
@@ -512,7 +1120,12 @@ impl Parser {
                     if matches!(self.current_token(), Token::Identifier(_)) && matches!(next_token, Token::LeftBrace) {
                         break;
                     }
-                    let attr_name = match self.current_token() {
+                    // `h1 t("nav.home")`: translated text content
+                    if self.is_translation_call() {
+                        break;
+                    }
+                    let attr_span = self.current_span();
+                    let mut attr_name = match self.current_token() {
                         Token::Identifier(ref name) => {
                             let name = name.clone();
                             self.advance();
@@ -520,6 +1133,17 @@ impl Parser {
                         }
                         _ => break,
                     };
+                    // `on:click.prevent`, `on:keydown.ctrl.s`: modifiers stay part of the name
+                    while attr_name.starts_with("on:")
+                        && matches!(self.current_token(), Token::Dot)
+                        && self.offsets[self.pos] == self.ends[self.pos - 1]
+                    {
+                        let Token::Identifier(modifier) = self.peek_token(1).clone() else { break };
+                        attr_name.push('.');
+                        attr_name.push_str(&modifier);
+                        self.advance();
+                        self.advance();
+                    }
                     
                     if matches!(self.current_token(), Token::Equals) {
                         self.advance();
@@ -541,9 +1165,14 @@ impl Parser {
                                         Token::Equals => expr.push('='),
                                         Token::LeftParen => expr.push('('),
                                         Token::RightParen => expr.push(')'),
+                                        Token::LeftBracket => expr.push('['),
+                                        Token::RightBracket => expr.push(']'),
                                         Token::Comma => expr.push(','),
                                         Token::Dot => expr.push('.'),
                                         Token::Arrow => expr.push_str("=>"),
+                                        Token::Colon => expr.push(':'),
+                                        Token::Bang => expr.push('!'),
+                                        Token::String(ref text) => expr.push_str(&format!("\"{}\"", text)),
                                         _ => expr.push(' '),
                                     }
                                     self.advance();
@@ -553,15 +1182,39 @@ impl Parser {
                             }
                             _ => AttributeValue::Boolean(true),
                         };
-                        attributes.push(Attribute { name: attr_name, value: attr_value });
+                        attributes.push(Attribute { name: attr_name, value: attr_value, span: attr_span });
                     } else {
-                        attributes.push(Attribute { name: attr_name, value: AttributeValue::Boolean(true) });
+                        attributes.push(Attribute { name: attr_name, value: AttributeValue::Boolean(true), span: attr_span });
                     }
                 }
                 
+                // script src="..." / script """inline js""": passed through untouched
+                if tag_name == "script" {
+                    let content = match self.current_token() {
+                        Token::String(ref text) => {
+                            let text = text.clone();
+                            self.advance();
+                            text
+                        }
+                        _ => String::new(),
+                    };
+                    let src = attributes.iter().find(|a| a.name == "src").and_then(|a| match &a.value {
+                        AttributeValue::String(v) => Some(v.clone()),
+                        _ => None,
+                    });
+                    attributes.retain(|a| a.name != "src");
+                    if src.is_none() && content.is_empty() {
+                        return Err(ParseError::InvalidSyntax("script needs a src attribute or inline content".to_string()));
+                    }
+                    return Ok(Element::Raw(RawBlock { kind: RawKind::Script, content, src, attributes, span }));
+                }
+                
                 // Check if there's content (string or nested elements)
-                let content = if matches!(self.current_token(), Token::String(_)) {
+                let content = if self.is_translation_call() {
+                    vec![self.parse_translation_call()?]
+                } else if matches!(self.current_token(), Token::String(_)) {
                     // Simple text content (with possible interpolations)
+                    let text_start = self.string_body_start();
                     let text = match self.current_token() {
                         Token::String(ref text) => {
                             let text = text.clone();
@@ -571,7 +1224,7 @@ impl Parser {
                         _ => return Err(ParseError::ExpectedToken("text content".to_string())),
                     };
                     // Split mixed text with interpolations into multiple elements
-                    split_interpolated_text(&text)
+                    split_interpolated_text(&text, |start, end| self.span_at(text_start + start, text_start + end))
                 } else if matches!(self.current_token(), Token::LeftBrace) {
                     // Nested elements
                     self.expect(Token::LeftBrace)?;
@@ -587,29 +1240,36 @@ impl Parser {
                 };
                 
                 // Check if it's a component (capitalized) or regular tag
-                if tag_name.chars().next().map_or(false, |c| c.is_uppercase()) {
+                if tag_name.chars().next().is_some_and(|c| c.is_uppercase()) {
                     Ok(Element::Component {
                         name: tag_name,
                         attributes,
                         content,
+                        span,
                     })
                 } else {
                     Ok(Element::Tag {
                         name: tag_name,
                         attributes,
                         content,
+                        span,
                     })
                 }
             }
             Token::String(ref text) => {
                 let text = text.clone();
+                let text_start = self.string_body_start();
                 self.advance();
-                // Fallback: if there is a single interpolation with no surrounding text, return it
-                if text.starts_with('{') && text.ends_with('}') && text.len() >= 2 {
-                    let var = text[1..text.len()-1].to_string();
-                    Ok(Element::Interpolation(var))
-                } else {
-                    Ok(Element::Text(text))
+                // Mixed text is kept together under a `text` element, which renders only its children
+                let mut parts = split_interpolated_text(&text, |start, end| self.span_at(text_start + start, text_start + end));
+                match parts.len() {
+                    1 => Ok(parts.remove(0)),
+                    _ => Ok(Element::Tag {
+                        name: "text".to_string(),
+                        attributes: Vec::new(),
+                        content: parts,
+                        span: self.span_at(text_start, text_start + text.len()),
+                    }),
                 }
             }
             _ => Err(ParseError::UnexpectedToken(format!("{:?}", self.current_token()))),
@@ -617,8 +1277,23 @@ impl Parser {
     }
 }
 
+// Strip the common leading indentation of a verbatim block
+pub(crate) fn dedent(text: &str) -> String {
+    let lines: Vec<&str> = text.trim_matches('\n').lines().collect();
+    let indent = lines.iter()
+        .filter(|l| !l.trim().is_empty())
+        .map(|l| l.len() - l.trim_start().len())
+        .min()
+        .unwrap_or(0);
+    lines.iter()
+        .map(|l| if l.len() >= indent { &l[indent..] } else { l.trim_start() })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
 // Split a string potentially containing multiple {var} interpolations into a sequence of Elements
-fn split_interpolated_text(text: &str) -> Vec<Element> {
+// `span_of` maps a byte range of `text` back to the source file
+fn split_interpolated_text(text: &str, span_of: impl Fn(usize, usize) -> Span) -> Vec<Element> {
     let mut elements: Vec<Element> = Vec::new();
     let mut i = 0usize;
     let bytes = text.as_bytes();
@@ -629,27 +1304,27 @@ fn split_interpolated_text(text: &str) -> Vec<Element> {
             let start_idx = i + start;
             // push prefix text if any
             if start_idx > i {
-                elements.push(Element::Text(text[i..start_idx].to_string()));
+                elements.push(Element::Text { text: text[i..start_idx].to_string(), span: span_of(i, start_idx) });
             }
             // find matching '}' after '{'
             if let Some(end) = text[start_idx..].find('}') {
                 let end_idx = start_idx + end;
                 let var_name = text[start_idx + 1..end_idx].trim().to_string();
-                elements.push(Element::Interpolation(var_name));
+                elements.push(Element::Interpolation { expr: var_name, span: span_of(start_idx + 1, end_idx) });
                 i = end_idx + 1; // move after '}'
             } else {
                 // no closing brace, treat rest as text
-                elements.push(Element::Text(text[start_idx..].to_string()));
+                elements.push(Element::Text { text: text[start_idx..].to_string(), span: span_of(start_idx, len) });
                 break;
             }
         } else {
             // no more '{'
-            elements.push(Element::Text(text[i..].to_string()));
+            elements.push(Element::Text { text: text[i..].to_string(), span: span_of(i, len) });
             break;
         }
     }
     if elements.is_empty() {
-        elements.push(Element::Text(text.to_string()));
+        elements.push(Element::Text { text: text.to_string(), span: span_of(0, len) });
     }
     elements
 }
@@ -664,6 +1339,7 @@ mod tests {
 component CounterPage {
   view {
     p "Nombre de clics: {count}"
+    p { "Hi {first}, {last}!" }
   }
 }
 "#;
@@ -673,10 +1349,107 @@ component CounterPage {
         match &comp.view[0] {
             Element::Tag { name, content, .. } => {
                 assert_eq!(name, "p");
-                assert!(matches!(content.get(0), Some(Element::Text(t)) if t == "Nombre de clics: "));
-                assert!(matches!(content.get(1), Some(Element::Interpolation(v)) if v == "count"));
+                assert!(matches!(content.first(), Some(Element::Text { text: t, .. }) if t == "Nombre de clics: "));
+                match content.get(1) {
+                    Some(Element::Interpolation { expr, span }) => {
+                        assert_eq!(expr, "count");
+                        assert_eq!((span.line, span.column), (4, 26));
+                        assert_eq!(&src[span.start..span.end], "count");
+                    }
+                    other => panic!("expected interpolation, got {:?}", other),
+                }
             }
             _ => panic!("expected p tag"),
         }
+        let Element::Tag { content, .. } = &comp.view[1] else { panic!("expected p tag") };
+        let Some(Element::Tag { name, content, .. }) = content.first() else { panic!("expected text") };
+        assert_eq!(name, "text");
+        let exprs: Vec<&str> = content.iter().filter_map(|e| match e { Element::Interpolation { expr, .. } => Some(expr.as_str()), _ => None }).collect();
+        assert_eq!((content.len(), exprs), (5, vec!["first", "last"]));
+    }
+
+    #[test]
+    fn parses_data_declarations_and_for_loops() {
+        let src = r#"
+page "blog" {
+  data posts = load("content/posts.json")
+  ul {
+    for post in posts {
+      li { link href={post.url} "{post.title}" }
+    }
+  }
+}
+"#;
+        let doc = parse_webc(src).expect("parse ok");
+        let page = doc.pages.get("blog").expect("page exists");
+        assert_eq!(page.data.len(), 1);
+        assert_eq!(page.data[0].name, "posts");
+        assert_eq!(page.data[0].path, "content/posts.json");
+        match &page.content[0] {
+            Element::Tag { content, .. } => match &content[0] {
+                Element::For { item, iterable, content, .. } => {
+                    assert_eq!(item, "post");
+                    assert_eq!(iterable, "posts");
+                    assert_eq!(content.len(), 1);
+                }
+                other => panic!("expected for loop, got {:?}", other),
+            },
+            _ => panic!("expected ul tag"),
+        }
+    }
+
+    #[test]
+    fn parses_raw_escape_hatches() {
+        let src = r#"
+style global {
+  /* the site's reset } */
+  body { margin: 0; }
+  a::after { content: "}"; }
+}
+component Widget {
+  view {
+    raw html """<svg viewBox="0 0 10 10"><path d="M0 0"/></svg>"""
+    h2 "Before"
+    script src="https://cdn.example.com/w.js" async
+  }
+}
+"#;
+        let doc = parse_webc(src).expect("parse ok");
+        assert_eq!(doc.global_styles.len(), 1);
+        assert_eq!(doc.global_styles[0].content, "/* the site's reset } */\nbody { margin: 0; }\na::after { content: \"}\"; }");
+        let view = &doc.components.get("Widget").expect("component").view;
+        assert!(matches!(&view[0], Element::Raw(RawBlock { kind: RawKind::Html, content, .. }) if content.starts_with("<svg viewBox=\"0 0 10 10\">")));
+        assert!(matches!(&view[1], Element::Tag { name, .. } if name == "h2"));
+        match &view[2] {
+            Element::Raw(block) => {
+                assert_eq!(block.kind, RawKind::Script);
+                assert_eq!(block.src.as_deref(), Some("https://cdn.example.com/w.js"));
+                assert_eq!(block.attributes[0].name, "async");
+            }
+            other => panic!("expected script, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn text_slots_raw_blocks_style_rules_and_the_app_have_spans() {
+        let src = r#"app Demo { layout: Shell }
+component Shell {
+  view { main { "Hi {name}!" slot } raw html "<hr>" }
+  style { main { color: red } }
+}
+"#;
+        let doc = parse_webc(src).expect("parse ok");
+        let text = |span: &Span| &src[span.start..span.end];
+        assert_eq!(text(&doc.app.as_ref().expect("app").span), "app");
+        let shell = doc.components.get("Shell").expect("component");
+        let Element::Tag { content, .. } = &shell.view[0] else { panic!("expected main") };
+        let Element::Tag { content: parts, .. } = &content[0] else { panic!("expected text") };
+        let Element::Text { span, .. } = &parts[2] else { panic!("expected trailing text") };
+        assert_eq!((text(span), span.line, span.column), ("!", 3, 27));
+        let Element::Slot { name, span } = &content[1] else { panic!("expected slot") };
+        assert_eq!((name.as_str(), text(span)), ("content", "slot"));
+        let Element::Raw(raw) = &shell.view[1] else { panic!("expected raw html") };
+        assert_eq!((raw.span.line, raw.span.column), (3, 37));
+        assert_eq!((text(&shell.style[0].span), shell.style[0].span.line), ("main", 4));
     }
 }
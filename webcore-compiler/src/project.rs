@@ -0,0 +1,1070 @@
+//! Compiling a whole project in memory
+//!
+//! `compile` reads nothing from disk and writes nothing back: the project is
+//! a map of relative paths to file contents, and the result is the map of
+//! files that make up `dist/` plus every diagnostic found on the way. The
+//! `webc` CLI is a wrapper that fills the input from the project directory
+//! and writes the output files.
+
+use crate::ast::{self, WebCoreDocument};
+use crate::checker::{self, Diagnostic};
+use crate::codegen::{codegen_css, codegen_elements, codegen_html, codegen_js};
+use crate::content::{self, ContentEntry};
+use crate::data::{self, DataSet};
+use crate::i18n::{self, Catalog};
+use crate::security::CspBuilder;
+use crate::ir;
+use crate::theme::{self, Theme};
+use crate::{a11y, assets, css_processor, html, parser, seo, transition};
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::Path;
+
+/// Project files keyed by their path relative to the project root, with `/`
+/// separators: `webc.toml`, `src/pages/home.webc`, `public/logo.png`
+#[derive(Debug, Clone, Default)]
+pub struct ProjectInput {
+    pub files: BTreeMap<String, Vec<u8>>,
+}
+
+impl ProjectInput {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Every file under `root`, except hidden entries and the `dist`, `target`
+    /// and `node_modules` directories
+    pub fn from_dir(root: &Path) -> Result<Self, String> {
+        let mut input = Self::new();
+        collect_files(root, root, &mut input)?;
+        Ok(input)
+    }
+
+    pub fn insert(&mut self, path: impl Into<String>, contents: impl Into<Vec<u8>>) {
+        self.files.insert(normalize(&path.into()), contents.into());
+    }
+
+    pub fn exists(&self, path: &str) -> bool {
+        self.files.contains_key(&normalize(path))
+    }
+
+    pub fn read(&self, path: &str) -> Option<&[u8]> {
+        self.files.get(&normalize(path)).map(Vec::as_slice)
+    }
+
+    pub fn read_to_string(&self, path: &str) -> Result<String, String> {
+        let bytes = self.read(path).ok_or_else(|| format!("{} not found", path))?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| format!("{} is not valid UTF-8", path))
+    }
+
+    /// Files anywhere below `dir`, in path order
+    pub fn files_in<'a>(&'a self, dir: &str) -> impl Iterator<Item = (&'a str, &'a [u8])> + 'a {
+        let prefix = format!("{}/", normalize(dir));
+        self.files.iter()
+            .filter(move |(path, _)| path.starts_with(&prefix))
+            .map(|(path, contents)| (path.as_str(), contents.as_slice()))
+    }
+}
+
+// `./data/x.json` and `data\x.json` name the same file as `data/x.json`
+fn normalize(path: &str) -> String {
+    let path = path.replace('\\', "/");
+    path.trim_start_matches("./").trim_start_matches('/').to_string()
+}
+
+fn collect_files(root: &Path, dir: &Path, input: &mut ProjectInput) -> Result<(), String> {
+    for entry in fs::read_dir(dir).map_err(|e| format!("Failed to read {:?}: {}", dir, e))? {
+        let path = entry.map_err(|e| format!("Failed to read entry: {}", e))?.path();
+        let name = path.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+        if name.starts_with('.') || (dir == root && matches!(name, "dist" | "target" | "node_modules")) {
+            continue;
+        }
+        if path.is_dir() {
+            collect_files(root, &path, input)?;
+        } else {
+            let contents = fs::read(&path).map_err(|e| format!("Failed to read {:?}: {}", path, e))?;
+            let relative = path.strip_prefix(root).unwrap_or(&path).to_string_lossy().to_string();
+            input.insert(relative, contents);
+        }
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Target {
+    /// Static pages, theme, runtime and public assets
+    #[default]
+    Pages,
+    /// One Custom Element module per component under `elements/`
+    Elements,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct BuildOptions {
+    pub target: Target,
+    /// Overrides `[app] mode` from webc.toml; `prod` minifies the CSS
+    pub mode: Option<String>,
+    /// Overrides `[app] router = "spa"` from webc.toml
+    pub spa: Option<bool>,
+}
+
+/// Files keyed by their path relative to `dist/`
+#[derive(Debug, Default)]
+pub struct BuildOutput {
+    pub files: BTreeMap<String, Vec<u8>>,
+    pub diagnostics: Vec<Diagnostic>,
+    /// Raw blocks by the page (or stylesheet) they ended up in
+    pub raw_blocks: RawReport,
+    /// Data files the project loads with `load("...")`, for watchers
+    pub data_files: Vec<String>,
+}
+
+impl BuildOutput {
+    /// No files are produced when this is true
+    pub fn has_errors(&self) -> bool {
+        checker::has_errors(&self.diagnostics)
+    }
+
+    fn write(&mut self, path: impl Into<String>, contents: impl Into<Vec<u8>>) {
+        self.files.insert(path.into(), contents.into());
+    }
+}
+
+pub type RawReport = Vec<(String, ast::RawBlock)>;
+
+/// Builds the project. Problems that stop the build (a missing webc.toml, an
+/// unreadable data file) are reported as error diagnostics too
+pub fn compile(input: &ProjectInput, options: &BuildOptions) -> BuildOutput {
+    let mut output = BuildOutput::default();
+    let result = match load(input, options, &mut output.diagnostics) {
+        Ok(project) => {
+            output.data_files = data::data_paths(&project.document);
+            match options.target {
+                Target::Pages => build_pages(&project, input, &mut output),
+                Target::Elements => build_elements(&project, &mut output),
+            }
+        }
+        Err(e) => Err(e),
+    };
+    if let Err(e) = result {
+        // Check errors are already in the list; the summary would only repeat them
+        if !output.has_errors() {
+            output.diagnostics.push(Diagnostic::error("", ast::Span::default(), e));
+        }
+        output.files.clear();
+    }
+    output
+}
+
+/// Parsed and checked sources of a project, with its build-time data
+pub struct Project {
+    pub config: Config,
+    pub document: WebCoreDocument,
+    pub catalogs: Vec<Catalog>,
+    pub theme: Option<Theme>,
+    pub data: DataSet,
+    pub content: Vec<ContentEntry>,
+}
+
+/// Reads, parses and checks everything the build needs. Diagnostics are
+/// pushed as they are found; any error among them fails the load
+pub fn load(input: &ProjectInput, options: &BuildOptions, diagnostics: &mut Vec<Diagnostic>) -> Result<Project, String> {
+    let mut config = read_config(input)?;
+    diagnostics.append(&mut config.warnings);
+    if let Some(mode) = &options.mode {
+        config.mode = mode.clone();
+    }
+    if let Some(spa) = options.spa {
+        config.spa = spa;
+    }
+    let theme = match input.exists("theme.toml") {
+        true => Some(theme::parse_theme(&input.read_to_string("theme.toml")?)
+            .map_err(|e| format!("Failed to parse theme file theme.toml: {}", e))?),
+        false => None,
+    };
+
+    let document = parse_sources(input, diagnostics);
+    diagnostics.extend(checker::check_document(&document));
+    let catalogs = i18n::load_catalogs(input)?;
+    diagnostics.extend(i18n::check_catalogs(&document, &catalogs));
+    diagnostics.extend(transition::check_directives(&document));
+    diagnostics.extend(a11y::lint(&document, config.declared_lang.as_deref(), config.lang_span, &config.a11y));
+    if checker::has_errors(diagnostics) {
+        let count = diagnostics.iter().filter(|d| d.severity == checker::Severity::Error).count();
+        return Err(format!("{} error(s) found while checking the project", count));
+    }
+
+    // Nesting is checked on the rendered pages, which need the data
+    let (data, content) = load_data(&document, input)?;
+    diagnostics.extend(check_content_slugs(&document, &content));
+    if config.validate_html {
+        diagnostics.extend(html::validate(&document, &data));
+    }
+    if checker::has_errors(diagnostics) {
+        let count = diagnostics.iter().filter(|d| d.severity == checker::Severity::Error).count();
+        return Err(format!("{} error(s) found while checking the project", count));
+    }
+    Ok(Project { config, document, catalogs, theme, data, content })
+}
+
+// app.webc first, then stores, layouts, components and pages; files are read
+// in name order so duplicate definitions are reported consistently
+fn parse_sources(input: &ProjectInput, diagnostics: &mut Vec<Diagnostic>) -> WebCoreDocument {
+    let mut document = WebCoreDocument {
+        app: None,
+        layouts: HashMap::new(),
+        pages: HashMap::new(),
+        components: HashMap::new(),
+        stores: HashMap::new(),
+        global_styles: Vec::new(),
+    };
+    let mut files = vec!["src/app.webc"];
+    for dir in ["src/stores", "src/layouts", "src/components", "src/pages"] {
+        files.extend(input.files_in(dir)
+            .map(|(path, _)| path)
+            .filter(|path| path.ends_with(".webc") && !path[dir.len() + 1..].contains('/')));
+    }
+    for file in files {
+        let Some(bytes) = input.read(file) else { continue };
+        let source = String::from_utf8_lossy(bytes);
+        match parser::parse_webc_file(&source, file) {
+            Ok(parsed) => checker::merge_definitions(&mut document, parsed, diagnostics),
+            Err(e) => diagnostics.push(checker::parse_error(file, &e)),
+        }
+    }
+    document
+}
+
+// Build-time data: data files, then content collections under their `collection:` keys
+pub(crate) fn load_data(document: &WebCoreDocument, input: &ProjectInput) -> Result<(DataSet, Vec<ContentEntry>), String> {
+    let content_entries = content::load_collections(input, "src/content")?;
+    let mut data_set = data::load_document_data(document, input)?;
+    for source in data::data_sources(document) {
+        if source.kind == ast::DataSourceKind::Collection {
+            if !source.path.is_empty() && !content_entries.iter().any(|e| e.collection == source.path) {
+                return Err(format!("Content collection '{}' not found in src/content", source.path));
+            }
+            data_set.insert(source.key(), content::collection_value(&content_entries, &source.path));
+        }
+    }
+    Ok((data_set, content_entries))
+}
+
+// A Markdown entry writes `<slug>.html`, which a page, a `*Page` component
+// or another entry with the same slug writes too
+fn check_content_slugs(document: &WebCoreDocument, content: &[ContentEntry]) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    let mut seen: HashMap<&str, &ContentEntry> = HashMap::new();
+    for entry in content {
+        if let Some(first) = seen.insert(&entry.slug, entry) {
+            diagnostics.push(Diagnostic::error(&entry.file, ast::Span::default(),
+                format!("'{}' is also the slug of {}; both would be written to {}", entry.slug, first.file, entry.output_file())));
+        }
+        let page = document.pages.get(&entry.slug).map(|page| (&page.file, page.span));
+        let component = document.components.get(&entry.slug).filter(|c| c.name.ends_with("Page")).map(|c| (&c.file, c.span));
+        if let Some((file, span)) = page.or(component) {
+            diagnostics.push(Diagnostic::error(file, span,
+                format!("'{}' is also the slug of {}; both would be written to {}", entry.slug, entry.file, entry.output_file())));
+        }
+    }
+    diagnostics
+}
+
+fn build_pages(project: &Project, input: &ProjectInput, output: &mut BuildOutput) -> Result<(), String> {
+    let Project { config, document, catalogs, theme, data, content } = project;
+
+    // theme.css is written below, only when the project has a theme. Global
+    // styles are passed through verbatim into their own stylesheet
+    let mut stylesheets = Vec::new();
+    if theme.is_some() {
+        stylesheets.push("theme.css".to_string());
+    }
+    if !document.global_styles.is_empty() {
+        let mut css = String::new();
+        for block in &document.global_styles {
+            css.push_str("/* webcore:raw style global */\n");
+            css.push_str(&block.content);
+            css.push_str("\n/* /webcore:raw */\n");
+        }
+        output.write("global.css", css);
+        stylesheets.push("global.css".to_string());
+    }
+    let component_styles = ir::lower_styles(document, theme.as_ref());
+    if !component_styles.is_empty() {
+        output.write("components.css", codegen_css::generate_component_css(&component_styles));
+        stylesheets.push("components.css".to_string());
+    }
+    if let Some(css) = transition::generate_css(document) {
+        output.write("transitions.css", css);
+        stylesheets.push("transitions.css".to_string());
+    }
+
+    let global = assets::resolve(&config.global, input, &mut output.files)?;
+    let base_options = codegen_html::HtmlPageOptions {
+        lang: config.app_lang.clone(),
+        title: config.app_title.clone(),
+        base_path: String::new(),
+        stylesheets,
+        csp: config.csp,
+        spa: config.spa,
+        global,
+        ..Default::default()
+    };
+
+    output.raw_blocks = document.global_styles.iter()
+        .map(|b| ("global.css".to_string(), b.clone()))
+        .collect();
+
+    // One output tree per locale (fr/, en/), or the root itself without catalogs.
+    // Root-absolute links follow the site when it is served below a path
+    let site_root = site_path(config.site_url.as_deref());
+    let locales: Vec<String> = catalogs.iter().map(|c| c.lang.clone()).collect();
+    let routes = document.app.as_ref().map(|app| app.routes.as_slice()).unwrap_or_default();
+    let mut sitemap_paths = Vec::new();
+    let passes: Vec<Option<&Catalog>> = if catalogs.is_empty() { vec![None] } else { catalogs.iter().map(Some).collect() };
+    for catalog in passes {
+        let (out_dir, options) = match catalog {
+            Some(catalog) => {
+                let options = codegen_html::HtmlPageOptions {
+                    lang: catalog.lang.clone(),
+                    base_path: "../".to_string(),
+                    root: Some(format!("{}{}/", site_root, catalog.lang)),
+                    catalog: Some(catalog.clone()),
+                    ..base_options.clone()
+                };
+                (format!("{}/", catalog.lang), options)
+            }
+            None => {
+                let root = (site_root != "/").then(|| site_root.clone());
+                (String::new(), codegen_html::HtmlPageOptions { root, ..base_options.clone() })
+            }
+        };
+        let pages = Pages { document, content_entries: content, data_set: data, locales: &locales, site_url: config.site_url.as_deref(), out_dir: &out_dir };
+        // The router swaps pages in under the policy of the first one loaded, so
+        // every page of the tree gets one allowing what any of them needs
+        let options = match options.spa && options.csp {
+            true => {
+                let mut sources = CspBuilder::new();
+                let mut scratch = BuildOutput::default();
+                pages.generate(&options, &mut scratch, &mut sources)?;
+                pages.generate_routes(routes, &options, &mut scratch, &mut sources)?;
+                codegen_html::HtmlPageOptions { policy: Some(sources.build()), ..options }
+            }
+            false => options,
+        };
+        let raw_blocks = pages.generate(&options, output, &mut CspBuilder::new())?;
+        // Every locale renders the same raw blocks; report them once
+        if catalog.is_none_or(|c| c.lang == locales[0]) {
+            output.raw_blocks.extend(raw_blocks);
+        }
+        output.write(format!("{}index.html", out_dir), generate_index_html(document, content, &options));
+        pages.generate_routes(routes, &options, output, &mut CspBuilder::new())?;
+        sitemap_paths.extend(pages.sitemap_paths(routes, options.spa));
+    }
+    if !catalogs.is_empty() {
+        let default_locale = if locales.contains(&config.app_lang) { config.app_lang.clone() } else { locales[0].clone() };
+        output.write("index.html", locale_redirect(&default_locale));
+    }
+
+    if let Some(theme) = theme {
+        let mut css_variables = codegen_css::generate_theme_css(theme);
+        if !content.is_empty() {
+            css_variables.push_str(&codegen_css::generate_content_css());
+        }
+        let processed_css = if config.mode == "prod" {
+            css_processor::minify_css(&css_variables)?
+        } else {
+            css_processor::format_css(&css_variables)?
+        };
+        output.write("theme.css", processed_css);
+    }
+
+    // Runtime, then the shared stores; handlers and component state are
+    // declared by each page. Every page loads it, with or without a theme
+    let mut runtime_js = codegen_js::generate_runtime_js(config.spa);
+    let mut stores: Vec<&ast::Store> = document.stores.values().collect();
+    stores.sort_by(|a, b| a.name.cmp(&b.name));
+    for store in stores {
+        runtime_js.push_str(&codegen_js::generate_store_js(store));
+    }
+    output.write("webcore.js", runtime_js);
+
+    if let Some(url) = &config.site_url {
+        output.write("sitemap.xml", seo::sitemap(url, &sitemap_paths, &config.sitemap)?);
+        for path in seo::unmatched_overrides(&sitemap_paths, &config.sitemap) {
+            output.diagnostics.push(Diagnostic::warning("webc.toml", ast::Span::default(),
+                format!("[sitemap.\"{}\"] names no page of the site, so it is ignored", path)));
+        }
+    }
+    if config.site_url.is_some() || config.robots.is_some() {
+        output.write("robots.txt", seo::robots(config.robots.as_ref().unwrap_or(&seo::RobotsSection::default()), config.site_url.as_deref()));
+    }
+
+    // Public assets are copied last and win over generated files
+    for (path, contents) in input.files_in("public") {
+        output.write(&path["public/".len()..], contents);
+    }
+    Ok(())
+}
+
+// One ES module per component under elements/; components the target cannot
+// express are left out with a warning instead of failing the build
+fn build_elements(project: &Project, output: &mut BuildOutput) -> Result<(), String> {
+    let mut components: Vec<&ast::Component> = project.document.components.values().collect();
+    components.sort_by(|a, b| a.name.cmp(&b.name));
+    let mut built = Vec::new();
+    for component in components {
+        match codegen_elements::generate_element(&project.document, component, &project.data, project.theme.as_ref()) {
+            Ok(js) => {
+                output.write(format!("elements/{}.js", ir::tag_name(&component.name)), js);
+                built.push(component);
+            }
+            Err(e) => output.diagnostics.push(Diagnostic::warning(
+                &component.file,
+                component.span,
+                format!("{} is not built as a custom element: {}", component.name, e),
+            )),
+        }
+    }
+    output.write("elements/index.js", codegen_elements::generate_index(&built));
+    Ok(())
+}
+
+// Everything one output tree (the root or a locale directory) is made of
+struct Pages<'a> {
+    document: &'a WebCoreDocument,
+    content_entries: &'a [ContentEntry],
+    data_set: &'a DataSet,
+    locales: &'a [String],
+    site_url: Option<&'a str>,
+    out_dir: &'a str,
+}
+
+impl Pages<'_> {
+    // Pages, component pages, Markdown entries and the fallback index; `sources`
+    // collects what their policies allow
+    fn generate(&self, options: &codegen_html::HtmlPageOptions, output: &mut BuildOutput, sources: &mut CspBuilder) -> Result<RawReport, String> {
+        let document = self.document;
+        let mut raw_report = Vec::new();
+        let mut render = |document: &WebCoreDocument, name: &str, file: &str, options: &codegen_html::HtmlPageOptions| -> Result<(), String> {
+            let options = self.page_options(options, file);
+            let html_result = codegen_html::generate_html(document, name, &options, self.data_set)?;
+            raw_report.extend(html_result.raw_blocks.into_iter().map(|b| (name.to_string(), b)));
+            sources.merge(&html_result.csp.unwrap_or_default());
+            output.write(format!("{}{}", self.out_dir, file), html_result.html);
+            Ok(())
+        };
+
+        for page_name in document.pages.keys() {
+            render(document, page_name, &format!("{}.html", page_name), options)?;
+        }
+
+        // Components named `*Page` are rendered as pages of their own
+        for component in document.components.values().filter(|c| c.name.ends_with("Page")) {
+            let mut temp_doc = document.clone();
+            temp_doc.pages.insert(component.name.clone(), component_page(component));
+            render(&temp_doc, &component.name, &format!("{}.html", component.name), options)?;
+        }
+
+        // Markdown content entries, wrapped in their layout
+        for entry in self.content_entries {
+            let mut temp_doc = document.clone();
+            temp_doc.pages.insert(entry.slug.clone(), entry.to_page());
+            let options = codegen_html::HtmlPageOptions {
+                title: entry.title.clone().unwrap_or_else(|| options.title.clone()),
+                base_path: format!("{}{}", options.base_path, "../".repeat(entry.slug.matches('/').count())),
+                ..options.clone()
+            };
+            render(&temp_doc, &entry.slug, &entry.output_file(), &options)
+                .map_err(|e| format!("{} (in src/content/{}.md)", e, entry.slug))?;
+        }
+
+        // Without any page, a default one keeps the output browsable
+        if document.pages.is_empty() && self.content_entries.is_empty() && !document.components.keys().any(|name| name.ends_with("Page")) {
+            let mut temp_doc = document.clone();
+            temp_doc.pages.insert("index".to_string(), default_page());
+            render(&temp_doc, "index", "index.html", options)?;
+        }
+
+        Ok(raw_report)
+    }
+
+    // With the router every route is also prerendered where the browser asks for
+    // it (`/blog` → blog/index.html), so any of them can be the entry point.
+    // Without it only the 404 route is, the other pages having their own files
+    fn generate_routes(&self, routes: &[ast::Route], options: &codegen_html::HtmlPageOptions, output: &mut BuildOutput, sources: &mut CspBuilder) -> Result<(), String> {
+        for route in routes {
+            let not_found = route.path.trim_matches('/') == NOT_FOUND;
+            let Some(file) = route_file(&route.path).filter(|_| options.spa || not_found) else { continue };
+            // Hosts serve 404.html for a missing page at any depth
+            let base_path = match not_found {
+                true => site_path(self.site_url),
+                false => format!("{}{}", options.base_path, "../".repeat(file.matches('/').count())),
+            };
+            let options = codegen_html::HtmlPageOptions { base_path, ..options.clone() };
+            let options = self.page_options(&options, &file);
+            let component = self.document.components.get(&route.target).filter(|_| !self.document.pages.contains_key(&route.target));
+            let html_result = match component {
+                Some(component) => {
+                    let mut temp_doc = self.document.clone();
+                    temp_doc.pages.insert(component.name.clone(), component_page(component));
+                    codegen_html::generate_html(&temp_doc, &route.target, &options, self.data_set)?
+                }
+                None => codegen_html::generate_html(self.document, &route.target, &options, self.data_set)?,
+            };
+            sources.merge(&html_result.csp.unwrap_or_default());
+            output.write(format!("{}{}", self.out_dir, file), html_result.html);
+        }
+        Ok(())
+    }
+
+    // `hreflang` alternates point at the same file in every locale tree, by
+    // its full URL when `[site] url` gives one
+    fn page_options(&self, options: &codegen_html::HtmlPageOptions, file: &str) -> codegen_html::HtmlPageOptions {
+        let origin = self.site_url.map(|url| url.trim_end_matches('/')).unwrap_or_default();
+        let alternates = self.locales.iter()
+            .map(|lang| (lang.clone(), format!("{}/{}/{}", origin, lang, file)))
+            .collect();
+        codegen_html::HtmlPageOptions { alternates, ..options.clone() }
+    }
+
+    // Site paths of this tree for sitemap.xml: its routes with the router,
+    // otherwise its pages except the 404 one, plus the Markdown entries either way
+    fn sitemap_paths(&self, routes: &[ast::Route], spa: bool) -> Vec<String> {
+        let prefix = format!("/{}", self.out_dir);
+        let mut paths: Vec<String> = if !spa || routes.is_empty() {
+            let not_found = routes.iter().find(|route| route.path.trim_matches('/') == NOT_FOUND).map(|route| route.target.as_str());
+            let pages = self.document.pages.keys().cloned();
+            let component_pages = self.document.components.keys().filter(|name| name.ends_with("Page")).cloned();
+            pages.chain(component_pages)
+                .filter(|name| Some(name.as_str()) != not_found)
+                .map(|name| format!("{}{}.html", prefix, name))
+                .collect()
+        } else {
+            routes.iter()
+                .filter(|route| route_file(&route.path).is_some() && route.path.trim_matches('/') != NOT_FOUND)
+                .map(|route| format!("{}{}", prefix, route.path.trim_start_matches('/')))
+                .collect()
+        };
+        paths.extend(self.content_entries.iter().map(|entry| format!("{}{}", prefix, entry.output_file())));
+        paths.sort();
+        paths.dedup();
+        paths
+    }
+}
+
+// `/` → index.html, `/blog/` → blog/index.html, `404` → 404.html, which static
+// hosts serve for missing pages; a path with parameters has no single file
+fn route_file(path: &str) -> Option<String> {
+    if path.contains([':', '*']) {
+        return None;
+    }
+    match path.trim_matches('/') {
+        "" => Some("index.html".to_string()),
+        NOT_FOUND => Some(format!("{}.html", NOT_FOUND)),
+        path => Some(format!("{}/index.html", path)),
+    }
+}
+
+const NOT_FOUND: &str = "404";
+
+fn default_page() -> ast::Page {
+    let paragraph = |tag: &str, text: &str| ast::Element::Tag {
+        name: tag.to_string(),
+        attributes: vec![],
+        content: vec![ast::Element::Text { text: text.to_string(), span: ast::Span::default() }],
+        span: ast::Span::default(),
+    };
+    ast::Page {
+        name: "index".to_string(),
+        layout: None,
+        data: Vec::new(),
+        content: vec![paragraph("h1", "Welcome to WebCore"), paragraph("p", "This is a default page.")],
+        file: String::new(),
+        span: ast::Span::default(),
+    }
+}
+
+/// A temporary page rendering one instance of a `*Page` component
+pub fn component_page(component: &ast::Component) -> ast::Page {
+    ast::Page {
+        name: component.name.clone(),
+        layout: component.layout.clone(),
+        data: Vec::new(),
+        content: vec![ast::Element::Component {
+            name: component.name.clone(),
+            attributes: Vec::new(),
+            content: Vec::new(),
+            span: component.span,
+        }],
+        file: component.file.clone(),
+        span: component.span,
+    }
+}
+
+// `https://example.com/docs` → `/docs/`, the path the site is served under
+fn site_path(site_url: Option<&str>) -> String {
+    let path = site_url
+        .and_then(|url| url.split_once("://"))
+        .and_then(|(_, rest)| rest.split_once('/'))
+        .map(|(_, path)| path.trim_matches('/'))
+        .unwrap_or_default();
+    match path {
+        "" => "/".to_string(),
+        path => format!("/{}/", path),
+    }
+}
+
+fn locale_redirect(lang: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html lang=\"{0}\">\n<head>\n  <meta charset=\"UTF-8\">\n  <meta http-equiv=\"refresh\" content=\"0; url={0}/index.html\">\n  <title>{0}</title>\n</head>\n<body>\n  <a href=\"{0}/index.html\">{0}</a>\n</body>\n</html>\n",
+        lang
+    )
+}
+
+fn generate_index_html(document: &WebCoreDocument, content_entries: &[ContentEntry], options: &codegen_html::HtmlPageOptions) -> String {
+    let mut links: Vec<(String, String)> = Vec::new();
+    for page_name in document.pages.keys() {
+        links.push((format!("{}.html", page_name), page_name.clone()));
+    }
+    for component_name in document.components.keys() {
+        if component_name.ends_with("Page") {
+            links.push((format!("{}.html", component_name), component_name.clone()));
+        }
+    }
+    for entry in content_entries {
+        links.push((entry.output_file(), entry.title.clone().unwrap_or_else(|| entry.slug.clone())));
+    }
+    links.sort_by(|a, b| a.1.cmp(&b.1));
+
+    let mut html = String::new();
+    html.push_str(&format!("<!DOCTYPE html>\n<html lang=\"{}\">\n<head>\n", options.lang));
+    html.push_str("  <meta charset=\"UTF-8\">\n");
+    html.push_str("  <meta name=\"viewport\" content=\"width=device-width, initial-scale=1.0\">\n");
+    html.push_str("  <title>Index</title>\n");
+    for stylesheet in &options.stylesheets {
+        html.push_str(&format!("  <link rel=\"stylesheet\" href=\"{}{}\">\n", options.base_path, codegen_html::html_escape(stylesheet)));
+    }
+    html.push_str("</head>\n<body>\n<h1>Pages</h1>\n<ul>\n");
+    for (href, label) in links {
+        html.push_str(&format!("  <li><a href=\"{}\">{}</a></li>\n", codegen_html::html_escape(&href), codegen_html::html_escape(&label)));
+    }
+    html.push_str(&format!("</ul>\n<script src=\"{}webcore.js\"></script>\n</body>\n</html>\n", options.base_path));
+    html
+}
+
+pub(crate) fn read_config(input: &ProjectInput) -> Result<Config, String> {
+    if !input.exists("webc.toml") {
+        return Err("webc.toml not found".to_string());
+    }
+    let content = input.read_to_string("webc.toml")?;
+
+    let parsed: WebcToml = toml::from_str(&content).map_err(|e| format!("Failed to parse webc.toml: {}", e))?;
+    let app_title = parsed.app.as_ref().and_then(|a| a.title.clone()).unwrap_or_else(|| "WebCore App".to_string());
+    let declared_lang = parsed.app.as_ref().and_then(|a| a.lang.as_ref()).map(|l| l.get_ref().clone());
+    let app_lang = declared_lang.clone().unwrap_or_else(|| "fr".to_string());
+    // The `lang` key, or the [app] header it belongs under
+    let lang_range = match parsed.app.as_ref().and_then(|a| a.lang.as_ref()) {
+        Some(lang) => Some(key_range(&content, lang.span())),
+        None => content.split_inclusive('\n').scan(0, |start, line| {
+            let at = *start;
+            *start += line.len();
+            Some((at, line))
+        }).find(|(_, line)| line.trim() == "[app]").map(|(at, line)| at..at + line.trim_end().len()),
+    };
+    let lang_span = lang_range.map(|r| toml_span(&content, r)).unwrap_or_default();
+    let mut warnings = Vec::new();
+    let declared_mode = parsed.app.as_ref().and_then(|a| a.mode.as_ref());
+    if let Some(mode) = declared_mode.filter(|m| m.get_ref() == "spa") {
+        warnings.push(Diagnostic::warning("webc.toml", toml_span(&content, key_range(&content, mode.span())),
+            "`mode = \"spa\"` does not turn on the router, set `router = \"spa\"` in [app]; mode is \"dev\" or \"prod\"".to_string()));
+    }
+    let mode = declared_mode.map(|m| m.get_ref().clone()).unwrap_or_else(|| "dev".to_string());
+    let spa = match parsed.app.as_ref().and_then(|a| a.router.as_deref()) {
+        None => false,
+        Some("spa") => true,
+        Some(other) => return Err(format!("Failed to parse webc.toml: [app] router must be \"spa\", not \"{}\"", other)),
+    };
+    let csp = parsed.security.as_ref().and_then(|s| s.csp).unwrap_or(false);
+    let a11y = a11y::Rules::from_config(&parsed.a11y.unwrap_or_default())?;
+    let validate_html = parsed.html.as_ref().and_then(|h| h.validate).unwrap_or(false);
+    if !parsed.sitemap.is_empty() && parsed.site.as_ref().and_then(|s| s.url.as_ref()).is_none() {
+        return Err("[sitemap] entries need the site URL: set `url` under [site] in webc.toml".to_string());
+    }
+
+    Ok(Config {
+        app_title,
+        app_lang,
+        declared_lang,
+        lang_span,
+        warnings,
+        mode,
+        spa,
+        csp,
+        a11y,
+        validate_html,
+        global: parsed.global,
+        site_url: parsed.site.and_then(|s| s.url),
+        robots: parsed.robots,
+        sitemap: parsed.sitemap,
+    })
+}
+
+// From the start of the key to the end of its value
+fn key_range(content: &str, value: std::ops::Range<usize>) -> std::ops::Range<usize> {
+    let line_start = content[..value.start].rfind('\n').map(|i| i + 1).unwrap_or(0);
+    let indent = content[line_start..].len() - content[line_start..].trim_start().len();
+    line_start + indent..value.end
+}
+
+fn toml_span(content: &str, range: std::ops::Range<usize>) -> ast::Span {
+    let before = &content[..range.start];
+    let line_start = before.rfind('\n').map(|i| i + 1).unwrap_or(0);
+    ast::Span { start: range.start, end: range.end, line: before.matches('\n').count() + 1, column: before[line_start..].chars().count() + 1 }
+}
+
+#[derive(Debug)]
+pub struct Config {
+    pub app_title: String,
+    pub app_lang: String,
+    /// `lang` as written in webc.toml; `app_lang` falls back to "fr" without it
+    pub declared_lang: Option<String>,
+    /// Where `lang` is, or should be, in webc.toml
+    pub lang_span: ast::Span,
+    /// Settings that are read but probably not what was meant
+    pub warnings: Vec<Diagnostic>,
+    pub mode: String,
+    /// `[app] router = "spa"`: pages are swapped by the history router
+    pub spa: bool,
+    pub csp: bool,
+    pub a11y: a11y::Rules,
+    /// `[html] validate`: report invalid element nesting
+    pub validate_html: bool,
+    /// `[global]` tags added to every page
+    pub global: assets::GlobalSection,
+    /// `[site] url`, which sitemap.xml needs
+    pub site_url: Option<String>,
+    pub robots: Option<seo::RobotsSection>,
+    /// `[sitemap."/path"]` overrides
+    pub sitemap: BTreeMap<String, seo::SitemapEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+struct WebcToml {
+    app: Option<AppSection>,
+    security: Option<SecuritySection>,
+    /// Severity per a11y rule: "error", "warning" or "off"
+    a11y: Option<HashMap<String, String>>,
+    html: Option<HtmlSection>,
+    #[serde(default)]
+    global: assets::GlobalSection,
+    site: Option<SiteSection>,
+    robots: Option<seo::RobotsSection>,
+    #[serde(default)]
+    sitemap: BTreeMap<String, seo::SitemapEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SiteSection {
+    url: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct HtmlSection {
+    validate: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SecuritySection {
+    csp: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AppSection {
+    title: Option<String>,
+    lang: Option<toml::Spanned<String>>,
+    mode: Option<toml::Spanned<String>>,
+    router: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(output: &BuildOutput, name: &str) -> String {
+        String::from_utf8(output.files[name].clone()).unwrap()
+    }
+
+    #[test]
+    fn compiles_an_in_memory_project_to_output_files() {
+        let mut input = ProjectInput::new();
+        input.insert("webc.toml", "[app]\nlang = \"en\"\n");
+        input.insert("src/pages/home.webc", "layout MainLayout { slot }\npage \"home\" {\n  data site = load(\"./data/site.json\")\n  h1 \"{site.tagline}\"\n}\n");
+        input.insert("data/site.json", "{\"tagline\": \"Hello\"}");
+        input.insert("public/robots.txt", "User-agent: *\n");
+        let output = compile(&input, &BuildOptions::default());
+        assert!(!output.has_errors(), "{:?}", output.diagnostics);
+        let names: Vec<&str> = output.files.keys().map(String::as_str).collect();
+        // The runtime does not depend on a theme
+        assert_eq!(names, vec!["home.html", "index.html", "robots.txt", "webcore.js"]);
+        assert!(file(&output, "home.html").contains("<h1>Hello</h1>"));
+        assert_eq!(output.data_files, vec!["./data/site.json"]);
+    }
+
+    #[test]
+    fn spa_router_prerenders_every_route_around_an_outlet() {
+        let mut input = ProjectInput::new();
+        input.insert("webc.toml", "[app]\nrouter = \"spa\"\n");
+        input.insert("src/app.webc", "app Demo {\n  routes {\n    \"/\": home\n    \"/docs/intro\": IntroPage\n    \"/posts/:id\": home\n  }\n}\n");
+        input.insert("src/pages/home.webc", "layout MainLayout { main { slot } }\npage \"home\" { h1 \"Home\" }\ncomponent IntroPage { view { h1 { \"Intro\" } } }\n");
+        let output = compile(&input, &BuildOptions::default());
+        assert!(!output.has_errors(), "{:?}", output.diagnostics);
+        assert!(file(&output, "index.html").contains("<main><!--webcore:outlet--><h1>Home</h1><!--/webcore:outlet--></main>"));
+        let intro = file(&output, "docs/intro/index.html");
+        assert!(intro.contains("<!--webcore:outlet--><h1>Intro</h1><!--/webcore:outlet-->"), "{}", intro);
+        assert!(intro.contains("<script src=\"../../webcore.js\"></script>"));
+        // Routes with parameters have no single page to prerender
+        assert!(!output.files.keys().any(|name| name.starts_with("posts")));
+        assert!(file(&output, "webcore.js").contains("function startRouter()"));
+    }
+
+    #[test]
+    fn build_options_can_turn_the_router_off() {
+        let mut input = ProjectInput::new();
+        input.insert("webc.toml", "[app]\nrouter = \"spa\"\n");
+        input.insert("src/app.webc", "app Demo {\n  routes {\n    \"/intro\": IntroPage\n  }\n}\n");
+        input.insert("src/pages/intro.webc", "layout MainLayout { slot }\ncomponent IntroPage { view { h1 { \"Intro\" } } }\n");
+        let output = compile(&input, &BuildOptions { spa: Some(false), ..Default::default() });
+        assert!(!output.has_errors(), "{:?}", output.diagnostics);
+        assert!(!file(&output, "IntroPage.html").contains("webcore:outlet"));
+        assert!(!output.files.contains_key("intro/index.html"));
+        assert!(!file(&output, "webcore.js").contains("function startRouter()"));
+    }
+
+    #[test]
+    fn router_must_be_spa() {
+        let mut input = ProjectInput::new();
+        input.insert("webc.toml", "[app]\nrouter = \"hash\"\n");
+        input.insert("src/pages/home.webc", "layout MainLayout { slot }\npage \"home\" { h1 \"Home\" }\n");
+        let output = compile(&input, &BuildOptions::default());
+        assert!(output.diagnostics.iter().any(|d| d.message.contains("[app] router must be \"spa\", not \"hash\"")), "{:?}", output.diagnostics);
+    }
+
+    #[test]
+    fn spa_pages_share_one_policy_so_the_router_can_swap_them() {
+        let mut input = ProjectInput::new();
+        input.insert("webc.toml", "[app]\nrouter = \"spa\"\n\n[security]\ncsp = true\n");
+        input.insert("src/app.webc", "app Demo {\n  routes {\n    \"/a\": APage\n    \"/b\": BPage\n  }\n}\n");
+        input.insert("src/pages/pages.webc", "layout MainLayout { slot }\ncomponent APage {\n  state { n: Number = 0 }\n  view { button on:click={n = n + 1} \"A\"\n p style=\"color: red\" \"a\" }\n}\ncomponent BPage {\n  state { n: Number = 0 }\n  view { button on:click={n = n - 1} \"B\" }\n}\n");
+        let policy = |output: &BuildOutput, name: &str| {
+            let html = file(output, name);
+            let marker = "Content-Security-Policy\" content=\"";
+            let start = html.find(marker).expect("policy") + marker.len();
+            html[start..start + html[start..].find('"').unwrap()].to_string()
+        };
+        let output = compile(&input, &BuildOptions::default());
+        assert!(!output.has_errors(), "{:?}", output.diagnostics);
+        let shared = policy(&output, "a/index.html");
+        assert_eq!(shared.matches("sha256-").count(), 3, "{}", shared);
+        assert!(shared.replace("&#x27;", "'").contains(&format!("'unsafe-hashes' {}", crate::security::hash_source("color: red"))), "{}", shared);
+        assert_eq!(policy(&output, "b/index.html"), shared);
+
+        // Without the router each page only allows its own handlers
+        let output = compile(&input, &BuildOptions { spa: Some(false), ..Default::default() });
+        assert_eq!(policy(&output, "BPage.html").matches("sha256-").count(), 1);
+    }
+
+    #[test]
+    fn mode_spa_warns_that_the_router_is_set_elsewhere() {
+        let mut input = ProjectInput::new();
+        input.insert("webc.toml", "[app]\nlang = \"en\"\nmode = \"spa\"\n");
+        input.insert("src/pages/home.webc", "layout MainLayout { slot }\npage \"home\" { h1 \"Home\" }\n");
+        let output = compile(&input, &BuildOptions::default());
+        assert!(!output.has_errors(), "{:?}", output.diagnostics);
+        let warning = &output.diagnostics[0];
+        assert_eq!((warning.file.as_str(), warning.span.line, warning.span.column), ("webc.toml", 3, 1));
+        assert!(warning.message.starts_with("`mode = \"spa\"` does not turn on the router"), "{}", warning.message);
+    }
+
+    #[test]
+    fn sitemap_lists_the_routes_and_warns_on_unknown_overrides() {
+        let mut input = ProjectInput::new();
+        input.insert("webc.toml", "[app]\nlang = \"en\"\nrouter = \"spa\"\n\n[site]\nurl = \"https://example.com\"\n\n[sitemap.\"/about/\"]\npriority = 0.5\n\n[sitemap.\"/abuot\"]\npriority = 0.1\n");
+        input.insert("src/app.webc", "app Demo {\n  routes {\n    \"/\": home\n    \"/about\": AboutPage\n  }\n}\n");
+        input.insert("src/pages/home.webc", "layout MainLayout { slot }\npage \"home\" { h1 \"Home\" }\ncomponent AboutPage { view { h1 { \"About\" } } }\n");
+        let locs = |output: &BuildOutput| -> Vec<String> {
+            file(output, "sitemap.xml").lines().filter_map(|l| Some(l.trim().strip_prefix("<loc>")?.strip_suffix("</loc>")?.to_string())).collect()
+        };
+        let output = compile(&input, &BuildOptions::default());
+        assert!(!output.has_errors(), "{:?}", output.diagnostics);
+        let warnings: Vec<&str> = output.diagnostics.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(warnings, vec!["[sitemap.\"/abuot\"] names no page of the site, so it is ignored"]);
+        assert_eq!(locs(&output), vec!["https://example.com/", "https://example.com/about"]);
+        assert!(file(&output, "sitemap.xml").contains("<loc>https://example.com/about</loc>\n    <priority>0.5</priority>"));
+
+        // Without the router the pages keep their own files
+        let output = compile(&input, &BuildOptions { spa: Some(false), ..Default::default() });
+        assert_eq!(locs(&output), vec!["https://example.com/AboutPage.html", "https://example.com/home.html"]);
+    }
+
+    #[test]
+    fn public_files_win_over_the_generated_robots_txt() {
+        let mut input = ProjectInput::new();
+        input.insert("webc.toml", "[app]\nlang = \"en\"\n\n[robots]\ndisallow = [\"/admin\"]\n");
+        input.insert("src/pages/home.webc", "layout MainLayout { slot }\npage \"home\" { h1 \"Home\" }\n");
+        let output = compile(&input, &BuildOptions::default());
+        assert_eq!(file(&output, "robots.txt"), "User-agent: *\nDisallow: /admin\n");
+
+        input.insert("public/robots.txt", "User-agent: *\n");
+        let output = compile(&input, &BuildOptions::default());
+        assert_eq!(file(&output, "robots.txt"), "User-agent: *\n");
+    }
+
+    #[test]
+    fn the_404_route_goes_through_the_layout() {
+        let mut input = ProjectInput::new();
+        input.insert("webc.toml", "[app]\nrouter = \"spa\"\n");
+        input.insert("src/app.webc", "app Demo {\n  routes {\n    \"404\": NotFoundPage\n  }\n}\n");
+        input.insert("src/pages/404.webc", "layout MainLayout { main { slot } }\ncomponent NotFoundPage { view { h1 { \"Not found\" } } }\n");
+        let output = compile(&input, &BuildOptions::default());
+        assert!(!output.has_errors(), "{:?}", output.diagnostics);
+        let not_found = file(&output, "404.html");
+        assert!(not_found.contains("<main><!--webcore:outlet--><h1>Not found</h1><!--/webcore:outlet--></main>"));
+        // Served for any missing path, so assets are found from any depth
+        assert!(not_found.contains("<script src=\"/webcore.js\"></script>"), "{}", not_found);
+
+        let output = compile(&input, &BuildOptions { spa: Some(false), ..Default::default() });
+        assert!(file(&output, "404.html").contains("<main><h1>Not found</h1></main>"));
+    }
+
+    #[test]
+    fn locale_trees_link_within_their_locale() {
+        let mut input = ProjectInput::new();
+        input.insert("webc.toml", "[app]\nlang = \"en\"\n\n[site]\nurl = \"https://example.com/\"\n");
+        input.insert("src/pages/home.webc", "layout MainLayout { slot }\npage \"home\" { link to=\"/about.html\" t(\"nav.about\") }\n");
+        input.insert("locales/en.toml", "[nav]\nabout = \"About\"\n");
+        input.insert("locales/fr.toml", "[nav]\nabout = \"À propos\"\n");
+        let output = compile(&input, &BuildOptions::default());
+        assert!(!output.has_errors(), "{:?}", output.diagnostics);
+        let home = file(&output, "fr/home.html");
+        assert!(home.contains("<a href=\"/fr/about.html\">À propos</a>"), "{}", home);
+        assert!(home.contains("<link rel=\"alternate\" hreflang=\"en\" href=\"https://example.com/en/home.html\">"), "{}", home);
+    }
+
+    #[test]
+    fn content_links_follow_the_site_path() {
+        let mut input = ProjectInput::new();
+        input.insert("webc.toml", "[app]\nlang = \"en\"\n\n[site]\nurl = \"https://example.com/docs\"\n");
+        input.insert("src/pages/home.webc", "layout MainLayout { slot }\npage \"home\" {\n  data posts = collection(\"blog\")\n  for post in posts { link to=\"{post.url}\" \"{post.title}\" }\n}\n");
+        input.insert("src/content/blog/hello.md", "---\ntitle: Hello\n---\nHi\n");
+        let output = compile(&input, &BuildOptions::default());
+        assert!(!output.has_errors(), "{:?}", output.diagnostics);
+        assert!(file(&output, "home.html").contains("<a href=\"/docs/blog/hello.html\">Hello</a>"));
+    }
+
+    #[test]
+    fn content_slugs_cannot_replace_pages() {
+        let mut input = ProjectInput::new();
+        input.insert("webc.toml", "[app]\nlang = \"en\"\n");
+        input.insert("src/pages/home.webc", "layout MainLayout { slot }\npage \"home\" { h1 \"Home\" }\n");
+        input.insert("src/content/home.md", "# Home\n");
+        let output = compile(&input, &BuildOptions::default());
+        assert_eq!(output.diagnostics[0].file, "src/pages/home.webc");
+        assert_eq!(output.diagnostics[0].message, "'home' is also the slug of src/content/home.md; both would be written to home.html");
+    }
+
+    #[test]
+    fn two_entries_with_one_slug_are_reported_at_the_second() {
+        let mut input = ProjectInput::new();
+        input.insert("webc.toml", "[app]\nlang = \"en\"\n");
+        input.insert("src/layouts/main.webc", "layout MainLayout { slot }\n");
+        input.insert("src/content/a.md", "---\nslug: post\n---\nA\n");
+        input.insert("src/content/b.md", "---\nslug: post\n---\nB\n");
+        let output = compile(&input, &BuildOptions::default());
+        assert_eq!(output.diagnostics[0].file, "src/content/b.md");
+        assert_eq!(output.diagnostics[0].message, "'post' is also the slug of src/content/a.md; both would be written to post.html");
+    }
+
+    #[test]
+    fn index_links_are_escaped() {
+        let mut input = ProjectInput::new();
+        input.insert("webc.toml", "[app]\nlang = \"en\"\n");
+        input.insert("src/layouts/main.webc", "layout MainLayout { slot }\n");
+        input.insert("src/content/post.md", "---\ntitle: \"<script>alert(1)</script>\"\n---\nBody\n");
+        let output = compile(&input, &BuildOptions::default());
+        assert!(!output.has_errors(), "{:?}", output.diagnostics);
+        let index = file(&output, "index.html");
+        assert!(index.contains("<li><a href=\"post.html\">&lt;script&gt;alert(1)&lt;/script&gt;</a></li>"), "{}", index);
+    }
+
+    #[test]
+    fn theme_css_is_linked_only_when_a_theme_is_written() {
+        let mut input = ProjectInput::new();
+        input.insert("webc.toml", "[app]\nlang = \"en\"\n");
+        input.insert("src/pages/home.webc", "layout MainLayout { slot }\npage \"home\" { h1 \"Home\" }\n");
+        let output = compile(&input, &BuildOptions::default());
+        assert!(!output.has_errors(), "{:?}", output.diagnostics);
+        for name in ["home.html", "index.html"] {
+            assert!(!file(&output, name).contains("theme.css"), "{}", name);
+        }
+
+        input.insert("theme.toml", "[theme]\nname = \"t\"\n[theme.colors]\nprimary = \"#00f\"\n[theme.fonts]\n[theme.radius]\n[theme.breakpoints]\n");
+        let output = compile(&input, &BuildOptions::default());
+        assert!(output.files.contains_key("theme.css"));
+        for name in ["home.html", "index.html"] {
+            assert!(file(&output, name).contains("<link rel=\"stylesheet\" href=\"theme.css\">"), "{}", name);
+        }
+    }
+
+    #[test]
+    fn global_tags_from_webc_toml_are_added_to_every_page() {
+        let mut input = ProjectInput::new();
+        input.insert("webc.toml", r##"[app]
+lang = "en"
+
+[global]
+before = ["https://cdn.example.com/lib.js"]
+after = [{ src = "scripts/app.js", defer = true }]
+meta = [{ name = "theme-color", content = "#007acc" }]
+link = [{ rel = "icon", type = "image/png", href = "/favicon.png" }]
+"##);
+        input.insert("src/pages/home.webc", "layout MainLayout { slot }\npage \"home\" { h1 \"Home\" }\n");
+        input.insert("scripts/app.js", "console.log(1)");
+        let output = compile(&input, &BuildOptions::default());
+        assert!(!output.has_errors(), "{:?}", output.diagnostics);
+        let html = file(&output, "home.html");
+        assert!(html.contains("  <meta name=\"theme-color\" content=\"#007acc\">\n"));
+        // Declared order, and root-absolute paths as written
+        assert!(html.contains("  <link rel=\"icon\" type=\"image/png\" href=\"/favicon.png\">\n  <script src=\"https://cdn.example.com/lib.js\"></script>\n</head>"), "{}", html);
+        let app = output.files.keys().find(|name| name.starts_with("assets/app.")).expect("copied script");
+        assert!(html.ends_with(&format!("  <script src=\"{}\" defer></script>\n</body>\n</html>", app)));
+    }
+
+    #[test]
+    fn global_script_origins_are_allowed_by_the_policy() {
+        let mut input = ProjectInput::new();
+        input.insert("webc.toml", "[app]\nlang = \"en\"\n\n[security]\ncsp = true\n\n[global]\nbefore = [\"https://cdn.example.com/lib.js\"]\n");
+        input.insert("src/pages/home.webc", "layout MainLayout { slot }\npage \"home\" { h1 \"Home\" }\n");
+        let output = compile(&input, &BuildOptions::default());
+        assert!(!output.has_errors(), "{:?}", output.diagnostics);
+        assert!(file(&output, "home.html").contains("script-src &#x27;self&#x27; https://cdn.example.com;"));
+    }
+
+    #[test]
+    fn errors_are_diagnostics_and_produce_no_files() {
+        let mut input = ProjectInput::new();
+        input.insert("webc.toml", "[app]\nlang = \"en\"\n");
+        input.insert("src/pages/home.webc", "layout MainLayout { slot }\npage \"home\" { Missing {} }\n");
+        let output = compile(&input, &BuildOptions::default());
+        assert!(output.has_errors());
+        assert!(output.files.is_empty());
+        assert_eq!(output.diagnostics[0].file, "src/pages/home.webc");
+    }
+
+    #[test]
+    fn a_missing_webc_toml_is_reported() {
+        let output = compile(&ProjectInput::new(), &BuildOptions::default());
+        assert_eq!(output.diagnostics[0].message, "webc.toml not found");
+    }
+}
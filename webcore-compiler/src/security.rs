@@ -0,0 +1,226 @@
+//! Content Security Policy generation (tech.md §12)
+//!
+//! Raw escape hatches are the only way third-party code reaches a page, so
+//! the policy is derived from them: external origins are allowed explicitly
+//! and inline code is allowed by hash, never with 'unsafe-inline'. Inline
+//! `style` attributes are allowed by hash too, which needs 'unsafe-hashes'.
+
+use crate::ast::*;
+use base64::Engine;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeSet;
+
+#[derive(Debug, Default)]
+pub struct CspBuilder {
+    script_src: BTreeSet<String>,
+    style_src: BTreeSet<String>,
+    /// Hashes of `style="..."` attribute values
+    style_attributes: BTreeSet<String>,
+    img_src: BTreeSet<String>,
+    frame_src: BTreeSet<String>,
+}
+
+impl CspBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_raw_block(&mut self, block: &RawBlock) {
+        match block.kind {
+            RawKind::Script => {
+                if let Some(src) = &block.src {
+                    if let Some(origin) = external_origin(src) {
+                        self.script_src.insert(origin);
+                    }
+                }
+                if !block.content.is_empty() {
+                    self.script_src.insert(hash_source(&block.content));
+                }
+            }
+            RawKind::Style => {
+                self.style_src.insert(hash_source(&block.content));
+            }
+            RawKind::Html => self.add_raw_html(&block.content),
+        }
+    }
+
+    /// `<script src>` added from webc.toml; only other origins need listing
+    pub fn add_script_src(&mut self, url: &str) {
+        if let Some(origin) = external_origin(url) {
+            self.script_src.insert(origin);
+        }
+    }
+
+    pub fn add_style_src(&mut self, url: &str) {
+        if let Some(origin) = external_origin(url) {
+            self.style_src.insert(origin);
+        }
+    }
+
+    /// Allows what another page's policy allows
+    pub fn merge(&mut self, other: &CspBuilder) {
+        self.script_src.extend(other.script_src.iter().cloned());
+        self.style_src.extend(other.style_src.iter().cloned());
+        self.style_attributes.extend(other.style_attributes.iter().cloned());
+        self.img_src.extend(other.img_src.iter().cloned());
+        self.frame_src.extend(other.frame_src.iter().cloned());
+    }
+
+    /// The value of a `style` attribute in the page
+    pub fn add_style_attribute(&mut self, value: &str) {
+        self.style_attributes.insert(hash_source(value));
+    }
+
+    /// A `<script>` the compiler writes into the page itself
+    pub fn add_inline_script(&mut self, code: &str) {
+        self.script_src.insert(hash_source(code));
+    }
+
+    // Best-effort scan of the tags a raw HTML snippet may pull in
+    fn add_raw_html(&mut self, html: &str) {
+        let lower = html.to_ascii_lowercase();
+        let mut pos = 0;
+        while let Some(start) = lower[pos..].find('<').map(|i| pos + i) {
+            let end = match lower[start..].find('>') {
+                Some(i) => start + i,
+                None => break,
+            };
+            let tag = &html[start + 1..end];
+            let name = tag.split(|c: char| c.is_whitespace() || c == '/').next().unwrap_or("").to_ascii_lowercase();
+            let src = attribute_value(tag, "src");
+            if let Some(style) = attribute_value(tag, "style") {
+                self.add_style_attribute(&style);
+            }
+            match name.as_str() {
+                "script" => match src {
+                    Some(src) => {
+                        if let Some(origin) = external_origin(&src) {
+                            self.script_src.insert(origin);
+                        }
+                    }
+                    None => {
+                        if let Some(close) = lower[end..].find("</script").map(|i| end + i) {
+                            self.script_src.insert(hash_source(&html[end + 1..close]));
+                        }
+                    }
+                },
+                "style" => {
+                    if let Some(close) = lower[end..].find("</style").map(|i| end + i) {
+                        self.style_src.insert(hash_source(&html[end + 1..close]));
+                    }
+                }
+                "img" | "source" | "image" => {
+                    if let Some(origin) = src.as_deref().and_then(external_origin) {
+                        self.img_src.insert(origin);
+                    }
+                }
+                "iframe" | "frame" => {
+                    if let Some(origin) = src.as_deref().and_then(external_origin) {
+                        self.frame_src.insert(origin);
+                    }
+                }
+                _ => {}
+            }
+            pos = end + 1;
+        }
+    }
+
+    pub fn build(&self) -> String {
+        let mut script_src = vec!["'self'".to_string()];
+        script_src.extend(self.script_src.iter().cloned());
+        let mut style_src = vec!["'self'".to_string()];
+        style_src.extend(self.style_src.iter().cloned());
+        if !self.style_attributes.is_empty() {
+            style_src.push("'unsafe-hashes'".to_string());
+            style_src.extend(self.style_attributes.iter().cloned());
+        }
+        let mut img_src = vec!["'self'".to_string(), "data:".to_string()];
+        img_src.extend(self.img_src.iter().cloned());
+
+        let mut directives = vec![
+            "default-src 'self'".to_string(),
+            format!("script-src {}", script_src.join(" ")),
+            format!("style-src {}", style_src.join(" ")),
+            format!("img-src {}", img_src.join(" ")),
+        ];
+        if self.frame_src.is_empty() {
+            directives.push("frame-src 'none'".to_string());
+        } else {
+            directives.push(format!("frame-src {}", self.frame_src.iter().cloned().collect::<Vec<_>>().join(" ")));
+        }
+        directives.push("object-src 'none'".to_string());
+        directives.push("base-uri 'self'".to_string());
+        directives.join("; ")
+    }
+}
+
+pub fn hash_source(code: &str) -> String {
+    let digest = Sha256::digest(code.as_bytes());
+    format!("'sha256-{}'", base64::engine::general_purpose::STANDARD.encode(digest))
+}
+
+/// `https://cdn.example.com/x.js` → `https://cdn.example.com`; relative URLs are same-origin
+fn external_origin(url: &str) -> Option<String> {
+    let url = url.trim();
+    let (scheme, rest) = if let Some(rest) = url.strip_prefix("https://") {
+        ("https://", rest)
+    } else if let Some(rest) = url.strip_prefix("http://") {
+        ("http://", rest)
+    } else if let Some(rest) = url.strip_prefix("//") {
+        ("", rest)
+    } else {
+        return None;
+    };
+    let host = rest.split(['/', '?', '#']).next().unwrap_or("");
+    if host.is_empty() { None } else { Some(format!("{}{}", scheme, host)) }
+}
+
+fn attribute_value(tag: &str, name: &str) -> Option<String> {
+    let lower = tag.to_ascii_lowercase();
+    let mut search = 0;
+    while let Some(i) = lower[search..].find(name).map(|i| search + i) {
+        search = i + name.len();
+        let before_ok = i == 0 || lower.as_bytes()[i - 1].is_ascii_whitespace();
+        let rest = tag[search..].trim_start();
+        if !before_ok || !rest.starts_with('=') {
+            continue;
+        }
+        let value = rest[1..].trim_start();
+        return match value.chars().next() {
+            Some(q @ ('"' | '\'')) => value[1..].split(q).next().map(|v| v.to_string()),
+            _ => value.split(|c: char| c.is_whitespace()).next().map(|v| v.to_string()),
+        };
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn policy_allows_raw_origins_and_hashes_inline_code() {
+        let mut csp = CspBuilder::new();
+        csp.add_raw_block(&RawBlock {
+            kind: RawKind::Script,
+            content: String::new(),
+            src: Some("https://cdn.example.com/widget.js".to_string()),
+            attributes: Vec::new(),
+            span: Span::default(),
+        });
+        csp.add_raw_block(&RawBlock {
+            kind: RawKind::Html,
+            content: "<iframe src=\"https://maps.example.org/embed?x=1\"></iframe><script>track()</script><p style=\"color: red\">!</p>".to_string(),
+            src: None,
+            attributes: Vec::new(),
+            span: Span::default(),
+        });
+        let policy = csp.build();
+        assert!(policy.contains("script-src 'self' 'sha256-"));
+        assert!(policy.contains("https://cdn.example.com"));
+        assert!(policy.contains(&hash_source("track()")));
+        assert!(policy.contains("frame-src https://maps.example.org"));
+        assert!(policy.contains(&format!("style-src 'self' 'unsafe-hashes' {};", hash_source("color: red"))), "{}", policy);
+        assert!(!policy.contains("unsafe-inline"));
+    }
+}
@@ -0,0 +1,118 @@
+//! sitemap.xml and robots.txt, generated from webc.toml
+//!
+//! ```toml
+//! [site]
+//! url = "https://example.com"
+//!
+//! [robots]
+//! disallow = ["/admin"]
+//!
+//! [sitemap."/about"]
+//! lastmod = "2026-01-10"
+//! priority = 0.8
+//! ```
+//!
+//! The sitemap lists the routes of `App.routes` when the SPA router serves
+//! them, otherwise every page, under `site.url`. A `robots.txt` in `public/`
+//! replaces the generated one.
+
+use crate::codegen::codegen_html::html_escape;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct RobotsSection {
+    allow: Vec<String>,
+    disallow: Vec<String>,
+}
+
+/// Overrides for one sitemap entry, keyed by its path
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct SitemapEntry {
+    lastmod: Option<String>,
+    priority: Option<f64>,
+}
+
+/// `paths` are absolute paths on the site (`/about`)
+pub fn sitemap(site_url: &str, paths: &[String], overrides: &BTreeMap<String, SitemapEntry>) -> Result<String, String> {
+    let overrides: BTreeMap<String, &SitemapEntry> = overrides.iter().map(|(path, entry)| (normalize(path), entry)).collect();
+    let base = site_url.trim_end_matches('/');
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str("<urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n");
+    for path in paths {
+        xml.push_str(&format!("  <url>\n    <loc>{}</loc>\n", html_escape(&format!("{}{}", base, path))));
+        if let Some(entry) = overrides.get(&normalize(path)) {
+            if let Some(lastmod) = &entry.lastmod {
+                xml.push_str(&format!("    <lastmod>{}</lastmod>\n", html_escape(lastmod)));
+            }
+            if let Some(priority) = entry.priority {
+                if !(0.0..=1.0).contains(&priority) {
+                    return Err(format!("[sitemap.\"{}\"] priority must be between 0.0 and 1.0, got {}", path, priority));
+                }
+                xml.push_str(&format!("    <priority>{}</priority>\n", priority));
+            }
+        }
+        xml.push_str("  </url>\n");
+    }
+    xml.push_str("</urlset>\n");
+    Ok(xml)
+}
+
+/// Override keys that name none of `paths`
+pub fn unmatched_overrides<'a>(paths: &[String], overrides: &'a BTreeMap<String, SitemapEntry>) -> Vec<&'a str> {
+    overrides.keys()
+        .filter(|key| !paths.iter().any(|path| normalize(path) == normalize(key)))
+        .map(String::as_str)
+        .collect()
+}
+
+// `/about/` and `about` are the entry of `/about`
+fn normalize(path: &str) -> String {
+    format!("/{}", path.trim_matches('/'))
+}
+
+/// Everything is allowed unless `[robots]` says otherwise; the sitemap is
+/// announced when the site URL is known
+pub fn robots(robots: &RobotsSection, site_url: Option<&str>) -> String {
+    let mut text = String::from("User-agent: *\n");
+    for path in &robots.allow {
+        text.push_str(&format!("Allow: {}\n", path));
+    }
+    for path in &robots.disallow {
+        text.push_str(&format!("Disallow: {}\n", path));
+    }
+    if robots.allow.is_empty() && robots.disallow.is_empty() {
+        text.push_str("Disallow:\n");
+    }
+    if let Some(url) = site_url {
+        text.push_str(&format!("\nSitemap: {}/sitemap.xml\n", url.trim_end_matches('/')));
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sitemap_uses_absolute_urls_and_overrides() {
+        let overrides: BTreeMap<String, SitemapEntry> = toml::from_str("[\"/about/\"]\nlastmod = \"2026-01-10\"\npriority = 0.8\n").expect("toml ok");
+        let paths = vec!["/".to_string(), "/about".to_string()];
+        let xml = sitemap("https://example.com/", &paths, &overrides).expect("sitemap");
+        assert!(xml.contains("  <url>\n    <loc>https://example.com/</loc>\n  </url>\n"));
+        assert!(xml.contains("<loc>https://example.com/about</loc>\n    <lastmod>2026-01-10</lastmod>\n    <priority>0.8</priority>\n"));
+
+        assert!(unmatched_overrides(&paths, &overrides).is_empty());
+
+        let invalid: BTreeMap<String, SitemapEntry> = toml::from_str("[\"/\"]\npriority = 2.0\n").expect("toml ok");
+        assert!(sitemap("https://example.com", &paths, &invalid).is_err());
+    }
+
+    #[test]
+    fn robots_rules_point_at_the_sitemap() {
+        let rules: RobotsSection = toml::from_str("disallow = [\"/admin\"]").expect("toml ok");
+        assert_eq!(robots(&rules, Some("https://example.com/")), "User-agent: *\nDisallow: /admin\n\nSitemap: https://example.com/sitemap.xml\n");
+    }
+}
@@ -29,10 +29,12 @@ struct ThemeData {
 pub fn load_theme(theme_path: &str) -> Result<Theme, String> {
     let content = fs::read_to_string(theme_path)
         .map_err(|e| format!("Failed to read theme file {}: {}", theme_path, e))?;
-    
-    let config: ThemeConfig = toml::from_str(&content)
-        .map_err(|e| format!("Failed to parse theme file {}: {}", theme_path, e))?;
-    
+    parse_theme(&content).map_err(|e| format!("Failed to parse theme file {}: {}", theme_path, e))
+}
+
+pub fn parse_theme(content: &str) -> Result<Theme, String> {
+    let config: ThemeConfig = toml::from_str(content).map_err(|e| e.to_string())?;
+
     Ok(Theme {
         name: config.theme.name,
         colors: config.theme.colors,
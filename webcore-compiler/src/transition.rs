@@ -0,0 +1,231 @@
+//! `transition:fade`, `transition:slide={ duration: 200 }` and `animate:flip`
+//!
+//! Elements at the top of a runtime `if`/`for` block carry the directive as
+//! data attributes; the runtime adds `wc-<name>-enter-*` / `wc-<name>-leave-*`
+//! classes and keeps a leaving element until its transition ends. Component
+//! `keyframes` become transitions played forwards on enter and reversed on leave.
+
+use crate::ast::*;
+use crate::checker::Diagnostic;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+
+pub const BUILTIN_TRANSITIONS: &[&str] = &["fade", "slide"];
+pub const ANIMATIONS: &[&str] = &["flip"];
+const OPTIONS: &[&str] = &["duration", "delay", "easing"];
+
+/// `duration: 200, easing: "ease-out"` → `{"duration":200,"easing":"ease-out"}`
+pub fn parse_options(expr: &str) -> Result<Map<String, Value>, String> {
+    let mut options = Map::new();
+    for pair in split_pairs(expr).into_iter().map(str::trim).filter(|p| !p.is_empty()) {
+        let (key, value) = pair.split_once(':')
+            .ok_or_else(|| format!("expected `name: value` in transition options, found '{}'", pair))?;
+        let key = key.trim();
+        if !OPTIONS.contains(&key) {
+            return Err(format!("unknown transition option '{}' (expected {})", key, OPTIONS.join(", ")));
+        }
+        let value = value.trim();
+        let value = value.parse::<i64>().map(Value::from)
+            .or_else(|_| value.parse::<f64>().map(Value::from))
+            .unwrap_or_else(|_| Value::String(value.trim_matches(['"', '\'']).to_string()));
+        options.insert(key.to_string(), value);
+    }
+    Ok(options)
+}
+
+// Commas inside parentheses or quotes belong to the value: `cubic-bezier(0.1, 0.7, 1, 0.1)`
+fn split_pairs(expr: &str) -> Vec<&str> {
+    let mut pairs = Vec::new();
+    let mut depth = 0usize;
+    let mut quote = None;
+    let mut start = 0;
+    for (i, c) in expr.char_indices() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '(') => depth += 1,
+            (None, ')') => depth = depth.saturating_sub(1),
+            (None, ',') if depth == 0 => {
+                pairs.push(&expr[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    pairs.push(&expr[start..]);
+    pairs
+}
+
+/// Keyframes declared by every component, by name
+pub fn keyframes(document: &WebCoreDocument) -> Vec<&Keyframes> {
+    let mut components: Vec<&Component> = document.components.values().collect();
+    components.sort_by(|a, b| a.name.cmp(&b.name));
+    components.iter().flat_map(|c| c.keyframes.iter()).collect()
+}
+
+/// Stylesheet for the built-in transitions and the components' keyframes, or
+/// `None` when the project uses neither
+pub fn generate_css(document: &WebCoreDocument) -> Option<String> {
+    let custom = keyframes(document);
+    let mut directives = Vec::new();
+    for_each_view(document, |elements, _| collect_directives(elements, &mut directives));
+    if custom.is_empty() && directives.is_empty() {
+        return None;
+    }
+
+    let mut css = String::new();
+    css.push_str("[data-webcore-transition] {\n  --wc-duration: 300ms;\n  --wc-delay: 0ms;\n  --wc-easing: ease;\n}\n");
+    css.push_str(".wc-fade-enter-active, .wc-fade-leave-active {\n  transition: opacity var(--wc-duration) var(--wc-easing) var(--wc-delay);\n}\n");
+    css.push_str(".wc-fade-enter-from, .wc-fade-leave-to {\n  opacity: 0;\n}\n");
+    css.push_str(".wc-slide-enter-active, .wc-slide-leave-active {\n  overflow: hidden;\n  transition: opacity var(--wc-duration) var(--wc-easing) var(--wc-delay), transform var(--wc-duration) var(--wc-easing) var(--wc-delay);\n}\n");
+    css.push_str(".wc-slide-enter-from, .wc-slide-leave-to {\n  opacity: 0;\n  transform: translateY(-0.75em);\n}\n");
+    for frames in custom {
+        css.push_str(&format!("@keyframes {} {{\n{}\n}}\n", frames.name, frames.body.trim()));
+        css.push_str(&format!(".wc-{}-enter-active {{\n  animation: {} var(--wc-duration) var(--wc-easing) var(--wc-delay) both;\n}}\n", frames.name, frames.name));
+        css.push_str(&format!(".wc-{}-leave-active {{\n  animation: {} var(--wc-duration) var(--wc-easing) var(--wc-delay) reverse both;\n}}\n", frames.name, frames.name));
+    }
+    Some(css)
+}
+
+/// Unknown transitions or options, duplicate keyframes, `animate:` outside a `for`
+/// and directives below the top of their block, which the runtime never plays
+pub fn check_directives(document: &WebCoreDocument) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    let mut declared: HashMap<&str, (&str, Span)> = HashMap::new();
+    let mut components: Vec<&Component> = document.components.values().collect();
+    components.sort_by(|a, b| a.name.cmp(&b.name));
+    for component in components {
+        for frames in &component.keyframes {
+            if BUILTIN_TRANSITIONS.contains(&frames.name.as_str()) {
+                diagnostics.push(Diagnostic::error(&component.file, frames.span,
+                    format!("keyframes '{}' would replace the built-in transition of the same name", frames.name)));
+            } else if let Some((file, span)) = declared.get(frames.name.as_str()) {
+                let first = Diagnostic::error(file, *span, "").location();
+                diagnostics.push(Diagnostic::error(&component.file, frames.span,
+                    format!("keyframes '{}' are already defined at {}", frames.name, first)));
+            } else {
+                declared.insert(&frames.name, (&component.file, frames.span));
+            }
+        }
+    }
+
+    for_each_view(document, |elements, file| {
+        let mut directives = Vec::new();
+        collect_directives(elements, &mut directives);
+        for directive in directives {
+            let (kind, name) = directive.attribute.name.split_once(':').unwrap_or_default();
+            let span = directive.attribute.span;
+            match kind {
+                "transition" if !BUILTIN_TRANSITIONS.contains(&name) && !declared.contains_key(name) => {
+                    diagnostics.push(Diagnostic::error(file, span, format!(
+                        "unknown transition '{}' (built-in: {}; or declare `keyframes {}` in a component style)",
+                        name, BUILTIN_TRANSITIONS.join(", "), name)));
+                }
+                "animate" if !ANIMATIONS.contains(&name) => {
+                    diagnostics.push(Diagnostic::error(file, span, format!("unknown animation '{}' (expected {})", name, ANIMATIONS.join(", "))));
+                }
+                "animate" if !directive.in_loop => {
+                    diagnostics.push(Diagnostic::warning(file, span, format!("animate:{} only has an effect inside a `for` block", name)));
+                }
+                _ if !directive.at_top => {
+                    diagnostics.push(Diagnostic::warning(file, span, format!(
+                        "{} only has an effect on an element directly inside an `if` or `for` block", directive.attribute.name)));
+                }
+                _ => {}
+            }
+            if let AttributeValue::Expression(expr) = &directive.attribute.value {
+                if let Err(message) = parse_options(expr) {
+                    diagnostics.push(Diagnostic::error(file, span, message));
+                }
+            }
+        }
+    });
+    diagnostics.sort_by(|a, b| (&a.file, a.span.start).cmp(&(&b.file, b.span.start)));
+    diagnostics
+}
+
+struct Directive<'a> {
+    attribute: &'a Attribute,
+    in_loop: bool,
+    /// On an element the block itself adds and removes
+    at_top: bool,
+}
+
+pub fn is_directive(attribute: &Attribute) -> bool {
+    attribute.name.starts_with("transition:") || attribute.name.starts_with("animate:")
+}
+
+fn for_each_view<'a>(document: &'a WebCoreDocument, mut visit: impl FnMut(&'a [Element], &'a str)) {
+    for layout in document.layouts.values() {
+        visit(&layout.content, &layout.file);
+    }
+    for page in document.pages.values() {
+        visit(&page.content, &page.file);
+    }
+    for component in document.components.values() {
+        visit(&component.view, &component.file);
+    }
+}
+
+fn collect_directives<'a>(elements: &'a [Element], out: &mut Vec<Directive<'a>>) {
+    walk(elements, false, false, out);
+}
+
+fn walk<'a>(elements: &'a [Element], in_loop: bool, at_top: bool, out: &mut Vec<Directive<'a>>) {
+    for element in elements {
+        match element {
+            Element::Tag { attributes, content, .. } | Element::Component { attributes, content, .. } => {
+                out.extend(attributes.iter().filter(|a| is_directive(a)).map(|attribute| Directive { attribute, in_loop, at_top }));
+                walk(content, in_loop, false, out);
+            }
+            Element::For { content, .. } => walk(content, true, true, out),
+            Element::If { content, else_content, .. } => {
+                walk(content, in_loop, true, out);
+                walk(else_content, in_loop, true, out);
+            }
+            Element::Text { .. } | Element::Slot { .. } | Element::Interpolation { .. } | Element::Raw(_) => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_webc_file;
+
+    #[test]
+    fn directives_are_checked_and_keyframes_become_transitions() {
+        let src = r#"
+component Toast {
+  state { open: Boolean = false items: List = [] }
+  view {
+    if open { div transition:slide={ duration: 200, easing: "ease-out" } "Saved" }
+    if open { p transition:wobble "Oops" }
+    if open { p transition:spin={ speed: 2 } "?" }
+    if open { div { p transition:fade "nested" } }
+    for item in items { li animate:flip transition:fade "{item}" }
+    p animate:flip "static"
+  }
+  style {
+    keyframes wobble { 0% { transform: rotate(-3deg); } 100% { transform: none; } }
+  }
+}
+"#;
+        let doc = parse_webc_file(src, "src/components/toast.webc").expect("parse ok");
+        let messages: Vec<String> = check_directives(&doc).iter().map(|d| d.to_string()).collect();
+        assert_eq!(messages.len(), 4, "{:#?}", messages);
+        assert!(messages[0].starts_with("error: unknown transition 'spin'"));
+        assert!(messages[1].starts_with("error: unknown transition option 'speed'"));
+        assert!(messages[2].starts_with("warning: transition:fade only has an effect on an element directly inside an `if` or `for` block"));
+        assert!(messages[3].starts_with("warning: animate:flip only has an effect inside a `for` block"));
+
+        let css = generate_css(&doc).expect("transitions are used");
+        assert!(css.contains("@keyframes wobble {\n0% { transform: rotate(-3deg); } 100% { transform: none; }\n}"));
+        assert!(css.contains(".wc-wobble-leave-active {\n  animation: wobble var(--wc-duration) var(--wc-easing) var(--wc-delay) reverse both;"));
+        assert_eq!(parse_options("duration:200,easing:\"ease-out\"").expect("options"),
+            serde_json::json!({ "duration": 200, "easing": "ease-out" }).as_object().cloned().expect("object"));
+        assert_eq!(parse_options("easing: cubic-bezier(0.1, 0.7, 1, 0.1), delay: 50").expect("options"),
+            serde_json::json!({ "easing": "cubic-bezier(0.1, 0.7, 1, 0.1)", "delay": 50 }).as_object().cloned().expect("object"));
+    }
+}